@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER clear_revoked_assignments ON user_lists;
+DROP FUNCTION clear_revoked_assignments();
+
+ALTER TABLE items
+    DROP COLUMN assignee_id;
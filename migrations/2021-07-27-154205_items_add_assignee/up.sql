@@ -0,0 +1,19 @@
+-- Your SQL goes here
+ALTER TABLE items
+    ADD COLUMN assignee_id INT
+        REFERENCES users(id)
+        ON DELETE SET NULL;
+
+-- Clears any assignments in a list once the assignee loses access to it.
+CREATE OR REPLACE FUNCTION clear_revoked_assignments() RETURNS trigger AS $$
+BEGIN
+    UPDATE items
+    SET assignee_id = NULL
+    WHERE list_id = OLD.list_id
+      AND assignee_id = OLD.user_id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER clear_revoked_assignments AFTER DELETE ON user_lists
+    FOR EACH ROW EXECUTE PROCEDURE clear_revoked_assignments();
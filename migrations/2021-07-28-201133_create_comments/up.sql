@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE comments (
+    comment_id SERIAL PRIMARY KEY,
+    item_id INT NOT NULL
+        REFERENCES items (item_id)
+        ON DELETE CASCADE,
+    author_id INT NOT NULL
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited_at TIMESTAMPTZ
+);
+
+CREATE INDEX comments_item_id_idx ON comments (item_id);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE attachments (
+    attachment_id SERIAL PRIMARY KEY,
+    item_id INT NOT NULL
+        REFERENCES items (item_id)
+        ON DELETE CASCADE,
+    uploader_id INT NOT NULL
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX attachments_item_id_idx ON attachments (item_id);
+CREATE INDEX attachments_uploader_id_idx ON attachments (uploader_id);
@@ -0,0 +1,36 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER set_updated_at ON attachments;
+ALTER TABLE attachments
+    DROP COLUMN updated_at;
+
+DROP TRIGGER set_updated_at ON comments;
+ALTER TABLE comments
+    DROP COLUMN updated_at;
+
+DROP TRIGGER set_completed_at ON items;
+DROP FUNCTION set_item_completed_at();
+DROP TRIGGER set_updated_at ON items;
+ALTER TABLE items
+    DROP COLUMN created_at,
+    DROP COLUMN updated_at,
+    DROP COLUMN completed_at;
+
+DROP TRIGGER set_updated_at ON user_lists;
+ALTER TABLE user_lists
+    DROP COLUMN created_at,
+    DROP COLUMN updated_at;
+
+DROP TRIGGER set_updated_at ON lists;
+ALTER TABLE lists
+    ADD COLUMN date_created DATE;
+UPDATE lists
+SET date_created = created_at::DATE;
+ALTER TABLE lists
+    ALTER COLUMN date_created SET NOT NULL,
+    DROP COLUMN created_at,
+    DROP COLUMN updated_at;
+
+DROP TRIGGER set_updated_at ON users;
+ALTER TABLE users
+    DROP COLUMN created_at,
+    DROP COLUMN updated_at;
@@ -0,0 +1,57 @@
+-- Your SQL goes here
+ALTER TABLE users
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+SELECT diesel_manage_updated_at('users');
+
+-- Lists already knew the day they were created, so keep that instead of the migration time
+ALTER TABLE lists
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE lists
+SET created_at = date_created::TIMESTAMPTZ,
+    updated_at = date_created::TIMESTAMPTZ;
+ALTER TABLE lists
+    DROP COLUMN date_created;
+SELECT diesel_manage_updated_at('lists');
+
+ALTER TABLE user_lists
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+SELECT diesel_manage_updated_at('user_lists');
+
+ALTER TABLE items
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN completed_at TIMESTAMPTZ;
+UPDATE items
+SET completed_at = NOW()
+WHERE finished;
+SELECT diesel_manage_updated_at('items');
+
+-- Keeps `completed_at` in step with `finished`, however the item gets changed.
+CREATE OR REPLACE FUNCTION set_item_completed_at() RETURNS trigger AS $$
+BEGIN
+    IF NOT NEW.finished THEN
+        NEW.completed_at := NULL;
+    ELSIF TG_OP = 'INSERT' OR NOT OLD.finished THEN
+        NEW.completed_at := current_timestamp;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_completed_at BEFORE INSERT OR UPDATE ON items
+    FOR EACH ROW EXECUTE PROCEDURE set_item_completed_at();
+
+ALTER TABLE comments
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE comments
+SET updated_at = COALESCE(edited_at, created_at);
+SELECT diesel_manage_updated_at('comments');
+
+ALTER TABLE attachments
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE attachments
+SET updated_at = created_at;
+SELECT diesel_manage_updated_at('attachments');
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER bump_version ON items;
+DROP TRIGGER bump_version ON lists;
+DROP FUNCTION bump_version();
+
+ALTER TABLE items
+    DROP COLUMN version;
+ALTER TABLE lists
+    DROP COLUMN version;
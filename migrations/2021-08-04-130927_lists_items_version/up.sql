@@ -0,0 +1,20 @@
+-- Your SQL goes here
+ALTER TABLE lists
+    ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE items
+    ADD COLUMN version INT NOT NULL DEFAULT 1;
+
+-- Bumps the `version` of a row whenever it actually changes, so that clients can detect stale copies.
+CREATE OR REPLACE FUNCTION bump_version() RETURNS trigger AS $$
+BEGIN
+    IF NEW IS DISTINCT FROM OLD THEN
+        NEW.version := OLD.version + 1;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER bump_version BEFORE UPDATE ON lists
+    FOR EACH ROW EXECUTE PROCEDURE bump_version();
+CREATE TRIGGER bump_version BEFORE UPDATE ON items
+    FOR EACH ROW EXECUTE PROCEDURE bump_version();
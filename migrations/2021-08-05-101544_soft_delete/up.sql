@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- Deleted lists and items are kept in the trash of the user who deleted them until they are restored
+-- or purged. Items of a trashed list stay hidden along with it.
+ALTER TABLE lists
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by INT REFERENCES users (id) ON DELETE SET NULL;
+ALTER TABLE items
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by INT REFERENCES users (id) ON DELETE SET NULL;
+
+CREATE INDEX lists_deleted_at_idx ON lists (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX items_deleted_at_idx ON items (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Archiving is per user, so it lives on the membership rather than the list itself
+ALTER TABLE user_lists
+    ADD COLUMN archived_at TIMESTAMPTZ;
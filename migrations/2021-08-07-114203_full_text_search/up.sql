@@ -0,0 +1,34 @@
+-- Your SQL goes here
+ALTER TABLE lists
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', name), 'A') ||
+        setweight(to_tsvector('english', COALESCE(description, '')), 'B')
+    ) STORED;
+CREATE INDEX lists_search_vector_idx ON lists USING GIN (search_vector);
+
+ALTER TABLE items
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (to_tsvector('english', description)) STORED;
+CREATE INDEX items_search_vector_idx ON items USING GIN (search_vector);
+
+-- Generated columns aren't computed yet in BEFORE triggers, so they would make every update look like a
+-- change. Compare the rows without them instead.
+CREATE OR REPLACE FUNCTION diesel_set_updated_at() RETURNS trigger AS $$
+BEGIN
+    IF (
+        to_jsonb(NEW) - 'search_vector' IS DISTINCT FROM to_jsonb(OLD) - 'search_vector' AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at := current_timestamp;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION bump_version() RETURNS trigger AS $$
+BEGIN
+    IF to_jsonb(NEW) - 'search_vector' IS DISTINCT FROM to_jsonb(OLD) - 'search_vector' THEN
+        NEW.version := OLD.version + 1;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+-- Tags belong to a single user, who can put them on any item in the lists they can access. Other members
+-- of those lists don't see them.
+CREATE TABLE tags (
+    tag_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL DEFAULT '#808080'
+        CHECK (color ~ '^#[0-9a-fA-F]{6}$'),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+SELECT diesel_manage_updated_at('tags');
+
+CREATE TABLE item_tags (
+    item_id INT NOT NULL
+        REFERENCES items (item_id)
+        ON DELETE CASCADE,
+    tag_id INT NOT NULL
+        REFERENCES tags (tag_id)
+        ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (item_id, tag_id)
+);
+
+CREATE INDEX item_tags_tag_id_idx ON item_tags (tag_id);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE smart_lists;
+ALTER TABLE users DROP COLUMN timezone;
+DROP INDEX items_due_at_idx;
+ALTER TABLE items DROP COLUMN due_at;
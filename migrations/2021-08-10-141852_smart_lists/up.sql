@@ -0,0 +1,20 @@
+-- Your SQL goes here
+ALTER TABLE items ADD COLUMN due_at TIMESTAMPTZ;
+CREATE INDEX items_due_at_idx ON items (due_at) WHERE deleted_at IS NULL;
+
+-- The IANA time zone of each user, which decides when their days start for relative due dates
+ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+-- Named item filters saved by a user, stored as the JSON expression they gave
+CREATE TABLE smart_lists (
+    smart_list_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    filter JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+SELECT diesel_manage_updated_at('smart_lists');
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE items ADD COLUMN priority TEXT
+    CHECK (priority IN ('low', 'medium', 'high'));
+
+-- How an item repeats, as a small subset of an iCalendar RRULE, eg `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO`
+ALTER TABLE items ADD COLUMN recurrence TEXT
+    CHECK (recurrence ~ '^FREQ=(DAILY|WEEKLY|MONTHLY|YEARLY)(;INTERVAL=[1-9][0-9]*)?(;BYDAY=(MO|TU|WE|TH|FR|SA|SU))?$');
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- The secret token in the URL of each user's calendar feed, which is regenerated to revoke the old URL
+CREATE TABLE calendar_feeds (
+    user_id INT PRIMARY KEY
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX items_list_id_dav_name_idx;
+ALTER TABLE items DROP COLUMN dav_name;
+ALTER TABLE items DROP COLUMN ical_uid;
+
+DROP TABLE app_passwords;
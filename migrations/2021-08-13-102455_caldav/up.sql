@@ -0,0 +1,20 @@
+-- Your SQL goes here
+-- Passwords that users make for apps that can only log in with HTTP Basic, such as CalDAV clients. Only a
+-- SHA-256 hash of each is kept, since they are long random tokens rather than something a person chose.
+CREATE TABLE app_passwords (
+    app_password_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    password_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ
+);
+
+-- The UID and resource name that a CalDAV client gave an item when it created it, which have to be kept so
+-- that the client finds the item again. Items made any other way have neither.
+ALTER TABLE items ADD COLUMN ical_uid TEXT;
+ALTER TABLE items ADD COLUMN dav_name TEXT;
+
+CREATE INDEX items_list_id_dav_name_idx ON items (list_id, dav_name);
@@ -0,0 +1,42 @@
+-- Your SQL goes here
+-- URLs that list owners register to be sent the changes to a list. `events` names the types of events to send,
+-- or is empty to send all of them, and the secret signs each delivery. A webhook that keeps failing is disabled.
+CREATE TABLE webhooks (
+    webhook_id SERIAL PRIMARY KEY,
+    list_id INT NOT NULL
+        REFERENCES lists (list_id)
+        ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT[] NOT NULL DEFAULT '{}',
+    is_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    consecutive_failures INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    disabled_at TIMESTAMPTZ
+);
+
+SELECT diesel_manage_updated_at('webhooks');
+
+CREATE INDEX webhooks_list_id_idx ON webhooks (list_id);
+
+-- The outbox of events to send to webhooks, which is also the log of each attempt. A delivery is `pending` until
+-- it succeeds, or until it fails too many times or its webhook is disabled, and is sent again at `next_attempt_at`.
+CREATE TABLE webhook_deliveries (
+    delivery_id SERIAL PRIMARY KEY,
+    webhook_id INT NOT NULL
+        REFERENCES webhooks (webhook_id)
+        ON DELETE CASCADE,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMPTZ,
+    response_status INT,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_deliveries_pending_idx ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id, delivery_id);
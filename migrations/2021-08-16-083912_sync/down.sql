@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE sync_mutations;
+
+DROP TRIGGER record_sync_change ON user_lists;
+DROP TRIGGER record_sync_change ON items;
+DROP TRIGGER record_sync_change ON lists;
+
+DROP FUNCTION record_membership_change();
+DROP FUNCTION record_item_change();
+DROP FUNCTION record_list_change();
+DROP FUNCTION record_sync_change(TEXT, INT, INT, BOOLEAN);
+
+DROP TABLE sync_changes;
@@ -0,0 +1,101 @@
+-- Your SQL goes here
+-- The latest change to every list, item and membership, which clients sync from. Each object has a row for every list
+-- it has been in, so that deleting it or moving an item out of a list leaves a tombstone behind in the old list.
+-- `changed_xid` is the transaction that made the change, which sync tokens are compared against. A membership is
+-- identified by its user.
+CREATE TABLE sync_changes (
+    object_type TEXT NOT NULL,
+    object_id INT NOT NULL,
+    list_id INT NOT NULL,
+    is_deleted BOOLEAN NOT NULL,
+    changed_xid BIGINT NOT NULL DEFAULT txid_current(),
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (object_type, object_id, list_id)
+);
+
+CREATE INDEX sync_changes_list_id_idx ON sync_changes (list_id, changed_xid);
+CREATE INDEX sync_changes_tombstones_idx ON sync_changes (changed_at) WHERE is_deleted;
+
+CREATE FUNCTION record_sync_change(_object_type TEXT, _object_id INT, _list_id INT, _is_deleted BOOLEAN) RETURNS VOID AS $$
+    INSERT INTO sync_changes (object_type, object_id, list_id, is_deleted)
+    VALUES (_object_type, _object_id, _list_id, _is_deleted)
+    ON CONFLICT (object_type, object_id, list_id) DO UPDATE
+        SET is_deleted = EXCLUDED.is_deleted, changed_xid = txid_current(), changed_at = NOW();
+$$ LANGUAGE sql;
+
+-- Lists in the trash are deleted as far as clients are concerned. Members drop everything in a list when it goes to
+-- the trash, so restoring it changes everything in it again.
+CREATE FUNCTION record_list_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('list', OLD.list_id, OLD.list_id, TRUE);
+        RETURN OLD;
+    END IF;
+
+    PERFORM record_sync_change('list', NEW.list_id, NEW.list_id, NEW.deleted_at IS NOT NULL);
+
+    IF TG_OP = 'UPDATE' AND OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+        UPDATE sync_changes SET changed_xid = txid_current(), changed_at = NOW()
+        WHERE list_id = NEW.list_id AND NOT is_deleted;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION record_item_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('item', OLD.item_id, OLD.list_id, TRUE);
+        RETURN OLD;
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND OLD.list_id <> NEW.list_id THEN
+        PERFORM record_sync_change('item', OLD.item_id, OLD.list_id, TRUE);
+    END IF;
+
+    PERFORM record_sync_change('item', NEW.item_id, NEW.list_id, NEW.deleted_at IS NOT NULL);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION record_membership_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('membership', OLD.user_id, OLD.list_id, TRUE);
+        RETURN OLD;
+    END IF;
+
+    PERFORM record_sync_change('membership', NEW.user_id, NEW.list_id, FALSE);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_sync_change AFTER INSERT OR UPDATE OR DELETE ON lists
+    FOR EACH ROW EXECUTE PROCEDURE record_list_change();
+CREATE TRIGGER record_sync_change AFTER INSERT OR UPDATE OR DELETE ON items
+    FOR EACH ROW EXECUTE PROCEDURE record_item_change();
+CREATE TRIGGER record_sync_change AFTER INSERT OR UPDATE OR DELETE ON user_lists
+    FOR EACH ROW EXECUTE PROCEDURE record_membership_change();
+
+INSERT INTO sync_changes (object_type, object_id, list_id, is_deleted)
+SELECT 'list', list_id, list_id, deleted_at IS NOT NULL FROM lists
+UNION ALL
+SELECT 'item', item_id, list_id, deleted_at IS NOT NULL FROM items
+UNION ALL
+SELECT 'membership', user_id, list_id, FALSE FROM user_lists;
+
+-- The offline changes that each user has already synced, by the id that their client gave each one, so that sending
+-- a batch again doesn't apply it twice. Later changes can refer to the list and item that a change created by its id.
+CREATE TABLE sync_mutations (
+    user_id INT NOT NULL
+        REFERENCES users (id)
+        ON DELETE CASCADE,
+    client_id TEXT NOT NULL,
+    list_id INT NOT NULL,
+    item_id INT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, client_id)
+);
+
+CREATE INDEX sync_mutations_created_at_idx ON sync_mutations (created_at);
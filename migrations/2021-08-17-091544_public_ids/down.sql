@@ -0,0 +1,101 @@
+-- This file should undo anything in `up.sql`
+CREATE FUNCTION serial_list_filter(_filter JSONB) RETURNS JSONB AS $$
+BEGIN
+    CASE jsonb_typeof(_filter)
+        WHEN 'object' THEN
+            RETURN (SELECT jsonb_object_agg(key, CASE
+                        WHEN key = 'list' AND jsonb_typeof(value) = 'string' THEN to_jsonb(COALESCE(
+                            (SELECT list_id FROM lists WHERE uuid::TEXT = value #>> '{}'), 0))
+                        ELSE serial_list_filter(value)
+                    END) FROM jsonb_each(_filter));
+        WHEN 'array' THEN
+            RETURN (SELECT COALESCE(jsonb_agg(serial_list_filter(value) ORDER BY position), '[]')
+                    FROM jsonb_array_elements(_filter) WITH ORDINALITY AS elements(value, position));
+        ELSE
+            RETURN _filter;
+    END CASE;
+END;
+$$ LANGUAGE plpgsql;
+
+UPDATE smart_lists SET filter = serial_list_filter(filter);
+
+DROP FUNCTION serial_list_filter(JSONB);
+
+ALTER TABLE sync_mutations ADD COLUMN list_id INT;
+ALTER TABLE sync_mutations ADD COLUMN item_id INT;
+
+UPDATE sync_mutations SET list_id = lists.list_id FROM lists WHERE lists.uuid = sync_mutations.list_uuid;
+UPDATE sync_mutations SET item_id = items.item_id FROM items WHERE items.uuid = sync_mutations.item_uuid;
+
+DELETE FROM sync_mutations WHERE list_id IS NULL OR (item_id IS NULL AND item_uuid IS NOT NULL);
+
+ALTER TABLE sync_mutations DROP COLUMN list_uuid;
+ALTER TABLE sync_mutations DROP COLUMN item_uuid;
+ALTER TABLE sync_mutations ALTER COLUMN list_id SET NOT NULL;
+
+DROP FUNCTION record_sync_change(TEXT, INT, UUID, INT, UUID, BOOLEAN);
+
+CREATE FUNCTION record_sync_change(_object_type TEXT, _object_id INT, _list_id INT, _is_deleted BOOLEAN) RETURNS VOID AS $$
+    INSERT INTO sync_changes (object_type, object_id, list_id, is_deleted)
+    VALUES (_object_type, _object_id, _list_id, _is_deleted)
+    ON CONFLICT (object_type, object_id, list_id) DO UPDATE
+        SET is_deleted = EXCLUDED.is_deleted, changed_xid = txid_current(), changed_at = NOW();
+$$ LANGUAGE sql;
+
+CREATE OR REPLACE FUNCTION record_list_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('list', OLD.list_id, OLD.list_id, TRUE);
+        RETURN OLD;
+    END IF;
+
+    PERFORM record_sync_change('list', NEW.list_id, NEW.list_id, NEW.deleted_at IS NOT NULL);
+
+    IF TG_OP = 'UPDATE' AND OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+        UPDATE sync_changes SET changed_xid = txid_current(), changed_at = NOW()
+        WHERE list_id = NEW.list_id AND NOT is_deleted;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_item_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('item', OLD.item_id, OLD.list_id, TRUE);
+        RETURN OLD;
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND OLD.list_id <> NEW.list_id THEN
+        PERFORM record_sync_change('item', OLD.item_id, OLD.list_id, TRUE);
+    END IF;
+
+    PERFORM record_sync_change('item', NEW.item_id, NEW.list_id, NEW.deleted_at IS NOT NULL);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_membership_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('membership', OLD.user_id, OLD.list_id, TRUE);
+        RETURN OLD;
+    END IF;
+
+    PERFORM record_sync_change('membership', NEW.user_id, NEW.list_id, FALSE);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE sync_changes DROP COLUMN object_uuid;
+ALTER TABLE sync_changes DROP COLUMN list_uuid;
+
+DROP TRIGGER set_item_list_uuid ON items;
+DROP FUNCTION set_item_list_uuid();
+
+ALTER TABLE items DROP COLUMN list_uuid;
+ALTER TABLE items DROP COLUMN uuid;
+ALTER TABLE lists DROP COLUMN uuid;
+
+DROP EXTENSION IF EXISTS pgcrypto;
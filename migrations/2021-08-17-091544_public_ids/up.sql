@@ -0,0 +1,149 @@
+-- Your SQL goes here
+-- `gen_random_uuid` is only built into Postgres 13 and later, and comes from pgcrypto before that.
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+-- The ids that lists and items are known by outside of the database, so that their serial keys are never shown. Clients
+-- may pick the id of a new list or item themselves, which lets them refer to it before the server has seen it.
+ALTER TABLE lists ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+ALTER TABLE items ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+
+-- Every item also carries the public id of its list, so that it can be shown without looking up the list. It's kept
+-- up to date whenever the item is added to or moved between lists.
+ALTER TABLE items ADD COLUMN list_uuid UUID;
+
+-- Calendar apps already know the items that weren't made through CalDAV by names and UIDs made from their serial keys,
+-- which are kept for them so that they don't see every item replaced. Only items made from now on use the public id.
+ALTER TABLE items DISABLE TRIGGER USER;
+UPDATE items SET list_uuid = lists.uuid FROM lists WHERE lists.list_id = items.list_id;
+UPDATE items SET ical_uid = COALESCE(ical_uid, 'item-' || item_id || '@todo-backend'),
+                 dav_name = COALESCE(dav_name, 'item-' || item_id || '.ics');
+ALTER TABLE items ENABLE TRIGGER USER;
+
+ALTER TABLE items ALTER COLUMN list_uuid SET NOT NULL;
+
+CREATE FUNCTION set_item_list_uuid() RETURNS trigger AS $$
+BEGIN
+    SELECT uuid INTO NEW.list_uuid FROM lists WHERE list_id = NEW.list_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_item_list_uuid BEFORE INSERT OR UPDATE OF list_id ON items
+    FOR EACH ROW EXECUTE PROCEDURE set_item_list_uuid();
+
+-- Clients only know objects by their public ids, so tombstones have to remember them after the object is gone. The
+-- `object_uuid` of a membership is null, since it's identified by its user. Tombstones of objects that are already
+-- gone can't be told apart any more, and are dropped.
+ALTER TABLE sync_changes ADD COLUMN object_uuid UUID;
+ALTER TABLE sync_changes ADD COLUMN list_uuid UUID;
+
+UPDATE sync_changes SET list_uuid = lists.uuid FROM lists WHERE lists.list_id = sync_changes.list_id;
+UPDATE sync_changes SET object_uuid = list_uuid WHERE object_type = 'list';
+UPDATE sync_changes SET object_uuid = items.uuid FROM items
+WHERE object_type = 'item' AND items.item_id = sync_changes.object_id;
+
+DELETE FROM sync_changes WHERE list_uuid IS NULL OR (object_uuid IS NULL AND object_type <> 'membership');
+
+ALTER TABLE sync_changes ALTER COLUMN list_uuid SET NOT NULL;
+
+DROP FUNCTION record_sync_change(TEXT, INT, INT, BOOLEAN);
+
+CREATE FUNCTION record_sync_change(_object_type TEXT, _object_id INT, _object_uuid UUID, _list_id INT, _list_uuid UUID,
+                                   _is_deleted BOOLEAN) RETURNS VOID AS $$
+    INSERT INTO sync_changes (object_type, object_id, object_uuid, list_id, list_uuid, is_deleted)
+    VALUES (_object_type, _object_id, _object_uuid, _list_id, _list_uuid, _is_deleted)
+    ON CONFLICT (object_type, object_id, list_id) DO UPDATE
+        SET is_deleted = EXCLUDED.is_deleted, changed_xid = txid_current(), changed_at = NOW();
+$$ LANGUAGE sql;
+
+CREATE OR REPLACE FUNCTION record_list_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('list', OLD.list_id, OLD.uuid, OLD.list_id, OLD.uuid, TRUE);
+        RETURN OLD;
+    END IF;
+
+    PERFORM record_sync_change('list', NEW.list_id, NEW.uuid, NEW.list_id, NEW.uuid, NEW.deleted_at IS NOT NULL);
+
+    IF TG_OP = 'UPDATE' AND OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+        UPDATE sync_changes SET changed_xid = txid_current(), changed_at = NOW()
+        WHERE list_id = NEW.list_id AND NOT is_deleted;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_item_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_sync_change('item', OLD.item_id, OLD.uuid, OLD.list_id, OLD.list_uuid, TRUE);
+        RETURN OLD;
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND OLD.list_id <> NEW.list_id THEN
+        PERFORM record_sync_change('item', OLD.item_id, OLD.uuid, OLD.list_id, OLD.list_uuid, TRUE);
+    END IF;
+
+    PERFORM record_sync_change('item', NEW.item_id, NEW.uuid, NEW.list_id, NEW.list_uuid, NEW.deleted_at IS NOT NULL);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Memberships are deleted along with their list, by which point the list can only be found through its own change
+CREATE OR REPLACE FUNCTION record_membership_change() RETURNS trigger AS $$
+DECLARE
+    _list_uuid UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        SELECT list_uuid INTO _list_uuid FROM sync_changes
+        WHERE object_type = 'list' AND object_id = OLD.list_id AND list_id = OLD.list_id;
+
+        PERFORM record_sync_change('membership', OLD.user_id, NULL, OLD.list_id, _list_uuid, TRUE);
+        RETURN OLD;
+    END IF;
+
+    SELECT uuid INTO _list_uuid FROM lists WHERE list_id = NEW.list_id;
+
+    PERFORM record_sync_change('membership', NEW.user_id, NULL, NEW.list_id, _list_uuid, FALSE);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Synced changes remember the public ids of what they created or changed, which is what clients are told
+ALTER TABLE sync_mutations ADD COLUMN list_uuid UUID;
+ALTER TABLE sync_mutations ADD COLUMN item_uuid UUID;
+
+UPDATE sync_mutations SET list_uuid = lists.uuid FROM lists WHERE lists.list_id = sync_mutations.list_id;
+UPDATE sync_mutations SET item_uuid = items.uuid FROM items WHERE items.item_id = sync_mutations.item_id;
+
+DELETE FROM sync_mutations WHERE list_uuid IS NULL OR (item_uuid IS NULL AND item_id IS NOT NULL);
+
+ALTER TABLE sync_mutations DROP COLUMN list_id;
+ALTER TABLE sync_mutations DROP COLUMN item_id;
+ALTER TABLE sync_mutations ALTER COLUMN list_uuid SET NOT NULL;
+
+-- Smart lists filter on lists by their public id. A list that no longer exists becomes the nil id, which still matches
+-- nothing.
+CREATE FUNCTION public_list_filter(_filter JSONB) RETURNS JSONB AS $$
+BEGIN
+    CASE jsonb_typeof(_filter)
+        WHEN 'object' THEN
+            RETURN (SELECT jsonb_object_agg(key, CASE
+                        WHEN key = 'list' AND jsonb_typeof(value) = 'number' THEN to_jsonb(COALESCE(
+                            (SELECT uuid FROM lists WHERE list_id = (value #>> '{}')::INT),
+                            '00000000-0000-0000-0000-000000000000'::UUID))
+                        ELSE public_list_filter(value)
+                    END) FROM jsonb_each(_filter));
+        WHEN 'array' THEN
+            RETURN (SELECT COALESCE(jsonb_agg(public_list_filter(value) ORDER BY position), '[]')
+                    FROM jsonb_array_elements(_filter) WITH ORDINALITY AS elements(value, position));
+        ELSE
+            RETURN _filter;
+    END CASE;
+END;
+$$ LANGUAGE plpgsql;
+
+UPDATE smart_lists SET filter = public_list_filter(filter);
+
+DROP FUNCTION public_list_filter(JSONB);
@@ -0,0 +1,162 @@
+//! Checks on the addresses that the server sends requests to for its users, such as webhook deliveries, so that
+//! they can't be pointed at services on the server's own machine or network.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::Url;
+use rocket::tokio::net::lookup_host;
+
+/// Which addresses requests can be sent to. Only public addresses are allowed, unless `allow_private` is set, which
+/// is meant for trying webhooks out against services on the same machine or network.
+#[derive(Clone, Copy, Default)]
+pub struct AddressPolicy {
+    pub allow_private: bool,
+}
+
+impl AddressPolicy {
+    /// Determines whether requests can be sent to the given address.
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.allow_private || is_public(ip)
+    }
+
+    /// Determines whether the host of `url` is allowed when it's an IP address. Hosts that are names are allowed
+    /// here, as they're checked when they're resolved.
+    pub fn allows_host_address(&self, url: &Url) -> bool {
+        match host_address(url) {
+            Some(ip) => self.allows(ip),
+            None => url.has_host(),
+        }
+    }
+
+    /// Determines whether requests can be sent to the host of `url`, resolving it if it's a name.
+    pub async fn allows_url(&self, url: &Url) -> bool {
+        match (host_address(url), url.host_str()) {
+            (Some(ip), _) => self.allows(ip),
+            (None, Some(host)) => self.lookup(host).await.is_ok(),
+            (None, None) => false,
+        }
+    }
+
+    /// Resolves the `host` name, failing unless it has addresses and every one of them is allowed. Turning away a
+    /// name with any address that isn't allowed keeps it from switching to one between being checked and used.
+    pub async fn lookup(&self, host: &str) -> io::Result<Vec<SocketAddr>> {
+        let addresses: Vec<SocketAddr> = lookup_host((host, 0)).await?.collect();
+
+        if !addresses.is_empty() && addresses.iter().all(|address| self.allows(address.ip())) {
+            Ok(addresses)
+        } else {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} isn't a public address", host)))
+        }
+    }
+}
+
+/// Resolves the names that a `reqwest` client connects to, so that it can only reach allowed addresses.
+impl Resolve for AddressPolicy {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = *self;
+
+        Box::pin(async move {
+            let addresses = policy.lookup(name.as_str()).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Gets the host of `url` as an IP address, if it is one.
+fn host_address(url: &Url) -> Option<IpAddr> {
+    url.host_str()?.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+/// Determines whether the given address can be reached over the internet, rather than being private, local, reserved
+/// or otherwise special.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0 // "This network"
+        || (a == 100 && (b & 0xc0) == 64) // Shared address space for carrier-grade NAT
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b & 0xfe) == 18) // Benchmarking
+        || a >= 240) // Reserved
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // Addresses with an IPv4 address embedded in them reach that address
+    if let [0, 0, 0, 0, 0, 0xffff, high, low] | [0x64, 0xff9b, 0, 0, 0, 0, high, low] = segments {
+        return is_public_v4(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)));
+    }
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00 // Unique local
+        || (segments[0] & 0xffc0) == 0xfe80 // Link local
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8) // Documentation
+        || (segments[0] == 0x0100 && segments[1..4] == [0, 0, 0])) // Discard only
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        for address in &["93.184.216.34", "8.8.8.8", "2606:2800:220:1:248:1893:25c8:1946", "::ffff:93.184.216.34"] {
+            assert!(is_public(ip(address)), "{} should be public", address);
+        }
+    }
+
+    #[test]
+    fn private_and_special_addresses_arent() {
+        let addresses = [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1",
+            "192.0.0.8", "198.18.0.1", "224.0.0.1", "255.255.255.255", "::1", "::", "fc00::1", "fe80::1",
+            "::ffff:127.0.0.1", "::ffff:169.254.169.254", "64:ff9b::a00:1", "2001:db8::1",
+        ];
+
+        for address in &addresses {
+            assert!(!is_public(ip(address)), "{} shouldn't be public", address);
+        }
+    }
+
+    #[test]
+    fn private_addresses_can_be_allowed() {
+        let policy = AddressPolicy { allow_private: true };
+
+        assert!(policy.allows(ip("127.0.0.1")));
+        assert!(!AddressPolicy::default().allows(ip("127.0.0.1")));
+    }
+
+    #[rocket::async_test]
+    async fn urls_are_checked_by_their_addresses() {
+        let policy = AddressPolicy::default();
+
+        assert!(!policy.allows_url(&Url::parse("http://127.0.0.1:8000/hook").unwrap()).await);
+        assert!(!policy.allows_url(&Url::parse("http://[::1]/hook").unwrap()).await);
+        assert!(!policy.allows_url(&Url::parse("http://localhost/hook").unwrap()).await);
+        assert!(policy.allows_url(&Url::parse("http://93.184.216.34/hook").unwrap()).await);
+        assert!(AddressPolicy { allow_private: true }.allows_url(&Url::parse("http://localhost/hook").unwrap()).await);
+    }
+}
@@ -75,6 +75,6 @@ pub fn generate_token(login: LoginInfoDTO) -> String {
 }
 
 /// Attempts to decode the given string token into its raw data.
-fn decode_token(token: String) -> Result<TokenData<UserToken>> {
+pub fn decode_token(token: String) -> Result<TokenData<UserToken>> {
     jsonwebtoken::decode::<UserToken>(&token, &DecodingKey::from_secret(include_bytes!("secret.key")), &Validation::default())
 }
\ No newline at end of file
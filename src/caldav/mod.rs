@@ -0,0 +1,287 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use hyper::body::HttpBody;
+use hyper::server::Builder;
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use uuid::Uuid;
+
+use crate::database::PostgresPool;
+use crate::etag::Preconditions;
+use crate::events::EventBus;
+use crate::services::caldav_service;
+
+pub mod xml;
+#[cfg(test)]
+mod tests;
+
+static REALM: &str = "todo-backend"; // Realm that clients show when asking for an app password
+static MAX_BODY_BYTES: usize = 1024 * 1024; // Largest request body accepted, which is plenty for a single VTODO
+static DAV_CLASSES: &str = "1, 3, calendar-access"; // Sent in the `DAV` header of every response
+static ALLOWED_METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+
+/// The characters that are percent encoded in a single path segment of an href.
+const SEGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'%').add(b'/').add(b'<').add(b'>').add(b'?')
+    .add(b'`').add(b'{').add(b'}');
+
+/// A resource in the CalDAV tree. Each user has a principal and a home collection, in which every list they can
+/// access is a calendar named by its public id, and every item in it is a VTODO object resource.
+pub enum DavPath {
+    Root,
+    Principal(String),
+    Home(String),
+    Calendar(String, Uuid),
+    Object(String, Uuid, String),
+}
+
+/// How deep a PROPFIND goes, either only the resource itself or its members too. `infinity` is treated as
+/// members only, which is all there is below a calendar anyway.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Depth {
+    Zero,
+    One,
+}
+
+/// A response to a CalDAV request.
+pub struct DavResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl DavPath {
+    /// Parses the path of a request, such as `/dav/calendars/alice/3f2b8e0c-5d1a-4f6e-9c7b-2a4d6e8f0b1c/`, decoding
+    /// each segment.
+    pub fn parse(path: &str) -> Option<DavPath> {
+        let segments: Option<Vec<String>> = path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| percent_decode_str(segment).decode_utf8().ok().map(String::from))
+            .collect();
+
+        match segments?.as_slice() {
+            [dav] if dav == "dav" => Some(DavPath::Root),
+            [dav, principals, username] if dav == "dav" && principals == "principals" => Some(DavPath::Principal(username.clone())),
+            [dav, calendars, username] if dav == "dav" && calendars == "calendars" => Some(DavPath::Home(username.clone())),
+            [dav, calendars, username, list_id] if dav == "dav" && calendars == "calendars" => {
+                Some(DavPath::Calendar(username.clone(), list_id.parse().ok()?))
+            }
+            [dav, calendars, username, list_id, name] if dav == "dav" && calendars == "calendars" => {
+                Some(DavPath::Object(username.clone(), list_id.parse().ok()?, name.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses an href from a request body, which may be a full URL rather than only a path.
+    pub fn parse_href(href: &str) -> Option<DavPath> {
+        let path = match href.split_once("://") {
+            Some((_, rest)) => &rest[rest.find('/')?..],
+            None => href,
+        };
+
+        DavPath::parse(path)
+    }
+
+    /// Gets the username in the path, if it's below a user's principal or home.
+    pub fn username(&self) -> Option<&str> {
+        match self {
+            DavPath::Root => None,
+            DavPath::Principal(username)
+            | DavPath::Home(username)
+            | DavPath::Calendar(username, _)
+            | DavPath::Object(username, _, _) => Some(username),
+        }
+    }
+
+    /// Builds the href of this resource. Collections end with a slash.
+    pub fn href(&self) -> String {
+        let encode = |segment: &str| utf8_percent_encode(segment, SEGMENT).to_string();
+
+        match self {
+            DavPath::Root => String::from("/dav/"),
+            DavPath::Principal(username) => format!("/dav/principals/{}/", encode(username)),
+            DavPath::Home(username) => format!("/dav/calendars/{}/", encode(username)),
+            DavPath::Calendar(username, list_id) => format!("/dav/calendars/{}/{}/", encode(username), list_id),
+            DavPath::Object(username, list_id, name) => format!("/dav/calendars/{}/{}/{}", encode(username), list_id, encode(name)),
+        }
+    }
+}
+
+impl DavResponse {
+    /// Builds a response with only a `status`.
+    pub fn status(status: StatusCode) -> DavResponse {
+        DavResponse {
+            status: status.as_u16(),
+            headers: vec![],
+            body: String::new(),
+        }
+    }
+
+    /// Builds a `207 Multi-Status` response with the given XML `body`.
+    pub fn multistatus(body: String) -> DavResponse {
+        DavResponse {
+            status: StatusCode::MULTI_STATUS.as_u16(),
+            headers: vec![("Content-Type", String::from("application/xml; charset=utf-8"))],
+            body,
+        }
+    }
+
+    /// Builds a response with the given `status` and a `DAV:error` body naming the `precondition` that failed.
+    pub fn error(status: StatusCode, precondition: &xml::PropName) -> DavResponse {
+        DavResponse {
+            status: status.as_u16(),
+            headers: vec![("Content-Type", String::from("application/xml; charset=utf-8"))],
+            body: xml::error(precondition),
+        }
+    }
+
+    /// Adds a header to this response.
+    pub fn with_header(mut self, name: &'static str, value: String) -> DavResponse {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+/// Binds the CalDAV server to `address`, so that startup can fail if the port can't be used.
+pub fn bind(address: SocketAddr) -> hyper::Result<Builder<AddrIncoming>> {
+    Server::try_bind(&address)
+}
+
+/// Serves the CalDAV tree with the bound `server` until it shuts down. CalDAV needs the PROPFIND and REPORT methods,
+/// which Rocket can't route, so it's served by its own HTTP server next to the API.
+pub async fn serve(server: Builder<AddrIncoming>, pool: PostgresPool, events: EventBus) {
+    let make_service = make_service_fn(move |_| {
+        let pool = pool.clone();
+        let events = events.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| handle(request, pool.clone(), events.clone())))
+        }
+    });
+
+    if let Err(error) = server.serve(make_service).await {
+        log::error!("The CalDAV server stopped: {}", error);
+    }
+}
+
+/// Handles a single CalDAV request, logging the user in with HTTP Basic and one of their app passwords or
+/// personal tokens.
+async fn handle(request: Request<Body>, pool: PostgresPool, events: EventBus) -> Result<Response<Body>, Infallible> {
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+
+    if method == "OPTIONS" {
+        return Ok(build(DavResponse::status(StatusCode::OK).with_header("Allow", String::from(ALLOWED_METHODS))));
+    }
+    if path.trim_end_matches('/') == "/.well-known/caldav" {
+        return Ok(build(DavResponse::status(StatusCode::MOVED_PERMANENTLY).with_header("Location", String::from("/dav/"))));
+    }
+
+    let dav_path = match DavPath::parse(&path) {
+        Some(dav_path) => dav_path,
+        None => return Ok(build(DavResponse::status(StatusCode::NOT_FOUND))),
+    };
+
+    let credentials = match request.headers().get("Authorization").and_then(|header| header.to_str().ok()).and_then(basic_credentials) {
+        Some(credentials) => credentials,
+        None => return Ok(build(unauthorized())),
+    };
+
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok()).map(String::from);
+    let depth = match header("Depth").as_deref() {
+        Some("0") => Depth::Zero,
+        _ => Depth::One,
+    };
+    let preconditions = Preconditions {
+        if_match: header("If-Match"),
+        if_none_match: header("If-None-Match"),
+    };
+
+    let body = match read_body(request.into_body()).await {
+        Some(body) => body,
+        None => return Ok(build(DavResponse::status(StatusCode::PAYLOAD_TOO_LARGE))),
+    };
+
+    let is_head = method == "HEAD";
+    let connection = match pool.get().await {
+        Some(connection) => connection,
+        None => return Ok(build(DavResponse::status(StatusCode::SERVICE_UNAVAILABLE))),
+    };
+
+    let response = connection.run(move |conn| {
+        let (username, password) = credentials;
+        let user = match caldav_service::authenticate(&username, &password, conn) {
+            Some(user) => user,
+            None => return unauthorized(),
+        };
+
+        if matches!(dav_path.username(), Some(username) if username != user.username) {
+            return DavResponse::status(StatusCode::FORBIDDEN);
+        }
+
+        match (method.as_str(), &dav_path) {
+            ("PROPFIND", _) => caldav_service::propfind(&user, &dav_path, depth, &body, conn),
+            ("REPORT", _) => caldav_service::report(&user, &dav_path, &body, conn),
+            ("GET", DavPath::Object(_, list_id, name)) | ("HEAD", DavPath::Object(_, list_id, name)) => {
+                caldav_service::get_object(&user, *list_id, name, &preconditions, conn)
+            }
+            ("PUT", DavPath::Object(_, list_id, name)) => {
+                caldav_service::put_object(&user, *list_id, name, &body, &preconditions, &events, conn)
+            }
+            ("DELETE", DavPath::Object(_, list_id, name)) => {
+                caldav_service::delete_object(&user, *list_id, name, &preconditions, &events, conn)
+            }
+            _ => DavResponse::status(StatusCode::METHOD_NOT_ALLOWED).with_header("Allow", String::from(ALLOWED_METHODS)),
+        }
+    }).await;
+
+    let mut response = build(response);
+    if is_head {
+        *response.body_mut() = Body::empty();
+    }
+    Ok(response)
+}
+
+/// Builds the response asking the client to log in.
+fn unauthorized() -> DavResponse {
+    DavResponse::status(StatusCode::UNAUTHORIZED)
+        .with_header("WWW-Authenticate", format!("Basic realm=\"{}\", charset=\"UTF-8\"", REALM))
+}
+
+/// Gets the username and password from an HTTP Basic `Authorization` header.
+fn basic_credentials(header: &str) -> Option<(String, String)> {
+    let encoded = header.strip_prefix("Basic ")?.trim();
+    let decoded = String::from_utf8(base64::decode(encoded).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+
+    Some((String::from(username), String::from(password)))
+}
+
+/// Reads a request body as text, as long as it's no longer than `MAX_BODY_BYTES`.
+async fn read_body(mut body: Body) -> Option<String> {
+    let mut bytes = vec![];
+
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk.ok()?);
+        if bytes.len() > MAX_BODY_BYTES {
+            return None;
+        }
+    }
+
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Turns a `DavResponse` into a hyper response.
+fn build(response: DavResponse) -> Response<Body> {
+    let mut builder = Response::builder()
+        .status(response.status)
+        .header("DAV", DAV_CLASSES);
+
+    for (name, value) in response.headers {
+        builder = builder.header(name, value);
+    }
+
+    builder.body(Body::from(response.body)).unwrap()
+}
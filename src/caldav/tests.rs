@@ -0,0 +1,198 @@
+use hyper::server::conn::AddrIncoming;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Method, RequestBuilder};
+
+use super::*;
+use crate::models::app_password::AppPassword;
+use crate::test_support;
+
+static PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:displayname/><d:resourcetype/><d:getetag/></d:prop>
+</d:propfind>"#;
+
+static CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter>
+</c:calendar-query>"#;
+
+/// A CalDAV server on a free port, with a user who has a list and an app password to log in with.
+struct Server {
+    base: String,
+    username: String,
+    password: String,
+    list_id: Uuid,
+    client: Client,
+}
+
+impl Server {
+    async fn start() -> Server {
+        let pool = test_support::pool().await;
+        let (username, password, list_id) = pool.get().await.unwrap().run(|conn| {
+            let user = test_support::create_user(conn);
+            let list = test_support::create_list("Groceries", user.id, conn);
+            let password = AppPassword::create_app_password(user.id, "Tests", conn).unwrap().password;
+
+            (user.username, password, list.uuid)
+        }).await;
+
+        let incoming = AddrIncoming::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let base = format!("http://{}", incoming.local_addr());
+        rocket::tokio::spawn(serve(hyper::Server::builder(incoming), pool, EventBus::new(16)));
+
+        Server {
+            base,
+            username,
+            password,
+            list_id,
+            client: Client::builder().redirect(Policy::none()).build().unwrap(),
+        }
+    }
+
+    /// Starts a request logged in as the user.
+    fn request(&self, method: &str, path: &str) -> RequestBuilder {
+        self.client.request(Method::from_bytes(method.as_bytes()).unwrap(), format!("{}{}", self.base, path))
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    fn calendar(&self) -> String {
+        DavPath::Calendar(self.username.clone(), self.list_id).href()
+    }
+
+    fn object(&self, name: &str) -> String {
+        DavPath::Object(self.username.clone(), self.list_id, String::from(name)).href()
+    }
+}
+
+/// Builds a calendar object with a single VTODO, due at a fixed time and repeating every week.
+fn vtodo(uid: &str, summary: &str) -> String {
+    [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Tests//EN",
+        "BEGIN:VTODO",
+        &format!("UID:{}", uid),
+        &format!("SUMMARY:{}", summary),
+        "DUE:20300820T090000Z",
+        "RRULE:FREQ=WEEKLY",
+        "END:VTODO",
+        "END:VCALENDAR",
+        "",
+    ].join("\r\n")
+}
+
+fn etag(response: &reqwest::Response) -> String {
+    response.headers()["ETag"].to_str().unwrap().to_string()
+}
+
+#[rocket::async_test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn finds_lists_as_calendars() {
+    let server = Server::start().await;
+
+    let response = server.client.get(format!("{}/.well-known/caldav", server.base)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()["Location"], "/dav/");
+
+    let home = DavPath::Home(server.username.clone()).href();
+    let response = server.request("PROPFIND", &home).header("Depth", "1").body(PROPFIND).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+
+    let body = response.text().await.unwrap();
+    assert!(body.contains(&server.calendar()));
+    assert!(body.contains("Groceries"));
+    assert!(body.contains("<c:calendar/>"));
+}
+
+#[rocket::async_test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn creates_reads_updates_and_deletes_todos() {
+    let server = Server::start().await;
+    let object = server.object("milk.ics");
+
+    let response = server.request("PUT", &object).header("If-None-Match", "*").body(vtodo("milk@tests", "Buy milk"))
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created = etag(&response);
+
+    let response = server.request("GET", &object).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(etag(&response), created);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("UID:milk@tests"));
+    assert!(body.contains("SUMMARY:Buy milk"));
+    assert!(body.contains("DTSTART:20300820T090000Z"));
+    assert!(body.contains("RRULE:FREQ=WEEKLY"));
+
+    let response = server.request("PUT", &object).header("If-None-Match", "*").body(vtodo("milk@tests", "Buy milk"))
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = server.request("PUT", &object).header("If-Match", &created).body(vtodo("milk@tests", "Buy oat milk"))
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let updated = etag(&response);
+    assert_ne!(updated, created);
+
+    let response = server.request("PUT", &object).header("If-Match", &created).body(vtodo("milk@tests", "Buy soy milk"))
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = server.request("REPORT", &server.calendar()).header("Depth", "1").body(CALENDAR_QUERY)
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = response.text().await.unwrap();
+    assert!(body.contains(&object));
+    assert!(body.contains("SUMMARY:Buy oat milk"));
+
+    let response = server.request("DELETE", &object).header("If-Match", &created).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = server.request("DELETE", &object).header("If-Match", &updated).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = server.request("GET", &object).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[rocket::async_test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn refuses_todos_that_items_cant_hold() {
+    let server = Server::start().await;
+
+    let invalid = vec![
+        (String::from("not a calendar"), "valid-calendar-data"),
+        (vtodo("invalid@tests", " "), "valid-calendar-data"),
+        (vtodo("invalid@tests", &"x".repeat(501)), "valid-calendar-object-resource"),
+    ];
+
+    for (body, precondition) in invalid {
+        let response = server.request("PUT", &server.object("invalid.ics")).body(body).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(response.text().await.unwrap().contains(precondition));
+    }
+
+    let response = server.request("PROPFIND", &server.calendar()).header("Depth", "1").body(PROPFIND).send().await.unwrap();
+    assert!(!response.text().await.unwrap().contains("invalid.ics"));
+}
+
+#[rocket::async_test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn only_lets_users_into_their_own_calendars() {
+    let server = Server::start().await;
+
+    let response = server.client.request(Method::from_bytes(b"PROPFIND").unwrap(), format!("{}{}", server.base, server.calendar()))
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+
+    let response = server.client.request(Method::from_bytes(b"PROPFIND").unwrap(), format!("{}{}", server.base, server.calendar()))
+        .basic_auth(&server.username, Some("wrong password"))
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let other_home = DavPath::Home(String::from("someone-else")).href();
+    let response = server.request("PROPFIND", &other_home).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
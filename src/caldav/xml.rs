@@ -0,0 +1,282 @@
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+pub static DAV: &str = "DAV:"; // Namespace of WebDAV elements
+pub static CALDAV: &str = "urn:ietf:params:xml:ns:caldav"; // Namespace of CalDAV elements
+pub static CALENDAR_SERVER: &str = "http://calendarserver.org/ns/"; // Namespace of `getctag`, which most clients poll
+
+/// An element of a parsed XML request body, with the namespace that its prefix stands for.
+pub struct Element {
+    pub namespace: String,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+/// The name of a WebDAV property, such as `displayname` in the `DAV:` namespace.
+#[derive(Clone, PartialEq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+/// The properties that a client asked for, either every property there is or only the named ones.
+pub enum PropRequest {
+    All,
+    Named(Vec<PropName>),
+}
+
+/// The REPORT requests that clients can make, with the properties they asked for in each. A calendar query
+/// can be limited to a single `component`, such as `VTODO`, and a multiget names the `hrefs` it wants.
+pub enum Report {
+    CalendarQuery { props: PropRequest, component: Option<String> },
+    CalendarMultiget { props: PropRequest, hrefs: Vec<String> },
+    Unsupported,
+}
+
+impl Element {
+    /// Determines whether this element has the given `namespace` and `name`.
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    /// Finds the first child element with the given `namespace` and `name`.
+    pub fn child(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(namespace, name))
+    }
+
+    /// Gets the value of the attribute called `name`, if it has one.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl PropName {
+    /// Builds the name of a property in the given `namespace`.
+    pub fn new(namespace: &str, name: &str) -> PropName {
+        PropName {
+            namespace: String::from(namespace),
+            name: String::from(name),
+        }
+    }
+}
+
+/// Parses an XML request `body` into its root element.
+pub fn parse(body: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(body);
+    reader.trim_text(true);
+
+    let mut buffer = vec![];
+    let mut namespace_buffer = vec![];
+    let mut open: Vec<Element> = vec![];
+
+    loop {
+        let (namespace, event) = reader.read_namespaced_event(&mut buffer, &mut namespace_buffer)
+            .map_err(|error| format!("the request body is not valid XML: {}", error))?;
+        let namespace = namespace.map(|namespace| String::from_utf8_lossy(namespace).into_owned()).unwrap_or_default();
+        let is_empty = matches!(event, Event::Empty(_));
+
+        match event {
+            Event::Start(start) | Event::Empty(start) => {
+                let attributes = start.attributes()
+                    .flatten()
+                    .map(|attribute| {
+                        let key = String::from_utf8_lossy(attribute.key).into_owned();
+                        let value = attribute.unescape_and_decode_value(&reader).unwrap_or_default();
+                        (key, value)
+                    })
+                    .collect();
+
+                open.push(Element {
+                    namespace,
+                    name: String::from_utf8_lossy(start.local_name()).into_owned(),
+                    attributes,
+                    children: vec![],
+                    text: String::new(),
+                });
+
+                if is_empty {
+                    if let Some(root) = close(&mut open) {
+                        return Ok(root);
+                    }
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = open.last_mut() {
+                    element.text.push_str(&text.unescape_and_decode(&reader).unwrap_or_default());
+                }
+            }
+            Event::End(_) => {
+                if let Some(root) = close(&mut open) {
+                    return Ok(root);
+                }
+            }
+            Event::Eof => return Err(String::from("the request body ended before its root element did")),
+            _ => {}
+        }
+
+        buffer.clear();
+    }
+}
+
+/// Closes the innermost open element, adding it to its parent. Returns the element if it was the root.
+fn close(open: &mut Vec<Element>) -> Option<Element> {
+    let element = open.pop()?;
+
+    match open.last_mut() {
+        Some(parent) => {
+            parent.children.push(element);
+            None
+        }
+        None => Some(element),
+    }
+}
+
+/// Parses the body of a PROPFIND request. An empty body asks for every property.
+pub fn parse_propfind(body: &str) -> Result<PropRequest, String> {
+    if body.trim().is_empty() {
+        return Ok(PropRequest::All);
+    }
+
+    let root = parse(body)?;
+    if !root.is(DAV, "propfind") {
+        return Err(String::from("the request body is not a DAV:propfind"));
+    }
+
+    Ok(prop_request(&root))
+}
+
+/// Parses the body of a REPORT request.
+pub fn parse_report(body: &str) -> Result<Report, String> {
+    let root = parse(body)?;
+
+    if root.is(CALDAV, "calendar-query") {
+        // The filter is a VCALENDAR comp-filter, which can name the single component type wanted inside it
+        let component = root.child(CALDAV, "filter")
+            .and_then(|filter| filter.child(CALDAV, "comp-filter"))
+            .and_then(|calendar| calendar.child(CALDAV, "comp-filter"))
+            .and_then(|component| component.attribute("name"))
+            .map(|name| name.to_ascii_uppercase());
+
+        Ok(Report::CalendarQuery {
+            props: prop_request(&root),
+            component,
+        })
+    } else if root.is(CALDAV, "calendar-multiget") {
+        let hrefs = root.children.iter()
+            .filter(|child| child.is(DAV, "href"))
+            .map(|href| href.text.trim().to_string())
+            .collect();
+
+        Ok(Report::CalendarMultiget {
+            props: prop_request(&root),
+            hrefs,
+        })
+    } else {
+        Ok(Report::Unsupported)
+    }
+}
+
+/// Gets the properties asked for in a `DAV:prop` child of `root`, or every property if it asks for all of them
+/// or their names with `DAV:allprop` or `DAV:propname`.
+fn prop_request(root: &Element) -> PropRequest {
+    match root.child(DAV, "prop") {
+        Some(prop) => PropRequest::Named(prop.children.iter()
+            .map(|child| PropName::new(&child.namespace, &child.name))
+            .collect()),
+        None => PropRequest::All,
+    }
+}
+
+/// A `DAV:multistatus` response body, built up one resource at a time.
+pub struct Multistatus {
+    body: String,
+}
+
+impl Multistatus {
+    /// Starts an empty multistatus response.
+    pub fn new() -> Multistatus {
+        Multistatus {
+            body: format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"{}\" xmlns:c=\"{}\" xmlns:cs=\"{}\">",
+                DAV, CALDAV, CALENDAR_SERVER,
+            ),
+        }
+    }
+
+    /// Adds the resource at `href` with the properties that were `found`, each with its value as XML, and the
+    /// ones that are `missing`.
+    pub fn add(&mut self, href: &str, found: &[(PropName, String)], missing: &[PropName]) {
+        self.body.push_str(&format!("<d:response><d:href>{}</d:href>", escape(href)));
+
+        if !found.is_empty() {
+            self.body.push_str("<d:propstat><d:prop>");
+            for (prop, value) in found {
+                self.body.push_str(&element(prop, value));
+            }
+            self.body.push_str("</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>");
+        }
+
+        if !missing.is_empty() {
+            self.body.push_str("<d:propstat><d:prop>");
+            for prop in missing {
+                self.body.push_str(&element(prop, ""));
+            }
+            self.body.push_str("</d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>");
+        }
+
+        self.body.push_str("</d:response>");
+    }
+
+    /// Adds the resource at `href` with only a `status` such as `HTTP/1.1 404 Not Found`.
+    pub fn add_status(&mut self, href: &str, status: &str) {
+        self.body.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:status>{}</d:status></d:response>",
+            escape(href), status,
+        ));
+    }
+
+    /// Finishes the response body.
+    pub fn finish(mut self) -> String {
+        self.body.push_str("</d:multistatus>");
+        self.body
+    }
+}
+
+/// Builds a `DAV:error` response body naming the precondition that the request broke, such as
+/// `supported-report` in the `DAV:` namespace.
+pub fn error(precondition: &PropName) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:error xmlns:d=\"{}\" xmlns:c=\"{}\">{}</d:error>",
+        DAV, CALDAV, element(precondition, ""),
+    )
+}
+
+/// Writes an element for a property with the given XML `value`, using the prefixes declared on the multistatus
+/// element for known namespaces.
+fn element(prop: &PropName, value: &str) -> String {
+    let (prefix, declaration) = match prop.namespace.as_str() {
+        "" => return format!("<{0}>{1}</{0}>", prop.name, value),
+        namespace if namespace == DAV => ("d", String::new()),
+        namespace if namespace == CALDAV => ("c", String::new()),
+        namespace if namespace == CALENDAR_SERVER => ("cs", String::new()),
+        namespace => ("x", format!(" xmlns:x=\"{}\"", escape(namespace))),
+    };
+
+    if value.is_empty() {
+        format!("<{}:{}{}/>", prefix, prop.name, declaration)
+    } else {
+        format!("<{0}:{1}{2}>{3}</{0}:{1}>", prefix, prop.name, declaration, value)
+    }
+}
+
+/// Escapes the characters that have a meaning in XML text and attribute values.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
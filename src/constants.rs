@@ -31,6 +31,10 @@ pub const MESSAGE_DELETE_ITEM_FAILED: &str = "error when deleting item, please t
 
 pub const MESSAGE_GET_LIST_SUCCESS: &str = "got list successfully";
 pub const MESSAGE_GET_LIST_FAILED: &str = "error when getting list, please try again";
+pub const MESSAGE_INVALID_PAGE: &str = "error, invalid pagination, filter or sort parameters";
+
+pub const MESSAGE_ARCHIVE_LIST_SUCCESS: &str = "archived list successfully";
+pub const MESSAGE_UNARCHIVE_LIST_SUCCESS: &str = "unarchived list successfully";
 
 // pub const MESSAGE_UPDATE_LIST_SUCCESS: &str = "updated list successfully";
 // pub const MESSAGE_UPDATE_LIST_FAILED: &str = "error when updating list, please try again";
@@ -39,6 +43,114 @@ pub const MESSAGE_GET_LIST_FAILED: &str = "error when getting list, please try a
 // pub const MESSAGE_UPDATE_ITEM_FAILED: &str = "error when updating item, please try again";
 
 pub const MESSAGE_ITEM_NOT_OWNED_BY_LIST: &str = "error, item not owned by the specified list";
+pub const MESSAGE_INVALID_ITEM_DESCRIPTION: &str = "error, item descriptions must be between 1 and 500 characters";
+pub const MESSAGE_INVALID_PRIORITY: &str = "error, the priority must be low, medium or high";
+pub const MESSAGE_INVALID_RECURRENCE: &str = "error, the recurrence must be an RRULE with a FREQ, then optionally an INTERVAL and a single BYDAY";
+
+pub const MESSAGE_BATCH_SUCCESS: &str = "completed batch, see the results for each item";
+pub const MESSAGE_BATCH_FAILED: &str = "error when running batch, no changes were made, please try again";
+
+pub const MESSAGE_MOVE_ITEM_SUCCESS: &str = "moved item successfully";
+pub const MESSAGE_MOVE_ITEM_FAILED: &str = "error when moving item, please try again";
+pub const MESSAGE_COPY_ITEM_SUCCESS: &str = "copied item successfully";
+pub const MESSAGE_COPY_ITEM_FAILED: &str = "error when copying item, please try again";
+
+pub const MESSAGE_ASSIGNEE_NO_ACCESS: &str = "error, the assignee does not have access to the specified list";
 
 pub const MESSAGE_UPDATE_SUCCESS: &str = "updated successfully";
 pub const MESSAGE_UPDATE_FAILED: &str = "error when updating data, please try again";
+
+pub const MESSAGE_GET_ITEM_SUCCESS: &str = "got item successfully";
+pub const MESSAGE_NOT_MODIFIED: &str = "not modified";
+pub const MESSAGE_PRECONDITION_FAILED: &str = "error, the resource was changed by someone else, please get it again and retry";
+
+pub const MESSAGE_CREATE_COMMENT_SUCCESS: &str = "created comment successfully";
+pub const MESSAGE_CREATE_COMMENT_FAILED: &str = "error when creating comment, please try again";
+pub const MESSAGE_DELETE_COMMENT_SUCCESS: &str = "deleted comment successfully";
+pub const MESSAGE_DELETE_COMMENT_FAILED: &str = "error when deleting comment, please try again";
+pub const MESSAGE_COMMENT_NOT_OWNED_BY_ITEM: &str = "error, comment not owned by the specified item";
+pub const MESSAGE_COMMENT_NOT_AUTHOR: &str = "error, only the author of a comment can edit it";
+pub const MESSAGE_COMMENT_NOT_AUTHOR_OR_OWNER: &str = "error, only the author of a comment or a list owner can delete it";
+pub const MESSAGE_INVALID_COMMENT_BODY: &str = "error, comments must be between 1 and 2000 characters";
+
+pub const MESSAGE_UPLOAD_ATTACHMENT_SUCCESS: &str = "uploaded attachment successfully";
+pub const MESSAGE_UPLOAD_ATTACHMENT_FAILED: &str = "error when uploading attachment, please try again";
+pub const MESSAGE_ATTACHMENT_QUOTA_EXCEEDED: &str = "error, uploading this file would exceed your storage quota";
+pub const MESSAGE_DELETE_ATTACHMENT_SUCCESS: &str = "deleted attachment successfully";
+pub const MESSAGE_DELETE_ATTACHMENT_FAILED: &str = "error when deleting attachment, please try again";
+pub const MESSAGE_ATTACHMENT_NOT_OWNED_BY_ITEM: &str = "error, attachment not owned by the specified item";
+pub const MESSAGE_GET_ATTACHMENT_FAILED: &str = "error when getting attachment, please try again";
+
+pub const MESSAGE_RESTORE_SUCCESS: &str = "restored from the trash successfully";
+pub const MESSAGE_RESTORE_FAILED: &str = "error, could not find that in your trash";
+pub const MESSAGE_RESTORE_LIST_FIRST: &str = "error, the list containing this item is not accessible, restore the list first";
+pub const MESSAGE_EMPTY_TRASH_SUCCESS: &str = "emptied trash successfully";
+pub const MESSAGE_EMPTY_TRASH_FAILED: &str = "error when emptying trash, please try again";
+
+pub const MESSAGE_EMPTY_SEARCH: &str = "error, the search query can not be empty";
+pub const MESSAGE_SEARCH_FAILED: &str = "error when searching, please try again";
+
+pub const MESSAGE_CREATE_TAG_SUCCESS: &str = "created tag successfully";
+pub const MESSAGE_CREATE_TAG_FAILED: &str = "error when creating tag, please try again";
+pub const MESSAGE_DELETE_TAG_SUCCESS: &str = "deleted tag successfully";
+pub const MESSAGE_DELETE_TAG_FAILED: &str = "error when deleting tag, please try again";
+pub const MESSAGE_TAG_ITEM_SUCCESS: &str = "tagged item successfully";
+pub const MESSAGE_UNTAG_ITEM_SUCCESS: &str = "untagged item successfully";
+pub const MESSAGE_TAG_ITEM_FAILED: &str = "error when tagging item, please try again";
+pub const MESSAGE_TAG_EXISTS: &str = "error, you already have a tag with that name";
+pub const MESSAGE_TAG_NOT_FOUND: &str = "error, you have no tag with that name";
+pub const MESSAGE_INVALID_TAG_NAME: &str = "error, tag names must be between 1 and 50 characters and can not contain a /";
+pub const MESSAGE_INVALID_TAG_COLOR: &str = "error, tag colors must be given as #rrggbb";
+
+pub const MESSAGE_CREATE_SMART_LIST_SUCCESS: &str = "created smart list successfully";
+pub const MESSAGE_CREATE_SMART_LIST_FAILED: &str = "error when creating smart list, please try again";
+pub const MESSAGE_DELETE_SMART_LIST_SUCCESS: &str = "deleted smart list successfully";
+pub const MESSAGE_DELETE_SMART_LIST_FAILED: &str = "error when deleting smart list, please try again";
+pub const MESSAGE_GET_SMART_LIST_FAILED: &str = "error when getting smart list, please try again";
+pub const MESSAGE_SMART_LIST_EXISTS: &str = "error, you already have a smart list with that name";
+pub const MESSAGE_SMART_LIST_NOT_FOUND: &str = "error, you have no smart list with that id";
+pub const MESSAGE_INVALID_SMART_LIST_NAME: &str = "error, smart list names can not be empty";
+pub const MESSAGE_INVALID_FILTER: &str = "error, the filter is not valid";
+
+pub const MESSAGE_INVALID_TIMEZONE: &str = "error, the time zone is not a known IANA time zone name";
+
+pub const MESSAGE_QUICK_ADD_EMPTY: &str = "error, the text has no description left after taking out the due date, priority, tags, assignee and recurrence";
+pub const MESSAGE_QUICK_ADD_UNKNOWN_ASSIGNEE: &str = "error, there is no user with the username given after @";
+
+pub const MESSAGE_INVALID_EXPORT_FORMAT: &str = "error, lists can only be exported as json, csv, markdown or todotxt";
+pub const MESSAGE_EXPORT_FAILED: &str = "error when exporting list, please try again";
+pub const MESSAGE_IMPORT_SUCCESS: &str = "imported list successfully";
+pub const MESSAGE_IMPORT_PREVIEW: &str = "previewed import successfully, nothing was saved";
+pub const MESSAGE_IMPORT_FAILED: &str = "error when importing list, please try again";
+pub const MESSAGE_INVALID_IMPORT_FORMAT: &str = "error, give the format of the file as json, csv, markdown or todotxt";
+pub const MESSAGE_INVALID_IMPORT: &str = "error, the file could not be imported";
+
+pub const MESSAGE_REGENERATE_CALENDAR_FEED_SUCCESS: &str = "regenerated calendar feed successfully, the old URL no longer works";
+pub const MESSAGE_DELETE_CALENDAR_FEED_SUCCESS: &str = "turned off calendar feed successfully";
+pub const MESSAGE_DELETE_CALENDAR_FEED_FAILED: &str = "error when turning off calendar feed, please try again";
+pub const MESSAGE_CALENDAR_FEED_FAILED: &str = "error when getting calendar feed, please try again";
+pub const MESSAGE_CALENDAR_FEED_NOT_FOUND: &str = "error, there is no calendar feed at this URL";
+pub const MESSAGE_CREATE_APP_PASSWORD_SUCCESS: &str = "created app password successfully, it won't be shown again";
+pub const MESSAGE_CREATE_APP_PASSWORD_FAILED: &str = "error when creating app password, please try again";
+pub const MESSAGE_INVALID_APP_PASSWORD_NAME: &str = "error, an app password must have a name";
+pub const MESSAGE_DELETE_APP_PASSWORD_SUCCESS: &str = "deleted app password successfully";
+pub const MESSAGE_DELETE_APP_PASSWORD_FAILED: &str = "error when deleting app password, please try again";
+pub const MESSAGE_GET_APP_PASSWORDS_FAILED: &str = "error when getting app passwords, please try again";
+pub const MESSAGE_SUBSCRIBE_EVENTS_FAILED: &str = "error when following changes, please try again";
+pub const MESSAGE_WEBHOOK_NOT_OWNER: &str = "error, only the owner of a list can manage its webhooks";
+pub const MESSAGE_INVALID_WEBHOOK_URL: &str = "error, a webhook URL must be an http or https URL";
+pub const MESSAGE_WEBHOOK_URL_NOT_PUBLIC: &str = "error, a webhook URL must have a host with a public address";
+pub const MESSAGE_INVALID_WEBHOOK_EVENTS: &str = "error, webhook events must be item_created, item_updated, item_finished, item_deleted, list_renamed, member_added, member_removed, list_trashed or list_restored";
+pub const MESSAGE_WEBHOOK_NOT_FOUND: &str = "error, the list has no webhook with that id";
+pub const MESSAGE_CREATE_WEBHOOK_SUCCESS: &str = "created webhook successfully, its secret won't be shown again";
+pub const MESSAGE_CREATE_WEBHOOK_FAILED: &str = "error when creating webhook, please try again";
+pub const MESSAGE_GET_WEBHOOKS_FAILED: &str = "error when getting webhooks, please try again";
+pub const MESSAGE_DELETE_WEBHOOK_SUCCESS: &str = "deleted webhook successfully";
+pub const MESSAGE_DELETE_WEBHOOK_FAILED: &str = "error when deleting webhook, please try again";
+pub const MESSAGE_INVALID_SYNC_TOKEN: &str = "error, invalid sync token";
+pub const MESSAGE_SYNC_TOKEN_EXPIRED: &str = "error, sync token has expired, please sync again without one";
+pub const MESSAGE_SYNC_FAILED: &str = "error when syncing, please try again";
+pub const MESSAGE_TOO_MANY_MUTATIONS: &str = "error, too many changes in a single sync";
+pub const MESSAGE_INVALID_CLIENT_ID: &str = "error, every change needs a client id of at most 100 characters";
+pub const MESSAGE_UNKNOWN_CLIENT_ID: &str = "error, no synced change has the given client id";
+pub const MESSAGE_SYNC_CONFLICT: &str = "changed on the server since the given version";
@@ -1,6 +1,10 @@
 use rocket::serde::json::Json;
-use rocket_sync_db_pools::{database, diesel};
+use rocket_sync_db_pools::{database, diesel, ConnectionPool};
 
 /// The wrapper around the database connection that allows it to be pooled.
 #[database("postgres_db")]
-pub struct PostgresDbConn(diesel::PgConnection);
\ No newline at end of file
+pub struct PostgresDbConn(diesel::PgConnection);
+
+/// The pool behind `PostgresDbConn`, which background tasks can hold on to in order to get
+/// connections outside of any request.
+pub type PostgresPool = ConnectionPool<PostgresDbConn, diesel::PgConnection>;
\ No newline at end of file
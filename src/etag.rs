@@ -0,0 +1,153 @@
+use std::convert::Infallible;
+
+use rocket::request::{FromRequest, Outcome, Request};
+use uuid::Uuid;
+
+use crate::models::item::Item;
+
+/// The conditional request headers sent by the client, used for optimistic concurrency control.
+/// Clients send the ETag they last saw in `If-Match` when changing a resource, so that the change is
+/// rejected if someone else changed it in the meantime, and in `If-None-Match` when getting a resource,
+/// so that it is only sent again if it has changed.
+#[derive(Clone, Default)]
+pub struct Preconditions {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Preconditions {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        Outcome::Success(Preconditions {
+            if_match: headers.get_one("If-Match").map(String::from),
+            if_none_match: headers.get_one("If-None-Match").map(String::from),
+        })
+    }
+}
+
+impl Preconditions {
+    /// Determines whether a change may be made to a resource whose current ETag is `etag`. Requests
+    /// without an `If-Match` header are always allowed. The ETag of any representation of the resource
+    /// will do, since they all change along with it.
+    pub fn allows_write(&self, etag: &str) -> bool {
+        match &self.if_match {
+            Some(header) => header_matches(header, |tag| strip_variant(tag) == strip_variant(etag)),
+            None => true,
+        }
+    }
+
+    /// Determines whether the client already has the current version of the representation of a resource
+    /// whose ETag is `etag`.
+    pub fn not_modified(&self, etag: &str) -> bool {
+        match &self.if_none_match {
+            Some(header) => header_matches(header, |tag| tag == etag),
+            None => false,
+        }
+    }
+}
+
+/// Determines whether a conditional header, which is either `*` or a comma separated list of ETags,
+/// has an ETag that `matches`. Weak validators are compared by their opaque tag.
+fn header_matches<F: Fn(&str) -> bool>(header: &str, matches: F) -> bool {
+    header.split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || matches(tag.trim_start_matches("W/")))
+}
+
+/// Removes the part of an ETag that picks a single representation of the resource, if it has one.
+fn strip_variant(etag: &str) -> &str {
+    match etag.rsplit_once('~') {
+        Some((resource, _)) => resource,
+        None => etag.trim_end_matches('"'),
+    }
+}
+
+/// Hashes `bytes` with 64-bit FNV-1a, which is stable between builds unlike the standard library hasher.
+fn fnv1a<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/// Builds the ETag for one representation of the resource whose ETag is `etag`, such as a filtered page of a
+/// list or the rendered HTML of an item, picked by the normalized `variant`. Different representations never
+/// share an ETag, so a client can't be told that a page it cached for one query is current for another.
+pub fn variant_etag(etag: &str, variant: &str) -> String {
+    format!("{}~{:016x}\"", etag.trim_end_matches('"'), fnv1a(variant.bytes()))
+}
+
+/// Builds the ETag for a single item, which changes whenever any of its fields do.
+pub fn item_etag(item: &Item) -> String {
+    format!("\"item-{}-{}\"", item.uuid, item.version)
+}
+
+/// Builds the ETag for a complete list with the public `list_id`, from the `version` of the list itself and the
+/// `(item_id, version)` pair of each of its items. It changes whenever the list changes, or any of its items is
+/// added, changed or removed.
+pub fn list_etag(list_id: Uuid, version: i32, item_versions: &[(i32, i32)]) -> String {
+    let hash = fnv1a(item_versions.iter()
+        .flat_map(|(item_id, item_version)| [item_id.to_be_bytes(), item_version.to_be_bytes()].concat()));
+
+    format!("\"list-{}-{}-{:016x}\"", list_id, version, hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::page::{PageQuery, SORT_CREATED, SORT_DESCRIPTION, SORT_UPDATED};
+
+    fn page_variant(query: PageQuery) -> String {
+        query.parse(&[SORT_CREATED, SORT_UPDATED, SORT_DESCRIPTION]).unwrap().variant()
+    }
+
+    fn if_none_match(etag: &str) -> Preconditions {
+        Preconditions { if_match: None, if_none_match: Some(String::from(etag)) }
+    }
+
+    #[test]
+    fn different_filters_never_match_each_other() {
+        let etag = list_etag(Uuid::nil(), 1, &[(1, 1), (2, 3)]);
+        let variants = vec![
+            page_variant(PageQuery::default()),
+            page_variant(PageQuery { limit: Some(10), ..PageQuery::default() }),
+            page_variant(PageQuery { sort: Some(String::from(SORT_DESCRIPTION)), ..PageQuery::default() }),
+            page_variant(PageQuery { order: Some(String::from("desc")), ..PageQuery::default() }),
+            page_variant(PageQuery { contains: Some(String::from("milk")), ..PageQuery::default() }),
+            page_variant(PageQuery { created_after: Some(String::from("2021-01-01T00:00:00Z")), ..PageQuery::default() }),
+            format!("{}&finished=Some(true)", page_variant(PageQuery::default())),
+        ];
+
+        for (i, first) in variants.iter().enumerate() {
+            for (j, second) in variants.iter().enumerate() {
+                let cached = if_none_match(&variant_etag(&etag, first));
+                assert_eq!(cached.not_modified(&variant_etag(&etag, second)), i == j);
+            }
+        }
+    }
+
+    #[test]
+    fn same_query_is_not_modified() {
+        let etag = list_etag(Uuid::nil(), 1, &[(1, 1)]);
+        let query = || PageQuery { contains: Some(String::from("milk")), ..PageQuery::default() };
+
+        let cached = if_none_match(&variant_etag(&etag, &page_variant(query())));
+        assert!(cached.not_modified(&variant_etag(&etag, &page_variant(query()))));
+    }
+
+    #[test]
+    fn any_variant_allows_writes_to_the_resource() {
+        let etag = list_etag(Uuid::nil(), 1, &[(1, 1)]);
+        let preconditions = Preconditions { if_match: Some(variant_etag(&etag, "render_html=true")), if_none_match: None };
+
+        assert!(preconditions.allows_write(&etag));
+        assert!(!preconditions.allows_write(&list_etag(Uuid::nil(), 2, &[(1, 1)])));
+    }
+}
@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use rocket::futures::{stream, StreamExt};
+use rocket::tokio::sync::broadcast::{self, Receiver, Sender};
+use rocket::tokio::sync::mpsc::{self, UnboundedReceiver};
+use rocket::tokio::time;
+use tokio_postgres::{AsyncMessage, Client, NoTls};
+
+use crate::database::PostgresPool;
+use crate::models::event::ListEvent;
+use crate::models::item::Item;
+use crate::models::webhook::WebhookDelivery;
+
+static CHANNEL: &str = "list_events"; // Postgres channel that every instance publishes and listens on
+static MAX_PAYLOAD_BYTES: usize = 7999; // Postgres rejects notifications of 8000 bytes or more
+static MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1); // Wait before listening again after losing the connection
+static MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60); // Longest wait, as failed attempts double it
+
+/// The messages that arrive on the connection the bus listens on.
+type Messages = UnboundedReceiver<Result<AsyncMessage, tokio_postgres::Error>>;
+
+/// The bus that changes to lists are published on, so that they can be streamed to every member following the list.
+/// Events are sent through Postgres notifications, so that members connected to any instance of the server get them,
+/// and each instance relays them to its own subscribers. Cloning the bus gives another handle to the same channel.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: Sender<ListEvent>,
+}
+
+/// The payload of a notification. Events only carry the public ids of their list and item when serialized, so
+/// the ids they're routed by are sent alongside them. Events with an item that makes them too large for a
+/// notification are sent with only the id of the item, which the listening instances find again.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Notification {
+    Event { list_id: i32, item_id: Option<i32>, event: Box<ListEvent> },
+    ItemCreated { item_id: i32 },
+    ItemUpdated { item_id: i32 },
+}
+
+impl EventBus {
+    /// Makes a new bus that keeps up to `capacity` events for subscribers that fall behind, after which the oldest
+    /// are dropped.
+    pub fn new(capacity: usize) -> EventBus {
+        let (sender, _) = broadcast::channel(capacity);
+        EventBus { sender }
+    }
+
+    /// Sends an event to every subscriber on every instance, by notifying the database on `conn`, and queues it
+    /// for the webhooks of its list. It should be published inside the transaction that made the change, as the
+    /// event is then only sent and queued once the transaction commits, and not at all if it rolls back. Fails if
+    /// the event couldn't be queued or sent, which should roll the change back too.
+    pub fn publish(&self, event: ListEvent, conn: &PgConnection) -> QueryResult<()> {
+        if let Ok(payload) = serde_json::to_string(&event) {
+            WebhookDelivery::enqueue(&event, &payload, conn)?;
+        }
+
+        if let Ok(payload) = Notification::payload(event) {
+            diesel::sql_query("SELECT pg_notify($1, $2)")
+                .bind::<Text, _>(CHANNEL)
+                .bind::<Text, _>(payload)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends each of the given events to every subscriber on every instance and queues them for webhooks, in order.
+    pub fn publish_all<I: IntoIterator<Item = ListEvent>>(&self, events: I, conn: &PgConnection) -> QueryResult<()> {
+        for event in events {
+            self.publish(event, conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Subscribes to every event published from now on.
+    pub fn subscribe(&self) -> Receiver<ListEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Relays the events published by every instance to the subscribers on this one, listening on its own
+    /// connection to the database at `url` until the server shuts down. When the connection is lost or can't be
+    /// made, the error is logged and the bus listens again after a delay that doubles with each failed attempt.
+    /// Events published while the connection is lost are missed.
+    pub async fn listen(self, url: String, pool: PostgresPool) {
+        let mut delay = MIN_RECONNECT_DELAY;
+
+        loop {
+            match connect(&url).await {
+                Ok((client, messages)) => {
+                    delay = MIN_RECONNECT_DELAY;
+
+                    match self.relay_notifications(messages, &pool).await {
+                        Ok(()) => log::error!("Lost the connection listening for list events"),
+                        Err(error) => log::error!("Lost the connection listening for list events: {}", describe(&error)),
+                    }
+
+                    drop(client);
+                },
+                Err(error) => log::error!("Could not listen for list events: {}", describe(&error)),
+            }
+
+            time::sleep(delay).await;
+            delay = next_reconnect_delay(delay);
+        }
+    }
+
+    /// Relays each notification in `messages` to the subscribers on this instance, until the connection they
+    /// arrive on is lost.
+    async fn relay_notifications(&self, mut messages: Messages, pool: &PostgresPool) -> Result<(), tokio_postgres::Error> {
+        while let Some(message) = messages.recv().await {
+            if let AsyncMessage::Notification(notification) = message? {
+                if let Ok(notification) = serde_json::from_str::<Notification>(notification.payload()) {
+                    if let Some(event) = notification.into_event(pool).await {
+                        let _ = self.sender.send(event);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Notification {
+    /// Builds the payload of the notification for `event`, leaving out its item if the event would be too large.
+    fn payload(event: ListEvent) -> serde_json::Result<String> {
+        let list_id = event.list_id();
+        let item_id = match &event {
+            ListEvent::ItemCreated { item, .. } | ListEvent::ItemUpdated { item, .. } => Some(item.item_id),
+            _ => None,
+        };
+
+        let payload = serde_json::to_string(&Notification::Event { list_id, item_id, event: Box::new(event.clone()) })?;
+        if payload.len() <= MAX_PAYLOAD_BYTES {
+            return Ok(payload);
+        }
+
+        let notification = match event {
+            ListEvent::ItemCreated { item, .. } => Notification::ItemCreated { item_id: item.item_id },
+            ListEvent::ItemUpdated { item, .. } => Notification::ItemUpdated { item_id: item.item_id },
+            event => Notification::Event { list_id, item_id, event: Box::new(event) },
+        };
+
+        serde_json::to_string(&notification)
+    }
+
+    /// Gets the event that was sent, finding its item again if it was left out. Returns `None` if the item
+    /// can't be found.
+    async fn into_event(self, pool: &PostgresPool) -> Option<ListEvent> {
+        match self {
+            Notification::Event { list_id, item_id, event } => Some(with_ids(*event, list_id, item_id)),
+            Notification::ItemCreated { item_id } => find_item(item_id, pool).await.map(ListEvent::item_created),
+            Notification::ItemUpdated { item_id } => find_item(item_id, pool).await.map(ListEvent::item_updated),
+        }
+    }
+}
+
+/// Puts the ids that weren't serialized back into an `event`, which are the id of its list and of its item,
+/// if it has one.
+fn with_ids(mut event: ListEvent, id: i32, item_id: Option<i32>) -> ListEvent {
+    match &mut event {
+        ListEvent::ItemCreated { list_id, item, .. } | ListEvent::ItemUpdated { list_id, item, .. } => {
+            *list_id = id;
+            item.list_id = id;
+            item.item_id = item_id.unwrap_or_default();
+        },
+        ListEvent::ItemDeleted { list_id, .. }
+        | ListEvent::ListRenamed { list_id, .. }
+        | ListEvent::MemberAdded { list_id, .. }
+        | ListEvent::MemberRemoved { list_id, .. }
+        | ListEvent::ListTrashed { list_id, .. }
+        | ListEvent::ListRestored { list_id, .. } => *list_id = id,
+    }
+
+    event
+}
+
+/// Opens a new connection to the database at `url` and listens for notifications on it, returning the client
+/// along with the messages that arrive on the connection.
+async fn connect(url: &str) -> Result<(Client, Messages), tokio_postgres::Error> {
+    let (client, mut connection) = tokio_postgres::connect(url, NoTls).await?;
+
+    // The connection has to be polled for anything to be sent on it, so its messages are passed along by
+    // a separate task while the client starts listening
+    let (sender, receiver) = mpsc::unbounded_channel();
+    rocket::tokio::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    client.batch_execute(&format!("LISTEN {}", CHANNEL)).await?;
+    Ok((client, receiver))
+}
+
+/// Describes an `error` from the database connection, including the message from the database itself, which
+/// isn't part of how the error is displayed.
+fn describe(error: &tokio_postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db_error) => format!("{}: {}", error, db_error.message()),
+        None => error.to_string(),
+    }
+}
+
+/// Gets how long to wait before listening again after waiting `delay` the last time.
+fn next_reconnect_delay(delay: Duration) -> Duration {
+    (delay * 2).min(MAX_RECONNECT_DELAY)
+}
+
+/// Finds the item with `item_id` on a connection from the `pool`.
+async fn find_item(item_id: i32, pool: &PostgresPool) -> Option<Item> {
+    pool.get().await?.run(move |conn| Item::find_item_by_id(item_id, conn)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::sql_types::{Integer, Text};
+    use rocket::tokio::time::Instant;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::test_support;
+
+    /// A process serving a connection to the database.
+    #[derive(QueryableByName)]
+    struct Backend {
+        #[sql_type = "Integer"]
+        pid: i32,
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_a_limit() {
+        let delays: Vec<_> = std::iter::successors(Some(MIN_RECONNECT_DELAY), |delay| Some(next_reconnect_delay(*delay)))
+            .take(9)
+            .map(|delay| delay.as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60, 60]);
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn relays_events_again_after_losing_its_connection() {
+        // Name the connection that the bus listens on, so that it can be closed without closing anyone else's
+        let name = format!("events-test-{}", Uuid::new_v4().to_simple());
+        let url = format!("{}{}application_name={}", test_support::database_url(),
+                          if test_support::database_url().contains('?') { "&" } else { "?" }, name);
+
+        let pool = test_support::pool().await;
+        let (events, mut receiver) = test_support::listening_bus_on(url, &pool).await;
+        let listener = listener_pid(&name, &pool).await.expect("the bus should be listening");
+
+        let closed = pool.get().await.unwrap().run(move |conn| {
+            diesel::sql_query("SELECT pg_terminate_backend($1)").bind::<Integer, _>(listener).execute(conn)
+        }).await;
+        assert_eq!(closed, Ok(1));
+
+        // The bus waits a moment before listening again on a new connection
+        let lost_at = Instant::now();
+        while listener_pid(&name, &pool).await.unwrap_or(listener) == listener {
+            assert!(lost_at.elapsed() < Duration::from_secs(10), "the bus didn't listen again");
+            time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(lost_at.elapsed() >= MIN_RECONNECT_DELAY);
+
+        test_support::wait_until_listening(&events, &mut receiver, &pool).await;
+    }
+
+    /// Finds the process of the connection named `name`, if it's open.
+    async fn listener_pid(name: &str, pool: &PostgresPool) -> Option<i32> {
+        let name = name.to_string();
+        pool.get().await?.run(move |conn| {
+            diesel::sql_query("SELECT pid FROM pg_stat_activity WHERE application_name = $1")
+                .bind::<Text, _>(name)
+                .get_result::<Backend>(conn)
+                .ok()
+                .map(|backend| backend.pid)
+        }).await
+    }
+}
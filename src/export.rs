@@ -0,0 +1,329 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use crate::models::portable::{ListFormat, PortableList};
+
+pub static CSV_HEADERS: [&str; 10] = ["description", "finished", "notes", "due_at", "priority", "recurrence", "tags",
+    "assignee", "created_at", "completed_at"];
+
+/// The characters that make spreadsheets read a cell as a formula.
+pub static FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Writes the `list` as a file in the given `format`. Dates in the Markdown and todo.txt formats are written
+/// in the time zone `tz`, since those formats have no way to give one.
+pub fn render(list: &PortableList, format: ListFormat, tz: Tz) -> String {
+    match format {
+        ListFormat::Json => serde_json::to_string_pretty(list).unwrap(),
+        ListFormat::Csv => to_csv(list),
+        ListFormat::Markdown => to_markdown(list, tz),
+        ListFormat::TodoTxt => to_todo_txt(list, tz),
+    }
+}
+
+/// Builds the name of the file to export the list called `name` to, made from the letters and digits in
+/// the name so that it needs no quoting.
+pub fn file_name(name: &str, format: ListFormat) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        format!("list.{}", format.extension())
+    } else {
+        format!("{}.{}", slug, format.extension())
+    }
+}
+
+/// Writes one row per item under a header row. Times are in RFC 3339 and tags are separated by commas. Text
+/// that a spreadsheet would run as a formula is escaped.
+fn to_csv(list: &PortableList) -> String {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(CSV_HEADERS).unwrap();
+
+    for item in &list.items {
+        writer.write_record(&[
+            escape_formula(&item.description),
+            item.finished.to_string(),
+            escape_formula(item.notes.as_deref().unwrap_or_default()),
+            item.due_at.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            item.priority.clone().unwrap_or_default(),
+            item.recurrence.clone().unwrap_or_default(),
+            escape_formula(&item.tags.join(",")),
+            escape_formula(item.assignee.as_deref().unwrap_or_default()),
+            item.created_at.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            item.completed_at.map(|time| time.to_rfc3339()).unwrap_or_default(),
+        ]).unwrap();
+    }
+
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+/// Writes the list as a Markdown checklist under its name and description. The rest of each item is written
+/// after its description in the same form as quick-add, such as `!high #bills @alice due 2021-08-20`, and its
+/// notes are indented below it.
+fn to_markdown(list: &PortableList, tz: Tz) -> String {
+    let mut markdown = format!("# {}\n\n", single_line(&list.name));
+    if let Some(description) = list.description.as_ref().filter(|description| !description.trim().is_empty()) {
+        markdown.push_str(description.trim_end());
+        markdown.push_str("\n\n");
+    }
+
+    for item in &list.items {
+        let mut line = vec![
+            String::from(if item.finished { "- [x]" } else { "- [ ]" }),
+            single_line(&item.description),
+        ];
+
+        if let Some(priority) = &item.priority {
+            line.push(format!("!{}", priority));
+        }
+        line.extend(item.tags.iter().map(|tag| format!("#{}", word(tag))));
+        if let Some(assignee) = &item.assignee {
+            line.push(format!("@{}", assignee));
+        }
+        if let Some(due_at) = item.due_at {
+            line.push(format!("due {}", local_due(due_at, tz)));
+        }
+        if let Some(phrase) = item.recurrence.as_deref().and_then(recurrence_phrase) {
+            line.push(phrase);
+        }
+
+        markdown.push_str(&line.join(" "));
+        markdown.push('\n');
+
+        if let Some(notes) = &item.notes {
+            for note_line in notes.lines() {
+                if note_line.trim().is_empty() {
+                    markdown.push('\n');
+                } else {
+                    markdown.push_str("  ");
+                    markdown.push_str(note_line);
+                    markdown.push('\n');
+                }
+            }
+        }
+    }
+
+    markdown
+}
+
+/// Writes one todo.txt line per item, such as `(A) 2021-08-01 Pay rent +Home @bills due:2021-08-20 rec:1m`.
+/// The list becomes the project, tags become contexts, and the due date, recurrence and assignee are
+/// written as `due:`, `rec:` and `assignee:`. Finished items keep their priority as `pri:`, and notes are
+/// left out since the format has no place for them.
+fn to_todo_txt(list: &PortableList, tz: Tz) -> String {
+    let project = format!("+{}", word(&list.name));
+    let mut todo_txt = String::new();
+
+    for item in &list.items {
+        let priority = item.priority.as_deref().and_then(priority_letter);
+        let mut line = vec![];
+
+        if item.finished {
+            line.push(String::from("x"));
+            if let (Some(completed_at), Some(_)) = (item.completed_at, item.created_at) {
+                line.push(local_date(completed_at, tz));
+            }
+        } else if let Some(letter) = priority {
+            line.push(format!("({})", letter));
+        }
+
+        if let Some(created_at) = item.created_at {
+            line.push(local_date(created_at, tz));
+        }
+
+        line.push(single_line(&item.description));
+        line.push(project.clone());
+        line.extend(item.tags.iter().map(|tag| format!("@{}", word(tag))));
+
+        if let Some(due_at) = item.due_at {
+            line.push(format!("due:{}", local_date(due_at, tz)));
+        }
+        if let Some(rec) = item.recurrence.as_deref().and_then(todo_txt_recurrence) {
+            line.push(format!("rec:{}", rec));
+        }
+        if let (true, Some(letter)) = (item.finished, priority) {
+            line.push(format!("pri:{}", letter));
+        }
+        if let Some(assignee) = &item.assignee {
+            line.push(format!("assignee:{}", assignee));
+        }
+
+        todo_txt.push_str(&line.join(" "));
+        todo_txt.push('\n');
+    }
+
+    todo_txt
+}
+
+/// Gets the todo.txt priority letter for a priority level.
+fn priority_letter(priority: &str) -> Option<char> {
+    match priority {
+        "high" => Some('A'),
+        "medium" => Some('B'),
+        "low" => Some('C'),
+        _ => None,
+    }
+}
+
+/// Splits an RRULE into its frequency, interval and day of the week.
+fn rule_parts(rule: &str) -> (Option<&str>, u32, Option<&str>) {
+    let mut frequency = None;
+    let mut interval = 1;
+    let mut day = None;
+
+    for part in rule.split(';') {
+        match part.split_once('=') {
+            Some(("FREQ", value)) => frequency = Some(value),
+            Some(("INTERVAL", value)) => interval = value.parse().unwrap_or(1),
+            Some(("BYDAY", value)) => day = Some(value),
+            _ => {}
+        }
+    }
+
+    (frequency, interval, day)
+}
+
+/// Writes an RRULE as a todo.txt `rec:` value, such as `2w`. The day of the week is left out, since it
+/// follows from the due date.
+fn todo_txt_recurrence(rule: &str) -> Option<String> {
+    let (frequency, interval, _) = rule_parts(rule);
+    let unit = match frequency? {
+        "DAILY" => 'd',
+        "WEEKLY" => 'w',
+        "MONTHLY" => 'm',
+        "YEARLY" => 'y',
+        _ => return None,
+    };
+
+    Some(format!("{}{}", interval, unit))
+}
+
+/// Writes an RRULE as a recurrence that quick-add understands, such as `every 2 weeks` or `every monday`.
+fn recurrence_phrase(rule: &str) -> Option<String> {
+    let (frequency, interval, day) = rule_parts(rule);
+    let unit = match frequency? {
+        "DAILY" => "day",
+        "WEEKLY" => "week",
+        "MONTHLY" => "month",
+        "YEARLY" => "year",
+        _ => return None,
+    };
+
+    let day_name = match day {
+        Some("MO") => Some("monday"),
+        Some("TU") => Some("tuesday"),
+        Some("WE") => Some("wednesday"),
+        Some("TH") => Some("thursday"),
+        Some("FR") => Some("friday"),
+        Some("SA") => Some("saturday"),
+        Some("SU") => Some("sunday"),
+        _ => None,
+    };
+
+    match (day_name, interval) {
+        (Some(day_name), 1) if unit == "week" => Some(format!("every {}", day_name)),
+        (_, 1) => Some(format!("every {}", unit)),
+        (_, 2) => Some(format!("every other {}", unit)),
+        (_, interval) => Some(format!("every {} {}s", interval, unit)),
+    }
+}
+
+/// Writes the date of `time` in the time zone `tz`.
+fn local_date(time: DateTime<Utc>, tz: Tz) -> String {
+    time.with_timezone(&tz).format("%Y-%m-%d").to_string()
+}
+
+/// Writes a due time in the time zone `tz`, leaving out the time of items due at the end of the day.
+fn local_due(time: DateTime<Utc>, tz: Tz) -> String {
+    let local = time.with_timezone(&tz);
+    if local.time() == NaiveTime::from_hms_opt(23, 59, 59).unwrap() {
+        local.format("%Y-%m-%d").to_string()
+    } else {
+        local.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// Starts a CSV cell with `'` if it begins with a character that makes spreadsheets read it as a formula, so
+/// that exported text can't run formulas when the file is opened.
+pub fn escape_formula(text: &str) -> String {
+    if text.starts_with(&FORMULA_PREFIXES[..]) {
+        format!("'{}", text)
+    } else {
+        String::from(text)
+    }
+}
+
+/// Joins the lines of `text` so that it fits on a single line.
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Replaces the whitespace in a name, so that it can be written as a single `#tag` or `+project` word.
+fn word(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::import;
+    use crate::models::portable::{PORTABLE_FORMAT, PORTABLE_VERSION, PortableItem};
+
+    fn list_of(description: &str, notes: &str, tag: &str, assignee: &str) -> PortableList {
+        PortableList {
+            format: String::from(PORTABLE_FORMAT),
+            version: PORTABLE_VERSION,
+            exported_at: None,
+            name: String::from("Formulas"),
+            description: None,
+            items: vec![PortableItem {
+                description: String::from(description),
+                finished: false,
+                notes: Some(String::from(notes)),
+                due_at: None,
+                priority: None,
+                recurrence: None,
+                tags: vec![String::from(tag)],
+                assignee: Some(String::from(assignee)),
+                created_at: None,
+                completed_at: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn escapes_formulas_in_csv() {
+        let csv = render(&list_of("=HYPERLINK(\"http://example.com\")", "+1 for this", "-x", "@alice"), ListFormat::Csv, Tz::UTC);
+        let row = csv.lines().nth(1).unwrap();
+
+        assert_eq!(row, "\"'=HYPERLINK(\"\"http://example.com\"\")\",false,'+1 for this,,,,'-x,'@alice,,");
+    }
+
+    #[test]
+    fn leaves_other_text_alone() {
+        assert_eq!(escape_formula("Buy milk"), "Buy milk");
+        assert_eq!(escape_formula("1+1=2"), "1+1=2");
+        assert_eq!(escape_formula(""), "");
+    }
+
+    #[test]
+    fn escaped_csv_imports_as_the_original_text() {
+        let csv = render(&list_of("=1+1", "-notes", "@home", "alice"), ListFormat::Csv, Tz::UTC);
+        let imported = import::parse(&csv, ListFormat::Csv, Tz::UTC, Utc::now()).unwrap();
+        let item = &imported.items[0].item;
+
+        assert_eq!(item.description, "=1+1");
+        assert_eq!(item.notes.as_deref(), Some("-notes"));
+        assert_eq!(item.tags, vec!["@home"]);
+        assert_eq!(item.assignee.as_deref(), Some("alice"));
+    }
+}
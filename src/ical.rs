@@ -0,0 +1,434 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::import;
+use crate::models::item::Item;
+use crate::quick_add;
+
+static PRODUCT_ID: &str = "-//todo-backend//todo-backend//EN"; // Identifies this server as the creator of calendars
+static MAX_LINE_OCTETS: usize = 75; // Longest content line before it has to be folded, not counting the line break
+
+/// The fields of a VTODO sent by a calendar app that items can hold. Anything else in it is ignored.
+pub struct Todo {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<String>,
+    pub recurrence: Option<String>,
+    pub categories: Vec<String>,
+    pub completed: bool,
+}
+
+/// A single unfolded content line, such as `DUE;TZID=Europe/London:20210820T090000`, with its name in upper case.
+struct ContentLine<'a> {
+    name: String,
+    params: Vec<(String, String)>,
+    value: &'a str,
+}
+
+/// Writes an RFC 5545 calendar called `name` with a VTODO for each item, along with its categories.
+pub fn calendar(name: &str, todos: &[(Item, Vec<String>)], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:{}", PRODUCT_ID),
+        String::from("CALSCALE:GREGORIAN"),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+
+    for (item, categories) in todos {
+        lines.extend(vtodo(item, categories, now));
+    }
+
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Writes a single item as an RFC 5545 calendar object with only its VTODO, as served to CalDAV clients.
+pub fn object(item: &Item, categories: &[String], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:{}", PRODUCT_ID),
+    ];
+
+    lines.extend(vtodo(item, categories, now));
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Builds the unfolded content lines of the VTODO for an `item`, with `categories` such as its tags.
+/// Finished items are `COMPLETED` and the rest `NEEDS-ACTION`.
+pub fn vtodo(item: &Item, categories: &[String], now: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec![
+        String::from("BEGIN:VTODO"),
+        format!("UID:{}", uid(item)),
+        format!("DTSTAMP:{}", format_time(now)),
+        format!("CREATED:{}", format_time(item.created_at)),
+        format!("LAST-MODIFIED:{}", format_time(item.updated_at)),
+        format!("SEQUENCE:{}", item.version),
+        format!("SUMMARY:{}", escape_text(&item.description)),
+    ];
+
+    if let Some(notes) = &item.notes {
+        lines.push(format!("DESCRIPTION:{}", escape_text(notes)));
+    }
+    // An RRULE repeats from DTSTART, so recurring items start when they're due, or else when they were created
+    if item.recurrence.is_some() {
+        lines.push(format!("DTSTART:{}", format_time(item.due_at.unwrap_or(item.created_at))));
+    }
+    if let Some(due_at) = item.due_at {
+        lines.push(format!("DUE:{}", format_time(due_at)));
+    }
+    if let Some(priority) = item.priority.as_deref().and_then(priority_number) {
+        lines.push(format!("PRIORITY:{}", priority));
+    }
+    if let Some(recurrence) = &item.recurrence {
+        lines.push(format!("RRULE:{}", recurrence));
+    }
+    if !categories.is_empty() {
+        let categories: Vec<String> = categories.iter().map(|category| escape_text(category)).collect();
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+
+    if item.finished {
+        lines.push(String::from("STATUS:COMPLETED"));
+        lines.push(String::from("PERCENT-COMPLETE:100"));
+        if let Some(completed_at) = item.completed_at {
+            lines.push(format!("COMPLETED:{}", format_time(completed_at)));
+        }
+    } else {
+        lines.push(String::from("STATUS:NEEDS-ACTION"));
+    }
+
+    lines.push(String::from("END:VTODO"));
+    lines
+}
+
+/// Builds the globally unique id of the VTODO for an item, keeping the one given by the calendar app that
+/// created it or the one it had before it had a public id, if there is one.
+pub fn uid(item: &Item) -> String {
+    match &item.ical_uid {
+        Some(uid) => uid.clone(),
+        None => format!("{}@todo-backend", item.uuid),
+    }
+}
+
+/// Parses the first VTODO in an iCalendar object, such as one uploaded by a calendar app. Times without a UTC
+/// offset are in their `TZID` if it's a known time zone, or else in `tz`, and dates alone are due at the end
+/// of the day. A recurrence that items can't have is left out rather than rejected, so that apps can still
+/// save the rest of the task.
+pub fn parse_vtodo(contents: &str, tz: Tz) -> Result<Todo, String> {
+    let unfolded = contents.replace("\r\n ", "").replace("\r\n\t", "").replace("\n ", "").replace("\n\t", "");
+
+    let mut todo = Todo {
+        uid: None,
+        summary: String::new(),
+        description: None,
+        due_at: None,
+        priority: None,
+        recurrence: None,
+        categories: vec![],
+        completed: false,
+    };
+    let mut status: Option<String> = None;
+    let mut completed_at = false;
+
+    // How many components deep the current line is inside the VTODO, where 1 is the VTODO itself
+    let mut depth = 0;
+    let mut found = false;
+
+    for line in unfolded.lines().map(|line| line.trim_end_matches('\r')).filter(|line| !line.is_empty()) {
+        let ContentLine { name, params, value } = match content_line(line) {
+            Some(content_line) => content_line,
+            None => return Err(format!("`{}` is not an iCalendar content line", line)),
+        };
+
+        if depth == 0 {
+            if name == "BEGIN" && value.eq_ignore_ascii_case("VTODO") && !found {
+                depth = 1;
+                found = true;
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "BEGIN" => depth += 1,
+            "END" => depth -= 1,
+            _ if depth > 1 => {}
+            "UID" => todo.uid = Some(String::from(value)),
+            "SUMMARY" => todo.summary = unescape_text(value),
+            "DESCRIPTION" => todo.description = Some(unescape_text(value)).filter(|notes| !notes.trim().is_empty()),
+            "DUE" => todo.due_at = Some(parse_time(&params, value, tz)?),
+            "PRIORITY" => todo.priority = match value.trim() {
+                "1" | "2" | "3" | "4" => Some(String::from("high")),
+                "5" => Some(String::from("medium")),
+                "6" | "7" | "8" | "9" => Some(String::from("low")),
+                _ => None,
+            },
+            "RRULE" => todo.recurrence = recurrence(value),
+            "CATEGORIES" => {
+                for category in split_text(value).iter().map(|category| category.trim()).filter(|category| !category.is_empty()) {
+                    if !todo.categories.iter().any(|existing| existing == category) {
+                        todo.categories.push(String::from(category));
+                    }
+                }
+            }
+            "STATUS" => status = Some(value.to_ascii_uppercase()),
+            "COMPLETED" => completed_at = true,
+            _ => {}
+        }
+    }
+
+    if !found {
+        return Err(String::from("the calendar object has no VTODO"));
+    }
+    if todo.summary.trim().is_empty() {
+        return Err(String::from("the VTODO has no SUMMARY"));
+    }
+
+    todo.completed = match status {
+        Some(status) => status == "COMPLETED",
+        None => completed_at,
+    };
+    Ok(todo)
+}
+
+/// Gets the iCalendar priority for a priority level, where 1 is highest and 9 is lowest.
+fn priority_number(priority: &str) -> Option<u8> {
+    match priority {
+        "high" => Some(1),
+        "medium" => Some(5),
+        "low" => Some(9),
+        _ => None,
+    }
+}
+
+/// Writes a time in the UTC form of an iCalendar DATE-TIME, such as `20210820T090000Z`.
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Splits an unfolded content line into its name, its parameters and its value.
+fn content_line(line: &str) -> Option<ContentLine<'_>> {
+    // The value starts after the first colon that isn't inside a quoted parameter value
+    let mut quoted = false;
+    let colon = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    })?.0;
+
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+
+    Some(ContentLine {
+        name,
+        params,
+        value: &line[colon + 1..],
+    })
+}
+
+/// Parses the value of a DATE-TIME or DATE property with the given parameters.
+fn parse_time(params: &[(String, String)], value: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    let param = |key: &str| params.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str());
+
+    if param("VALUE") == Some("DATE") || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(|date| quick_add::local_time(tz, date, NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+            .map_err(|_| format!("`{}` is not an iCalendar date", value));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|time| Utc.from_utc_datetime(&time))
+            .map_err(|_| format!("`{}` is not an iCalendar time", value));
+    }
+
+    let tz = param("TZID").and_then(|tzid| tzid.parse::<Tz>().ok()).unwrap_or(tz);
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map(|time| quick_add::local_time(tz, time.date(), time.time()))
+        .map_err(|_| format!("`{}` is not an iCalendar time", value))
+}
+
+/// Turns an RRULE into one that items can have, keeping only its `FREQ`, `INTERVAL` and `BYDAY` in that order.
+/// Rules with anything else that changes when they repeat can't be kept.
+fn recurrence(rule: &str) -> Option<String> {
+    let mut frequency = None;
+    let mut interval = None;
+    let mut day = None;
+
+    for part in rule.trim().split(';').filter(|part| !part.is_empty()) {
+        match part.split_once('=')? {
+            ("FREQ", value) => frequency = Some(value),
+            ("INTERVAL", "1") => {}
+            ("INTERVAL", value) => interval = Some(value),
+            ("BYDAY", value) => day = Some(value),
+            ("WKST", _) => {}
+            _ => return None,
+        }
+    }
+
+    let mut recurrence = format!("FREQ={}", frequency?);
+    if let Some(interval) = interval {
+        recurrence.push_str(&format!(";INTERVAL={}", interval));
+    }
+    if let Some(day) = day {
+        recurrence.push_str(&format!(";BYDAY={}", day));
+    }
+
+    Some(recurrence).filter(|recurrence| import::is_valid_recurrence(recurrence))
+}
+
+/// Escapes the characters that have a meaning in iCalendar TEXT values.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Undoes the escaping of an iCalendar TEXT value.
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Splits a list of iCalendar TEXT values on the commas that aren't escaped, unescaping each value.
+fn split_text(text: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                values.push(unescape_text(&text[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    values.push(unescape_text(&text[start..]));
+    values
+}
+
+/// Folds a content `line` so that no line is longer than `MAX_LINE_OCTETS`, ending every line with CRLF.
+/// Lines are only split between characters, never inside one.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn item(due_at: Option<DateTime<Utc>>, recurrence: Option<&str>) -> Item {
+        let created_at = Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2021, 8, 1).unwrap().and_hms_opt(8, 0, 0).unwrap());
+
+        Item {
+            item_id: 1,
+            list_id: 1,
+            description: String::from("Pay rent"),
+            finished: false,
+            notes: None,
+            assignee_id: None,
+            created_at,
+            updated_at: created_at,
+            completed_at: None,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+            due_at,
+            priority: None,
+            recurrence: recurrence.map(String::from),
+            ical_uid: None,
+            dav_name: None,
+            uuid: Uuid::nil(),
+            list_uuid: Uuid::nil(),
+        }
+    }
+
+    fn due() -> DateTime<Utc> {
+        Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2021, 8, 20).unwrap().and_hms_opt(9, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn recurring_items_start_when_due() {
+        let lines = vtodo(&item(Some(due()), Some("FREQ=MONTHLY")), &[], Utc::now());
+
+        assert!(lines.contains(&String::from("DTSTART:20210820T090000Z")));
+        assert!(lines.contains(&String::from("DUE:20210820T090000Z")));
+        assert!(lines.contains(&String::from("RRULE:FREQ=MONTHLY")));
+    }
+
+    #[test]
+    fn recurring_items_without_a_due_date_start_when_created() {
+        let lines = vtodo(&item(None, Some("FREQ=DAILY")), &[], Utc::now());
+
+        assert!(lines.contains(&String::from("DTSTART:20210801T080000Z")));
+        assert!(!lines.iter().any(|line| line.starts_with("DUE:")));
+    }
+
+    #[test]
+    fn items_that_dont_repeat_have_no_start() {
+        let lines = vtodo(&item(Some(due()), None), &[], Utc::now());
+
+        assert!(!lines.iter().any(|line| line.starts_with("DTSTART:") || line.starts_with("RRULE:")));
+    }
+
+    #[test]
+    fn written_recurrences_parse_back() {
+        let object = object(&item(Some(due()), Some("FREQ=WEEKLY;BYDAY=FR")), &[], Utc::now());
+        let todo = parse_vtodo(&object, Tz::UTC).unwrap();
+
+        assert_eq!(todo.due_at, Some(due()));
+        assert_eq!(todo.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=FR"));
+    }
+}
@@ -0,0 +1,555 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use crate::export;
+use crate::models::portable::{ListFormat, PORTABLE_FORMAT, PORTABLE_VERSION, PortableItem, PortableList, SkippedLine};
+use crate::quick_add;
+
+/// An item read from an imported file, along with the line it was read from.
+pub struct ImportedItem {
+    pub line: usize,
+    pub text: String,
+    pub item: PortableItem,
+}
+
+/// A list read from an imported file. The `name` is only known if the file gives one.
+#[derive(Default)]
+pub struct ImportedList {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub items: Vec<ImportedItem>,
+    pub skipped: Vec<SkippedLine>,
+}
+
+impl ImportedList {
+    /// Adds the `item` read from `line` if its description, priority and recurrence are valid, or records the
+    /// line as skipped otherwise. Imported items are created at the time of the import, so the times they were
+    /// created and completed in the file are dropped.
+    fn accept(&mut self, line: usize, text: &str, mut item: PortableItem) {
+        item.description = item.description.trim().to_string();
+        item.created_at = None;
+        item.completed_at = None;
+
+        match check_item(&item) {
+            Err(reason) => self.skip(line, text, reason),
+            Ok(()) => self.items.push(ImportedItem {
+                line,
+                text: String::from(text),
+                item,
+            }),
+        }
+    }
+
+    /// Records that `line` was left out for the given `reason`.
+    pub fn skip(&mut self, line: usize, text: &str, reason: String) {
+        self.skipped.push(SkippedLine {
+            line,
+            text: String::from(text),
+            reason,
+        });
+    }
+}
+
+/// Checks that the description, priority and recurrence of an imported `item` are valid. Returns the reason
+/// if they aren't.
+fn check_item(item: &PortableItem) -> Result<(), String> {
+    if item.description.is_empty() {
+        return Err(String::from("the item has no description"));
+    }
+
+    if !matches!(item.priority.as_deref(), None | Some("low" | "medium" | "high")) {
+        return Err(String::from("the priority must be low, medium or high"));
+    }
+
+    match item.recurrence.as_deref() {
+        Some(rule) if !is_valid_recurrence(rule) => Err(format!("`{}` is not a supported recurrence", rule)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a list from the `contents` of a file in the given `format`. Dates without a time zone are in the
+/// time zone `tz`, and relative dates in Markdown items are resolved at the time `now`. Lines that can't be
+/// read as items are skipped, but if the file as a whole can't be read, returns the reason.
+pub fn parse(contents: &str, format: ListFormat, tz: Tz, now: DateTime<Utc>) -> Result<ImportedList, String> {
+    match format {
+        ListFormat::Json => from_json(contents),
+        ListFormat::Csv => from_csv(contents, tz),
+        ListFormat::Markdown => Ok(from_markdown(contents, tz, now)),
+        ListFormat::TodoTxt => Ok(from_todo_txt(contents, tz)),
+    }
+}
+
+/// Reads a list in the portable JSON format written by exports.
+fn from_json(contents: &str) -> Result<ImportedList, String> {
+    let list: PortableList = serde_json::from_str(contents).map_err(|error| error.to_string())?;
+    if list.format != PORTABLE_FORMAT {
+        return Err(format!("the `format` of the file must be `{}`", PORTABLE_FORMAT));
+    }
+
+    if list.version > PORTABLE_VERSION {
+        return Err(format!("the file is version {}, but only versions up to {} can be imported", list.version, PORTABLE_VERSION));
+    }
+
+    let mut imported = ImportedList {
+        name: Some(list.name),
+        description: list.description,
+        ..ImportedList::default()
+    };
+
+    for (index, item) in list.items.into_iter().enumerate() {
+        let text = item.description.clone();
+        imported.accept(index + 1, &text, item);
+    }
+
+    Ok(imported)
+}
+
+/// Reads a CSV file with a header row, such as one written by exports or another app. The columns are found
+/// by name, and only the description is required. Tags are separated by commas.
+fn from_csv(contents: &str, tz: Tz) -> Result<ImportedList, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers: Vec<String> = reader.headers()
+        .map_err(|error| error.to_string())?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|header| names.contains(&header.as_str()));
+
+    let columns = CsvColumns {
+        description: column(&["description", "content", "title", "task", "name"])
+            .ok_or_else(|| String::from("the CSV file needs a `description` column"))?,
+        finished: column(&["finished", "completed", "done", "status"]),
+        notes: column(&["notes", "note"]),
+        due_at: column(&["due_at", "due", "due_date", "date"]),
+        priority: column(&["priority"]),
+        recurrence: column(&["recurrence", "rrule"]),
+        tags: column(&["tags", "labels"]),
+        assignee: column(&["assignee", "responsible"]),
+    };
+
+    let mut imported = ImportedList::default();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                let line = error.position().map_or(0, |position| position.line() as usize);
+                imported.skip(line, "", error.to_string());
+                continue;
+            }
+        };
+
+        let line = record.position().map_or(0, |position| position.line() as usize);
+        let text = record.iter().collect::<Vec<&str>>().join(",");
+
+        match columns.read(&record, tz) {
+            Ok(item) => imported.accept(line, &text, item),
+            Err(reason) => imported.skip(line, &text, reason),
+        }
+    }
+
+    Ok(imported)
+}
+
+/// The positions of the columns of a CSV file that hold each field of an item.
+struct CsvColumns {
+    description: usize,
+    finished: Option<usize>,
+    notes: Option<usize>,
+    due_at: Option<usize>,
+    priority: Option<usize>,
+    recurrence: Option<usize>,
+    tags: Option<usize>,
+    assignee: Option<usize>,
+}
+
+impl CsvColumns {
+    /// Reads an item from a `record` of the CSV file, returning the reason if it can't be read.
+    fn read(&self, record: &csv::StringRecord, tz: Tz) -> Result<PortableItem, String> {
+        let field = |column: Option<usize>| column
+            .and_then(|column| record.get(column))
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+
+        let text = |column: Option<usize>| field(column).map(unescape_formula);
+
+        Ok(PortableItem {
+            description: String::from(text(Some(self.description)).unwrap_or_default()),
+            finished: field(self.finished).map(parse_finished).transpose()?.unwrap_or(false),
+            notes: text(self.notes).map(String::from),
+            due_at: field(self.due_at).map(|value| parse_time(value, tz)).transpose()?,
+            priority: field(self.priority).map(parse_priority).transpose()?,
+            recurrence: field(self.recurrence).map(|value| value.trim_start_matches("RRULE:").to_uppercase()),
+            tags: text(self.tags).map(split_tags).unwrap_or_default(),
+            assignee: text(self.assignee).map(|value| value.trim_start_matches('@').to_string()),
+            created_at: None,
+            completed_at: None,
+        })
+    }
+}
+
+/// Removes the `'` that exports put before CSV cells that a spreadsheet would read as a formula.
+fn unescape_formula(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(&export::FORMULA_PREFIXES[..]) => rest,
+        _ => value,
+    }
+}
+
+/// Reads a Markdown checklist such as one written by exports. The first heading is the name of the list and
+/// anything before the first item is its description. Each `- [ ]` or `- [x]` item is read like quick-add, so
+/// that `!high #bills @alice due 2021-08-20` is understood, and the indented lines below it are its notes.
+/// Anything else after the first item is skipped.
+fn from_markdown(contents: &str, tz: Tz, now: DateTime<Utc>) -> ImportedList {
+    let mut imported = ImportedList::default();
+    let mut description: Vec<&str> = vec![];
+    let mut current: Option<(usize, String, PortableItem, Vec<String>)> = None;
+    let mut seen_item = false;
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw_line.trim();
+
+        if let Some((finished, text)) = checklist_item(trimmed) {
+            if let Some((line, text, item, notes)) = current.take() {
+                imported.accept(line, &text, with_notes(item, notes));
+            }
+
+            let parsed = quick_add::parse(text, tz, now);
+            let item = PortableItem {
+                description: parsed.description,
+                finished,
+                notes: None,
+                due_at: parsed.due_at,
+                priority: parsed.priority,
+                recurrence: parsed.recurrence,
+                tags: parsed.tags,
+                assignee: parsed.assignee,
+                created_at: None,
+                completed_at: None,
+            };
+
+            current = Some((line, String::from(trimmed), item, vec![]));
+            seen_item = true;
+        } else if current.is_some() && (trimmed.is_empty() || raw_line.starts_with(char::is_whitespace)) {
+            if let Some((_, _, _, notes)) = &mut current {
+                notes.push(unindent(raw_line));
+            }
+        } else if seen_item {
+            if let Some((line, text, item, notes)) = current.take() {
+                imported.accept(line, &text, with_notes(item, notes));
+            }
+
+            if !trimmed.is_empty() {
+                imported.skip(line, trimmed, String::from("only checklist items can follow the first item"));
+            }
+        } else if imported.name.is_none() && trimmed.starts_with("# ") {
+            imported.name = Some(String::from(trimmed[2..].trim()));
+        } else {
+            description.push(raw_line);
+        }
+    }
+
+    if let Some((line, text, item, notes)) = current.take() {
+        imported.accept(line, &text, with_notes(item, notes));
+    }
+
+    let description = description.join("\n").trim().to_string();
+    if !description.is_empty() {
+        imported.description = Some(description);
+    }
+
+    imported
+}
+
+/// Reads a todo.txt file such as one written by exports. Priorities `(A)` to `(C)` become high, medium and low
+/// and the rest low, the first `+project` names the list, `@contexts` become tags, and `due:`, `rec:`, `pri:`
+/// and `assignee:` are understood. Lines that can't be read are skipped.
+fn from_todo_txt(contents: &str, tz: Tz) -> ImportedList {
+    let mut imported = ImportedList::default();
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        match parse_todo_txt_line(trimmed, tz) {
+            Ok((item, project)) => {
+                if imported.name.is_none() {
+                    imported.name = project;
+                }
+
+                imported.accept(line, trimmed, item);
+            }
+            Err(reason) => imported.skip(line, trimmed, reason),
+        }
+    }
+
+    imported
+}
+
+/// Reads a single todo.txt line into an item, along with its first project. Underscores in the project are
+/// read as spaces, since exports write them in place of spaces.
+fn parse_todo_txt_line(line: &str, tz: Tz) -> Result<(PortableItem, Option<String>), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let mut i = 0;
+
+    // The completion and creation dates are read past, since imported items are created at the time of the import
+    let finished = words[0] == "x";
+    if finished {
+        i += 1;
+        if words.get(i).and_then(|word| parse_date(word)).is_some() {
+            i += 1;
+        }
+    }
+
+    let mut priority = None;
+    if let Some(letter) = words.get(i).and_then(|word| todo_txt_priority(word)) {
+        priority = Some(letter);
+        i += 1;
+    }
+
+    if words.get(i).and_then(|word| parse_date(word)).is_some() {
+        i += 1;
+    }
+
+    let mut description = vec![];
+    let mut project = None;
+    let mut tags: Vec<String> = vec![];
+    let mut due_at = None;
+    let mut recurrence = None;
+    let mut assignee = None;
+
+    for word in &words[i..] {
+        if let Some(name) = word.strip_prefix('+').filter(|name| !name.is_empty()) {
+            if project.is_none() {
+                project = Some(name.replace('_', " "));
+            }
+        } else if let Some(tag) = word.strip_prefix('@').filter(|tag| !tag.is_empty()) {
+            if !tags.iter().any(|existing| existing == tag) {
+                tags.push(String::from(tag));
+            }
+        } else if let Some(value) = word.strip_prefix("due:") {
+            let date = parse_date(value).ok_or_else(|| format!("`{}` is not a date", word))?;
+            due_at = Some(quick_add::local_time(tz, date, NaiveTime::from_hms_opt(23, 59, 59).unwrap()));
+        } else if let Some(value) = word.strip_prefix("rec:") {
+            recurrence = Some(parse_todo_txt_recurrence(value).ok_or_else(|| format!("`{}` is not a supported recurrence", word))?);
+        } else if let Some(value) = word.strip_prefix("pri:") {
+            let level = todo_txt_priority(&format!("({})", value)).ok_or_else(|| format!("`{}` is not a priority", word))?;
+            priority.get_or_insert(level);
+        } else if let Some(value) = word.strip_prefix("assignee:").filter(|value| !value.is_empty()) {
+            assignee = Some(String::from(value));
+        } else {
+            description.push(*word);
+        }
+    }
+
+    let item = PortableItem {
+        description: description.join(" "),
+        finished,
+        notes: None,
+        due_at,
+        priority,
+        recurrence,
+        tags,
+        assignee,
+        created_at: None,
+        completed_at: None,
+    };
+
+    Ok((item, project))
+}
+
+/// Parses a todo.txt priority such as `(A)`, where `A` is high, `B` is medium and the rest are low.
+fn todo_txt_priority(word: &str) -> Option<String> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+
+    match letter {
+        "A" => Some(String::from("high")),
+        "B" => Some(String::from("medium")),
+        _ if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Some(String::from("low")),
+        _ => None,
+    }
+}
+
+/// Parses a todo.txt `rec:` value such as `1m` or `+2w` into an RRULE. Business days aren't supported.
+fn parse_todo_txt_recurrence(value: &str) -> Option<String> {
+    let value = value.trim_start_matches('+');
+    let frequency = match value.chars().last()? {
+        'd' => "DAILY",
+        'w' => "WEEKLY",
+        'm' => "MONTHLY",
+        'y' => "YEARLY",
+        _ => return None,
+    };
+
+    let count = &value[..value.len() - 1];
+    let interval: u32 = if count.is_empty() { 1 } else { count.parse().ok()? };
+
+    match interval {
+        0 => None,
+        1 => Some(format!("FREQ={}", frequency)),
+        _ => Some(format!("FREQ={};INTERVAL={}", frequency, interval)),
+    }
+}
+
+/// Checks that `rule` is an RRULE that items can have, with a `FREQ`, then optionally an `INTERVAL` and a
+/// single `BYDAY`.
+pub fn is_valid_recurrence(rule: &str) -> bool {
+    let mut parts = rule.split(';').peekable();
+
+    let valid_frequency = matches!(parts.next(), Some("FREQ=DAILY" | "FREQ=WEEKLY" | "FREQ=MONTHLY" | "FREQ=YEARLY"));
+    if let Some(interval) = parts.peek().and_then(|part| part.strip_prefix("INTERVAL=")) {
+        if interval.starts_with('0') || interval.is_empty() || !interval.chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+        parts.next();
+    }
+    if let Some(day) = parts.peek().and_then(|part| part.strip_prefix("BYDAY=")) {
+        if !matches!(day, "MO" | "TU" | "WE" | "TH" | "FR" | "SA" | "SU") {
+            return false;
+        }
+        parts.next();
+    }
+
+    valid_frequency && parts.next().is_none()
+}
+
+/// Checks whether `line` is a Markdown checklist item such as `- [x] Buy milk`, returning whether it's checked
+/// and its text.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line.strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?
+        .trim_start();
+
+    let finished = if rest.starts_with("[ ]") {
+        false
+    } else if rest.starts_with("[x]") || rest.starts_with("[X]") {
+        true
+    } else {
+        return None;
+    };
+
+    Some((finished, rest[3..].trim()))
+}
+
+/// Adds the indented `notes` lines read below a Markdown item to it.
+fn with_notes(mut item: PortableItem, notes: Vec<String>) -> PortableItem {
+    let notes = notes.join("\n").trim_end().to_string();
+    if !notes.is_empty() {
+        item.notes = Some(notes);
+    }
+
+    item
+}
+
+/// Removes up to four spaces or a tab of indentation from the start of `line`.
+fn unindent(line: &str) -> String {
+    if let Some(rest) = line.strip_prefix('\t') {
+        return String::from(rest);
+    }
+
+    let spaces = line.chars().take(4).take_while(|c| *c == ' ').count();
+    String::from(&line[spaces..])
+}
+
+/// Parses whether an item is finished, such as `true`, `yes`, `x` or `done`.
+fn parse_finished(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" | "x" | "done" | "completed" | "finished" => Ok(true),
+        "false" | "no" | "n" | "0" | "todo" | "open" | "unfinished" => Ok(false),
+        _ => Err(format!("`{}` is not a finished state", value)),
+    }
+}
+
+/// Parses a priority given by name, as a todo.txt letter or as a number where 1 is highest.
+fn parse_priority(value: &str) -> Result<String, String> {
+    match value.to_lowercase().as_str() {
+        "high" | "a" | "1" => Ok(String::from("high")),
+        "medium" | "b" | "2" => Ok(String::from("medium")),
+        "low" | "c" | "3" => Ok(String::from("low")),
+        _ => Err(format!("`{}` is not a priority", value)),
+    }
+}
+
+/// Parses a time in RFC 3339, or a local date with an optional time in the time zone `tz`. Dates without a
+/// time are at the end of the day.
+fn parse_time(value: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    for pattern in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(value, pattern) {
+            return Ok(quick_add::local_time(tz, local.date(), local.time()));
+        }
+    }
+
+    match parse_date(value) {
+        Some(date) => Ok(quick_add::local_time(tz, date, NaiveTime::from_hms_opt(23, 59, 59).unwrap())),
+        None => Err(format!("`{}` is not a date", value)),
+    }
+}
+
+/// Parses a date such as `2021-08-20`.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Splits a comma separated list of tags, leaving out any leading `#`.
+fn split_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in value.split(',').map(|tag| tag.trim().trim_start_matches('#')).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|existing| existing == tag) {
+            tags.push(String::from(tag));
+        }
+    }
+
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn import_markdown(contents: &str) -> ImportedList {
+        let now = Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2021, 8, 2).unwrap().and_hms_opt(10, 0, 0).unwrap());
+        parse(contents, ListFormat::Markdown, Tz::UTC, now).unwrap()
+    }
+
+    #[test]
+    fn reads_markdown_checklists() {
+        let imported = import_markdown("# Groceries\nFor the weekend\n\n- [ ] Buy milk tomorrow !high #dairy\n    Semi-skimmed\n- [x] Eggs\nNot an item\n");
+
+        assert_eq!(imported.name.as_deref(), Some("Groceries"));
+        assert_eq!(imported.description.as_deref(), Some("For the weekend"));
+        assert_eq!(imported.items.len(), 2);
+        assert_eq!(imported.items[0].item.description, "Buy milk");
+        assert_eq!(imported.items[0].item.priority.as_deref(), Some("high"));
+        assert_eq!(imported.items[0].item.tags, vec!["dairy"]);
+        assert_eq!(imported.items[0].item.notes.as_deref(), Some("Semi-skimmed"));
+        assert!(imported.items[0].item.due_at.is_some());
+        assert!(imported.items[1].item.finished);
+        assert_eq!(imported.skipped.len(), 1);
+        assert_eq!(imported.skipped[0].line, 7);
+    }
+
+    #[test]
+    fn overflowing_dates_in_markdown_stay_in_the_description() {
+        let imported = import_markdown("- [ ] x in 999999999 years\n- [ ] y in 100000000 days\n- [ ] Buy milk\n");
+
+        assert_eq!(imported.items.len(), 3);
+        assert_eq!(imported.items[0].item.description, "x in 999999999 years");
+        assert_eq!(imported.items[0].item.due_at, None);
+        assert_eq!(imported.items[1].item.description, "y in 100000000 days");
+        assert_eq!(imported.items[1].item.due_at, None);
+        assert!(imported.skipped.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use rocket::catcher::{self, Catcher};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::status;
+use rocket::response::Responder;
+use rocket::serde::json::Json;
+use uuid::Uuid;
+
+use crate::database::PostgresDbConn;
+use crate::models::response::ResponseWithStatus;
+use crate::services::list_service;
+
+/// The serial key of the list that a route under `/lists/<list_id>` refers to by its public id, which is the second
+/// segment of the path. Routes that take it leave that segment unnamed, as `/lists/<_>`. A request for a list that
+/// doesn't exist is answered with the response from `list_service::find_list_id`.
+pub struct ListKey {
+    pub list_id: i32,
+}
+
+/// The serial keys of the list and item that a route under `/lists/<list_id>/<item_id>` refers to by their public
+/// ids, which are the second and third segments of the path. Routes that take them leave those segments unnamed, as
+/// `/lists/<_>/<_>`. A request for a list or item that doesn't exist is answered with the response from
+/// `list_service::find_item_ids`.
+pub struct ItemKeys {
+    pub list_id: i32,
+    pub item_id: i32,
+}
+
+/// The response to send for a request that was turned away because what it refers to doesn't exist.
+struct Rejection(Option<ResponseWithStatus>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ListKey {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let list_id = match request.param::<Uuid>(1) {
+            Some(Ok(list_id)) => list_id,
+            _ => return Outcome::Forward(()),
+        };
+
+        let db = match request.guard::<PostgresDbConn>().await {
+            Outcome::Success(db) => db,
+            Outcome::Failure((status, _)) => return Outcome::Failure((status, ())),
+            Outcome::Forward(_) => return Outcome::Forward(()),
+        };
+
+        match list_service::find_list_id(list_id, &db).await {
+            Ok(list_id) => Outcome::Success(ListKey { list_id }),
+            Err(response) => reject(request, response),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ItemKeys {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let (list_id, item_id) = match (request.param::<Uuid>(1), request.param::<Uuid>(2)) {
+            (Some(Ok(list_id)), Some(Ok(item_id))) => (list_id, item_id),
+            _ => return Outcome::Forward(()),
+        };
+
+        let db = match request.guard::<PostgresDbConn>().await {
+            Outcome::Success(db) => db,
+            Outcome::Failure((status, _)) => return Outcome::Failure((status, ())),
+            Outcome::Forward(_) => return Outcome::Forward(()),
+        };
+
+        match list_service::find_item_ids(list_id, item_id, &db).await {
+            Ok((list_id, item_id)) => Outcome::Success(ItemKeys { list_id, item_id }),
+            Err(response) => reject(request, response),
+        }
+    }
+}
+
+/// Turns the `request` away, keeping the `response` for the catcher to send.
+fn reject<T>(request: &Request<'_>, response: ResponseWithStatus) -> Outcome<T, ()> {
+    let status = Status::from_code(response.status_code).unwrap();
+    request.local_cache(|| Rejection(Some(response)));
+
+    Outcome::Failure((status, ()))
+}
+
+/// Builds the catcher for requests with the same status as the responses of `ListKey` and `ItemKeys`, which sends the
+/// response that the request was turned away with, or Rocket's own response for any other request.
+pub fn catcher() -> Catcher {
+    fn handler<'r>(status: Status, request: &'r Request<'_>) -> catcher::BoxFuture<'r> {
+        Box::pin(async move {
+            match &request.local_cache(|| Rejection(None)).0 {
+                Some(rejection) => status::Custom(status, Json(&rejection.response)).respond_to(request),
+                None => Catcher::default().handler.handle(status, request).await,
+            }
+        })
+    }
+
+    Catcher::new(Status::BadRequest.code, handler)
+}
@@ -5,7 +5,18 @@ extern crate rocket;
 #[macro_use]
 extern crate serde;
 
+use std::net::SocketAddr;
+use std::sync::Arc;
+
 use rocket::{Build, Request};
+use rocket::fairing::AdHoc;
+
+use crate::addresses::AddressPolicy;
+use crate::database::PostgresDbConn;
+use crate::events::EventBus;
+use crate::services::{attachment_service, sync_service, trash_service, webhook_service};
+use crate::storage::Storage;
+use crate::storage::local::LocalStorage;
 
 mod database;
 mod models;
@@ -14,6 +25,21 @@ mod schema;
 mod auth;
 mod constants;
 mod services;
+mod markdown;
+mod storage;
+mod etag;
+mod quick_add;
+mod export;
+mod import;
+mod ical;
+mod caldav;
+mod events;
+mod addresses;
+mod keys;
+#[cfg(test)]
+mod test_support;
+
+static EVENT_BUS_CAPACITY: usize = 1024; // Number of events kept for subscribers that fall behind
 
 #[catch(404)]
 fn not_found(request: &Request) -> String {
@@ -30,16 +56,173 @@ pub fn rocket() -> rocket::Rocket<Build> {
             routes::users::put_preferred_name,
             routes::users::put_password,
             routes::users::get_user,
+            routes::users::patch_user,
 
             routes::lists::create_list,
             routes::lists::get_lists,
             routes::lists::get_list,
+            routes::lists::get_item,
             routes::lists::put_list,
             routes::lists::put_item,
+            routes::lists::patch_list,
+            routes::lists::patch_item,
             routes::lists::delete_list,
             routes::lists::post_item,
+            routes::lists::quick_add_item,
             routes::lists::delete_item,
+            routes::lists::get_assigned_items,
+            routes::lists::move_item,
+            routes::lists::copy_item,
+            routes::lists::post_items,
+            routes::lists::put_all_finished,
+            routes::lists::delete_finished,
+            routes::lists::archive_list,
+            routes::lists::unarchive_list,
+            routes::lists::export_list,
+            routes::lists::import_list,
+
+            routes::comments::get_comments,
+            routes::comments::post_comment,
+            routes::comments::put_comment,
+            routes::comments::delete_comment,
+
+            routes::attachments::get_attachments,
+            routes::attachments::post_attachment,
+            routes::attachments::get_attachment,
+            routes::attachments::delete_attachment,
+
+            routes::trash::get_trash,
+            routes::trash::restore_list,
+            routes::trash::restore_item,
+            routes::trash::empty_trash,
+
+            routes::search::search,
+
+            routes::tags::get_tags,
+            routes::tags::create_tag,
+            routes::tags::put_tag,
+            routes::tags::delete_tag,
+            routes::tags::get_tagged_items,
+            routes::tags::get_item_tags,
+            routes::tags::tag_item,
+            routes::tags::untag_item,
+
+            routes::smart_lists::get_smart_lists,
+            routes::smart_lists::create_smart_list,
+            routes::smart_lists::get_today,
+            routes::smart_lists::get_smart_list,
+            routes::smart_lists::put_smart_list,
+            routes::smart_lists::delete_smart_list,
+
+            routes::calendar::get_calendar_feed,
+            routes::calendar::regenerate_calendar_feed,
+            routes::calendar::delete_calendar_feed,
+            routes::calendar::get_calendar,
+
+            routes::app_passwords::get_app_passwords,
+            routes::app_passwords::create_app_password,
+            routes::app_passwords::delete_app_password,
+
+            routes::events::get_events,
+
+            routes::webhooks::get_webhooks,
+            routes::webhooks::create_webhook,
+            routes::webhooks::patch_webhook,
+            routes::webhooks::delete_webhook,
+            routes::webhooks::get_deliveries,
+
+            routes::sync::get_sync,
+            routes::sync::post_sync,
         ])
+        .manage(EventBus::new(EVENT_BUS_CAPACITY))
         .attach(database::PostgresDbConn::fairing())
+        .attach(AdHoc::try_on_ignite("Attachment Storage", |rocket| async {
+            // Attachments are kept on the local disk, in the directory named by `attachments_dir`
+            let root = rocket.figment()
+                .extract_inner::<String>("attachments_dir")
+                .unwrap_or_else(|_| String::from("attachments"));
+
+            match LocalStorage::new(root) {
+                Ok(storage) => Ok(rocket.manage::<Arc<dyn Storage>>(Arc::new(storage))),
+                Err(_) => Err(rocket),
+            }
+        }))
+        .attach(AdHoc::on_liftoff("Orphaned Attachment Cleanup", |rocket| Box::pin(async move {
+            let storage = rocket.state::<Arc<dyn Storage>>().cloned();
+            let pool = PostgresDbConn::pool(rocket).cloned();
+
+            if let (Some(storage), Some(pool)) = (storage, pool) {
+                rocket::tokio::spawn(attachment_service::clean_up_orphaned_files(storage, pool));
+            }
+        })))
+        .attach(AdHoc::on_liftoff("Trash Purge", |rocket| Box::pin(async move {
+            // Deleted lists and items are purged once they have been in the trash for `trash_retention_days`
+            let retention_days = rocket.figment()
+                .extract_inner::<i64>("trash_retention_days")
+                .unwrap_or(30);
+
+            if let Some(pool) = PostgresDbConn::pool(rocket).cloned() {
+                rocket::tokio::spawn(trash_service::purge_expired_trash(chrono::Duration::days(retention_days), pool));
+            }
+        })))
+        .attach(AdHoc::on_liftoff("Sync History Purge", |rocket| Box::pin(async move {
+            if let Some(pool) = PostgresDbConn::pool(rocket).cloned() {
+                rocket::tokio::spawn(sync_service::purge_expired_history(pool));
+            }
+        })))
+        .attach(AdHoc::on_liftoff("Event Listener", |rocket| Box::pin(async move {
+            // Events are relayed from the same database that the connection pool uses
+            let url = rocket.figment()
+                .extract_inner::<String>("databases.postgres_db.url")
+                .ok();
+            let events = rocket.state::<EventBus>().cloned();
+
+            if let (Some(url), Some(events), Some(pool)) = (url, events, PostgresDbConn::pool(rocket).cloned()) {
+                rocket::tokio::spawn(events.listen(url, pool));
+            }
+        })))
+        .attach(AdHoc::on_ignite("Webhook Addresses", |rocket| async {
+            // Webhooks can only be sent to public addresses, unless `webhook_allow_private_networks` is set
+            let allow_private = rocket.figment()
+                .extract_inner::<bool>("webhook_allow_private_networks")
+                .unwrap_or(false);
+
+            rocket.manage(AddressPolicy { allow_private })
+        }))
+        .attach(AdHoc::on_liftoff("Webhook Delivery", |rocket| Box::pin(async move {
+            let addresses = rocket.state::<AddressPolicy>().copied();
+
+            if let (Some(addresses), Some(pool)) = (addresses, PostgresDbConn::pool(rocket).cloned()) {
+                rocket::tokio::spawn(webhook_service::deliver_webhooks(addresses, pool));
+            }
+        })))
+        .attach(AdHoc::try_on_ignite("CalDAV Server", |rocket| async {
+            // CalDAV is only served when `caldav_port` is set, on the same address as the API. The port is bound
+            // here rather than at liftoff so that launching fails if it's taken.
+            let port = match rocket.figment().extract_inner::<u16>("caldav_port") {
+                Ok(port) => port,
+                Err(_) => return Ok(rocket),
+            };
+
+            let config = rocket.figment().extract::<rocket::Config>().ok();
+            let events = rocket.state::<EventBus>().cloned();
+            let (config, pool, events) = match (config, PostgresDbConn::pool(&rocket).cloned(), events) {
+                (Some(config), Some(pool), Some(events)) => (config, pool, events),
+                _ => return Err(rocket),
+            };
+
+            let address = SocketAddr::new(config.address, port);
+            match caldav::bind(address) {
+                Ok(server) => {
+                    rocket::tokio::spawn(caldav::serve(server, pool, events));
+                    Ok(rocket)
+                }
+                Err(error) => {
+                    log::error!("Could not serve CalDAV on {}: {}", address, error);
+                    Err(rocket)
+                }
+            }
+        }))
         .register("/api", catchers![not_found])
+        .register("/api", vec![keys::catcher()])
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+use pulldown_cmark::{escape, html, Options, Parser};
+
+/// The value of the `render` query parameter that requests HTML alongside the raw Markdown.
+pub const RENDER_HTML: &str = "html";
+
+/// Determines whether the given `render` query parameter asks for rendered HTML.
+pub fn is_html(render: &Option<String>) -> bool {
+    render.as_deref() == Some(RENDER_HTML)
+}
+
+/// Renders the given Markdown `source` to HTML, stripping anything that isn't safe to embed
+/// directly in a page (scripts, event handlers, unknown URL schemes, etc).
+pub fn render_html(source: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+
+    // Task list items render as disabled checkboxes, so those are the only inputs let through.
+    ammonia::Builder::default()
+        .add_tags(&["input"])
+        .add_tag_attributes("input", &["checked"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "")
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// Renders the given optional Markdown `source` to sanitized HTML, if it exists.
+pub fn render_optional_html(source: &Option<String>) -> Option<String> {
+    source.as_deref().map(render_html)
+}
+
+/// The characters that mark the start and end of each match in search highlights, before they're turned
+/// into HTML. Searches strip them from the text they highlight, since stored text can contain them.
+pub const HIGHLIGHT_START: char = '\u{2}';
+pub const HIGHLIGHT_END: char = '\u{3}';
+
+/// Turns plain `text` with matches between `HIGHLIGHT_START` and `HIGHLIGHT_END` into HTML, where the text
+/// is escaped and each match is wrapped in a `<mark>` element. Markers that don't start or end a match are
+/// dropped, so the elements are always balanced.
+pub fn highlight_html(text: &str) -> String {
+    let mut escaped = String::new();
+    escape::escape_html(&mut escaped, text).unwrap();
+
+    let mut highlighted = String::with_capacity(escaped.len());
+    let mut in_match = false;
+    for c in escaped.chars() {
+        match c {
+            HIGHLIGHT_START if !in_match => {
+                highlighted.push_str("<mark>");
+                in_match = true;
+            }
+            HIGHLIGHT_END if in_match => {
+                highlighted.push_str("</mark>");
+                in_match = false;
+            }
+            HIGHLIGHT_START | HIGHLIGHT_END => {}
+            c => highlighted.push(c),
+        }
+    }
+
+    if in_match {
+        highlighted.push_str("</mark>");
+    }
+
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_matches_in_escaped_text() {
+        let text = format!("Buy {}milk{} & <eggs>", HIGHLIGHT_START, HIGHLIGHT_END);
+
+        assert_eq!(highlight_html(&text), "Buy <mark>milk</mark> &amp; &lt;eggs&gt;");
+    }
+
+    #[test]
+    fn stray_markers_dont_unbalance_highlights() {
+        let text = format!("{}a{} {}milk{} b{}", HIGHLIGHT_END, HIGHLIGHT_START, HIGHLIGHT_START, HIGHLIGHT_END, HIGHLIGHT_START);
+
+        assert_eq!(highlight_html(&text), "a<mark> milk</mark> b<mark></mark>");
+        assert!(!highlight_html(&text).contains(&[HIGHLIGHT_START, HIGHLIGHT_END][..]));
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::schema::app_passwords;
+
+/// An object representing a full row in the app_passwords table, ie a password that a user made for an app
+/// that can only log in with HTTP Basic, such as a CalDAV client. The password itself is only shown once,
+/// when it's made, and only its hash is kept.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(app_password_id)]
+pub struct AppPassword {
+    pub app_password_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A transfer object with the name the user gives an app password, so they can tell which app uses it.
+#[derive(Serialize, Deserialize)]
+pub struct AppPasswordDTO {
+    pub name: String,
+}
+
+/// A newly made app password along with the password itself, which can't be found again later.
+#[derive(Serialize)]
+pub struct CreatedAppPassword {
+    #[serde(flatten)]
+    pub app_password: AppPassword,
+    pub password: String,
+}
+
+impl AppPassword {
+    /// Attempts to make a new app password with a random password for the user with `user_id`. Returns the
+    /// new row along with the password.
+    pub fn create_app_password(user_id: i32, name: &str, conn: &PgConnection) -> QueryResult<CreatedAppPassword> {
+        // Two random UUIDs give the password 244 random bits
+        let password = format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple());
+
+        let app_password = diesel::insert_into(app_passwords::table)
+            .values((
+                app_passwords::user_id.eq(user_id),
+                app_passwords::name.eq(name),
+                app_passwords::password_hash.eq(hash_password(&password)),
+            ))
+            .get_result(conn)?;
+
+        Ok(CreatedAppPassword {
+            app_password,
+            password,
+        })
+    }
+
+    /// Finds every app password of the user with `user_id`, oldest first.
+    pub fn find_app_passwords_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<Vec<AppPassword>> {
+        app_passwords::table
+            .filter(app_passwords::user_id.eq(user_id))
+            .order(app_passwords::app_password_id)
+            .load(conn)
+    }
+
+    /// Finds the id of the user with the given app `password`, if there is one, noting that it was just used.
+    pub fn find_user_id_by_password(password: &str, conn: &PgConnection) -> Option<i32> {
+        diesel::update(app_passwords::table.filter(app_passwords::password_hash.eq(hash_password(password))))
+            .set(app_passwords::last_used_at.eq(diesel::dsl::now))
+            .returning(app_passwords::user_id)
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Attempts to delete the app password with `app_password_id` belonging to the user with `user_id`, so that
+    /// it can't be used to log in anymore. Returns true if it was deleted or false otherwise.
+    pub fn delete_app_password(app_password_id: i32, user_id: i32, conn: &PgConnection) -> bool {
+        let deleted = diesel::delete(app_passwords::table
+            .filter(app_passwords::app_password_id.eq(app_password_id))
+            .filter(app_passwords::user_id.eq(user_id)))
+            .execute(conn);
+
+        matches!(deleted, Ok(count) if count > 0)
+    }
+}
+
+/// Hashes an app password for storing. A fast hash is enough, unlike for passwords that people choose, since
+/// app passwords are long random tokens.
+fn hash_password(password: &str) -> String {
+    format!("{:x}", Sha256::digest(password.as_bytes()))
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use rocket::fs::TempFile;
+
+use crate::schema::{attachments, users};
+use crate::schema::attachments::dsl;
+
+/// An object representing a full row in the attachments table. The file contents themselves live in
+/// the storage backend under `storage_key`.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(attachment_id)]
+pub struct Attachment {
+    pub attachment_id: i32,
+    #[serde(skip_serializing)]
+    pub item_id: i32,
+    pub uploader_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The multipart form used to upload a new attachment.
+#[derive(FromForm)]
+pub struct AttachmentUpload<'r> {
+    pub file: TempFile<'r>,
+}
+
+/// An attachment that can be inserted into the attachments table.
+#[derive(Insertable)]
+#[table_name = "attachments"]
+pub struct NewAttachment {
+    pub item_id: i32,
+    pub uploader_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+}
+
+impl Attachment {
+    /// Attempts to insert the given `attachment`. If the row is created, returns its contents,
+    /// otherwise returns none.
+    pub fn create_attachment(attachment: NewAttachment, conn: &PgConnection) -> Option<Attachment> {
+        diesel::insert_into(dsl::attachments)
+            .values(&attachment)
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Finds the `Attachment` with the given id, if it exists.
+    pub fn find_attachment_by_id(id: i32, conn: &PgConnection) -> Option<Attachment> {
+        dsl::attachments
+            .filter(dsl::attachment_id.eq(id))
+            .get_result::<Attachment>(conn)
+            .ok()
+    }
+
+    /// Finds all of the attachments on the item with the given `item_id`, oldest first.
+    pub fn find_attachments_for_item(item_id: i32, conn: &PgConnection) -> Vec<Attachment> {
+        let possible_attachments = dsl::attachments
+            .filter(dsl::item_id.eq(item_id))
+            .order(dsl::attachment_id)
+            .load::<Attachment>(conn);
+
+        match possible_attachments {
+            Ok(result_attachments) => result_attachments,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Locks the row of the user with `user_id` until the end of the current transaction, so that nothing else
+    /// can count or use up their storage quota until then.
+    pub fn lock_quota(user_id: i32, conn: &PgConnection) -> QueryResult<i32> {
+        users::table
+            .select(users::id)
+            .filter(users::id.eq(user_id))
+            .for_update()
+            .get_result::<i32>(conn)
+    }
+
+    /// Finds the total number of bytes that the user with `user_id` has uploaded, counting against
+    /// their storage quota.
+    pub fn total_bytes_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<i64> {
+        // SUM over a BIGINT is a NUMERIC in Postgres, so cast it back down
+        dsl::attachments
+            .filter(dsl::uploader_id.eq(user_id))
+            .select(sql::<BigInt>("COALESCE(SUM(size_bytes), 0)::BIGINT"))
+            .get_result::<i64>(conn)
+    }
+
+    /// Finds the total number of bytes attached to the item with `item_id`.
+    pub fn total_bytes_for_item(item_id: i32, conn: &PgConnection) -> QueryResult<i64> {
+        dsl::attachments
+            .filter(dsl::item_id.eq(item_id))
+            .select(sql::<BigInt>("COALESCE(SUM(size_bytes), 0)::BIGINT"))
+            .get_result::<i64>(conn)
+    }
+
+    /// Finds the storage key of every attachment, so that files without a row can be found.
+    pub fn find_all_storage_keys(conn: &PgConnection) -> QueryResult<Vec<String>> {
+        dsl::attachments
+            .select(dsl::storage_key)
+            .load::<String>(conn)
+    }
+
+    /// Attempts to delete the attachment with the given `id`. Returns true if successful or
+    /// false otherwise.
+    pub fn delete_attachment(id: i32, conn: &PgConnection) -> bool {
+        diesel::delete(dsl::attachments.filter(dsl::attachment_id.eq(id)))
+            .execute(conn)
+            .is_ok()
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::calendar_feeds;
+
+/// An object representing a full row in the calendar_feeds table, ie the secret token in the URL of a
+/// user's calendar feed. Anyone with the token can read the feed, so it's only shown to its user.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(user_id)]
+pub struct CalendarFeed {
+    pub user_id: i32,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The calendar feed of a user as shown to them, with the path to subscribe to relative to the server.
+#[derive(Serialize, Deserialize)]
+pub struct CalendarFeedInfo {
+    pub token: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<CalendarFeed> for CalendarFeedInfo {
+    fn from(feed: CalendarFeed) -> CalendarFeedInfo {
+        CalendarFeedInfo {
+            path: format!("/api/calendar/{}.ics", feed.token),
+            token: feed.token,
+            created_at: feed.created_at,
+        }
+    }
+}
+
+impl CalendarFeed {
+    /// Finds the calendar feed of the user with `user_id`, if they have one.
+    pub fn find_feed_for_user(user_id: i32, conn: &PgConnection) -> Option<CalendarFeed> {
+        calendar_feeds::table
+            .filter(calendar_feeds::user_id.eq(user_id))
+            .get_result::<CalendarFeed>(conn)
+            .ok()
+    }
+
+    /// Finds the calendar feed with the given secret `token`, if there is one.
+    pub fn find_feed_by_token(token: &str, conn: &PgConnection) -> Option<CalendarFeed> {
+        calendar_feeds::table
+            .filter(calendar_feeds::token.eq(token))
+            .get_result::<CalendarFeed>(conn)
+            .ok()
+    }
+
+    /// Attempts to give the user with `user_id` a calendar feed with a new random token, replacing the token
+    /// of the feed they already have. Returns the new feed.
+    pub fn regenerate_feed(user_id: i32, conn: &PgConnection) -> QueryResult<CalendarFeed> {
+        // Two random UUIDs give the token 244 random bits
+        let token = format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple());
+
+        diesel::insert_into(calendar_feeds::table)
+            .values((
+                calendar_feeds::user_id.eq(user_id),
+                calendar_feeds::token.eq(&token),
+            ))
+            .on_conflict(calendar_feeds::user_id)
+            .do_update()
+            .set((
+                calendar_feeds::token.eq(&token),
+                calendar_feeds::created_at.eq(diesel::dsl::now),
+            ))
+            .get_result(conn)
+    }
+
+    /// Attempts to delete the calendar feed of the user with `user_id`, so that its URL stops working.
+    /// Returns true if successful or false otherwise.
+    pub fn delete_feed(user_id: i32, conn: &PgConnection) -> bool {
+        diesel::delete(calendar_feeds::table.filter(calendar_feeds::user_id.eq(user_id)))
+            .execute(conn)
+            .is_ok()
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+use crate::schema::comments;
+use crate::schema::comments::dsl;
+
+/// An object representing a full row in the comments table, ie a single message in the discussion
+/// thread of an item.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(comment_id)]
+pub struct Comment {
+    pub comment_id: i32,
+    #[serde(skip_serializing)]
+    pub item_id: i32,
+    pub author_id: i32,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A transfer object with the information the user provides to write or edit a comment.
+#[derive(Serialize, Deserialize)]
+pub struct CommentDTO {
+    pub body: String,
+}
+
+/// A comment that can be inserted into the comments table.
+#[derive(Insertable)]
+#[table_name = "comments"]
+pub struct NewComment {
+    pub item_id: i32,
+    pub author_id: i32,
+    pub body: String,
+}
+
+impl Comment {
+    /// Attempts to insert the given `comment` on the item with `item_id`, written by the user with
+    /// `author_id`. If the row is created, returns its contents, otherwise returns none.
+    pub fn create_comment(comment: CommentDTO, item_id: i32, author_id: i32, conn: &PgConnection) -> Option<Comment> {
+        let comment = NewComment {
+            item_id,
+            author_id,
+            body: comment.body,
+        };
+
+        diesel::insert_into(dsl::comments)
+            .values(&comment)
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Finds the `Comment` with the given id, if it exists.
+    pub fn find_comment_by_id(id: i32, conn: &PgConnection) -> Option<Comment> {
+        dsl::comments
+            .filter(dsl::comment_id.eq(id))
+            .get_result::<Comment>(conn)
+            .ok()
+    }
+
+    /// Finds all of the comments on the item with the given `item_id`, oldest first.
+    pub fn find_comments_for_item(item_id: i32, conn: &PgConnection) -> Vec<Comment> {
+        let possible_comments = dsl::comments
+            .filter(dsl::item_id.eq(item_id))
+            .order((dsl::created_at, dsl::comment_id))
+            .load::<Comment>(conn);
+
+        match possible_comments {
+            Ok(result_comments) => result_comments,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Attempts to copy every comment on the item with `from_item_id` onto the item with `to_item_id`,
+    /// keeping their authors and timestamps. Returns the number of comments copied.
+    pub fn copy_comments(from_item_id: i32, to_item_id: i32, conn: &PgConnection) -> QueryResult<usize> {
+        let copies: Vec<_> = dsl::comments
+            .filter(dsl::item_id.eq(from_item_id))
+            .order((dsl::created_at, dsl::comment_id))
+            .load::<Comment>(conn)?
+            .into_iter()
+            .map(|comment| (
+                dsl::item_id.eq(to_item_id),
+                dsl::author_id.eq(comment.author_id),
+                dsl::body.eq(comment.body),
+                dsl::created_at.eq(comment.created_at),
+                dsl::edited_at.eq(comment.edited_at),
+            ))
+            .collect();
+
+        diesel::insert_into(dsl::comments)
+            .values(&copies)
+            .execute(conn)
+    }
+
+    /// Attempts to replace the body of the comment with the given `id`, marking it as edited.
+    /// Returns true if successful or false otherwise.
+    pub fn update_comment(id: i32, new_comment: CommentDTO, conn: &PgConnection) -> bool {
+        diesel::update(dsl::comments.filter(dsl::comment_id.eq(id)))
+            .set((
+                dsl::body.eq(new_comment.body),
+                dsl::edited_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .is_ok()
+    }
+
+    /// Attempts to delete the comment with the given `id`. Returns true if successful or
+    /// false otherwise.
+    pub fn delete_comment(id: i32, conn: &PgConnection) -> bool {
+        diesel::delete(dsl::comments.filter(dsl::comment_id.eq(id)))
+            .execute(conn)
+            .is_ok()
+    }
+}
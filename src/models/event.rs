@@ -0,0 +1,161 @@
+use uuid::Uuid;
+
+use crate::models::item::Item;
+use crate::models::list::List;
+
+/// A change to a list or one of its items, sent to the members of the list who are following it. Items that
+/// were created or changed are sent whole, so clients don't have to get them again. Events are sent with the
+/// public ids of their list and item, and the id of the list is only kept to route them.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ListEvent {
+    ItemCreated {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+        item: Item,
+    },
+    ItemUpdated {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+        item: Item,
+    },
+    ItemDeleted {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+        item_id: Uuid,
+    },
+    ListRenamed {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+        name: String,
+    },
+    MemberAdded {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+        user_id: i32,
+    },
+    MemberRemoved {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+        user_id: i32,
+    },
+    ListTrashed {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+    },
+    ListRestored {
+        #[serde(skip)]
+        list_id: i32,
+        #[serde(rename = "list_id")]
+        list_uuid: Uuid,
+    },
+}
+
+impl ListEvent {
+    /// Builds the event for an `item` that was just created, in the list that it was created in.
+    pub fn item_created(item: Item) -> ListEvent {
+        ListEvent::ItemCreated { list_id: item.list_id, list_uuid: item.list_uuid, item }
+    }
+
+    /// Builds the event for an `item` that was just changed.
+    pub fn item_updated(item: Item) -> ListEvent {
+        ListEvent::ItemUpdated { list_id: item.list_id, list_uuid: item.list_uuid, item }
+    }
+
+    /// Builds the event for an `item` that was deleted from the list it was in.
+    pub fn item_deleted(item: &Item) -> ListEvent {
+        ListEvent::ItemDeleted { list_id: item.list_id, list_uuid: item.list_uuid, item_id: item.uuid }
+    }
+
+    /// Builds the event for a `list` that was just renamed.
+    pub fn list_renamed(list: &List) -> ListEvent {
+        ListEvent::ListRenamed { list_id: list.list_id, list_uuid: list.uuid, name: list.name.clone() }
+    }
+
+    /// Builds the event for the user with `user_id` joining the `list`.
+    pub fn member_added(list: &List, user_id: i32) -> ListEvent {
+        ListEvent::MemberAdded { list_id: list.list_id, list_uuid: list.uuid, user_id }
+    }
+
+    /// Builds the event for the user with `user_id` losing access to the `list`.
+    pub fn member_removed(list: &List, user_id: i32) -> ListEvent {
+        ListEvent::MemberRemoved { list_id: list.list_id, list_uuid: list.uuid, user_id }
+    }
+
+    /// Builds the event for a `list` that was just moved into the trash.
+    pub fn list_trashed(list: &List) -> ListEvent {
+        ListEvent::ListTrashed { list_id: list.list_id, list_uuid: list.uuid }
+    }
+
+    /// Builds the event for a `list` that was just taken back out of the trash.
+    pub fn list_restored(list: &List) -> ListEvent {
+        ListEvent::ListRestored { list_id: list.list_id, list_uuid: list.uuid }
+    }
+
+    /// Gets the id of the list that changed.
+    pub fn list_id(&self) -> i32 {
+        match self {
+            ListEvent::ItemCreated { list_id, .. }
+            | ListEvent::ItemUpdated { list_id, .. }
+            | ListEvent::ItemDeleted { list_id, .. }
+            | ListEvent::ListRenamed { list_id, .. }
+            | ListEvent::MemberAdded { list_id, .. }
+            | ListEvent::MemberRemoved { list_id, .. }
+            | ListEvent::ListTrashed { list_id, .. }
+            | ListEvent::ListRestored { list_id, .. } => *list_id,
+        }
+    }
+
+    /// Gets the name of this type of event, which is also its `type` when serialized.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ListEvent::ItemCreated { .. } => "item_created",
+            ListEvent::ItemUpdated { .. } => "item_updated",
+            ListEvent::ItemDeleted { .. } => "item_deleted",
+            ListEvent::ListRenamed { .. } => "list_renamed",
+            ListEvent::MemberAdded { .. } => "member_added",
+            ListEvent::MemberRemoved { .. } => "member_removed",
+            ListEvent::ListTrashed { .. } => "list_trashed",
+            ListEvent::ListRestored { .. } => "list_restored",
+        }
+    }
+
+    /// Determines whether this event is for an item that was just marked as finished, which is when it was
+    /// completed by the same change that updated it.
+    pub fn finishes_item(&self) -> bool {
+        matches!(self, ListEvent::ItemUpdated { item, .. } if item.finished && item.completed_at == Some(item.updated_at))
+    }
+
+    /// Builds the events for items that were just created.
+    pub fn items_created(items: Vec<Item>) -> impl Iterator<Item = ListEvent> {
+        items.into_iter().map(ListEvent::item_created)
+    }
+
+    /// Builds the events for a `list` that was just moved into the trash: the list being trashed, followed by
+    /// each of the members with `member_ids` losing access to it.
+    pub fn list_and_members_trashed(list: &List, member_ids: Vec<i32>) -> Vec<ListEvent> {
+        let removed = member_ids.into_iter().map(|user_id| ListEvent::member_removed(list, user_id));
+        std::iter::once(ListEvent::list_trashed(list)).chain(removed).collect()
+    }
+
+    /// Builds the events for a `list` that was just taken back out of the trash: each of the members with
+    /// `member_ids` getting access to it again, followed by the list being restored.
+    pub fn list_and_members_restored(list: &List, member_ids: Vec<i32>) -> Vec<ListEvent> {
+        let added = member_ids.into_iter().map(|user_id| ListEvent::member_added(list, user_id));
+        added.chain(std::iter::once(ListEvent::list_restored(list))).collect()
+    }
+}
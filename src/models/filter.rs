@@ -0,0 +1,226 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use diesel::dsl::{not, sql};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use uuid::Uuid;
+
+use crate::schema::{item_tags, items, lists, tags, user_lists};
+
+static MAX_FILTER_DEPTH: usize = 8; // Deepest nesting of `all`, `any` and `not` in a filter
+static MAX_FILTER_CONDITIONS: usize = 50; // Most conditions that a single filter can contain
+
+/// A condition on items that is compiled into a query, such as
+/// `{"all": [{"due": "this_week"}, {"finished": false}, {"tag": "urgent"}, {"assignee": "me"}]}`.
+/// Clients send and store it as JSON, and it's checked with `validate` before being used.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Filter {
+    /// Matches items that match every one of the filters.
+    All(Vec<Filter>),
+    /// Matches items that match at least one of the filters.
+    Any(Vec<Filter>),
+    /// Matches items that don't match the filter.
+    Not(Box<Filter>),
+    /// Matches items with the given finished state.
+    Finished(bool),
+    /// Matches items with the given assignee.
+    Assignee(Assignee),
+    /// Matches items that the user put their tag with the given name on.
+    Tag(String),
+    /// Matches items in the list with the given public id.
+    List(Uuid),
+    /// Matches items due at the given times.
+    Due(Due),
+    /// Matches items whose description or notes contain the given text, ignoring case.
+    Contains(String),
+}
+
+/// The assignee of an item in a `Filter`, given either as a user id or as `me` or `nobody`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Assignee {
+    User(i32),
+    Keyword(AssigneeKeyword),
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssigneeKeyword {
+    Me,
+    Nobody,
+}
+
+/// The due times of an item in a `Filter`. The relative periods are calendar days and weeks, starting on
+/// Mondays, in the time zone of the user.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Due {
+    /// Due before now.
+    Overdue,
+    Today,
+    Tomorrow,
+    ThisWeek,
+    /// Has any due time.
+    Any,
+    /// Has no due time.
+    None,
+    Before(DateTime<Utc>),
+    After(DateTime<Utc>),
+}
+
+/// The information about the user and the current time needed to compile a `Filter`.
+pub struct FilterContext {
+    pub user_id: i32,
+    pub time_zone: Tz,
+    pub now: DateTime<Utc>,
+}
+
+type ItemCondition = Box<dyn BoxableExpression<items::table, Pg, SqlType = Bool>>;
+
+impl Filter {
+    /// Checks that this filter isn't nested too deeply or too large, and that none of its groups or text is
+    /// empty. Returns the reason if it isn't valid.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut conditions = 0;
+        self.validate_at(1, &mut conditions)
+    }
+
+    fn validate_at(&self, depth: usize, conditions: &mut usize) -> Result<(), String> {
+        if depth > MAX_FILTER_DEPTH {
+            return Err(format!("filters can not be nested more than {} levels deep", MAX_FILTER_DEPTH));
+        }
+
+        *conditions += 1;
+        if *conditions > MAX_FILTER_CONDITIONS {
+            return Err(format!("filters can not contain more than {} conditions", MAX_FILTER_CONDITIONS));
+        }
+
+        match self {
+            Filter::All(filters) | Filter::Any(filters) => {
+                if filters.is_empty() {
+                    return Err(String::from("`all` and `any` need at least one filter"));
+                }
+
+                filters.iter().try_for_each(|filter| filter.validate_at(depth + 1, conditions))
+            }
+            Filter::Not(filter) => filter.validate_at(depth + 1, conditions),
+            Filter::Tag(text) | Filter::Contains(text) if text.trim().is_empty() => {
+                Err(String::from("`tag` and `contains` can not be empty"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the items in every list that the user in `context` can access, other than those in the trash
+    /// or in lists they archived, that match this filter.
+    pub fn matching_items<'a>(&self, context: &FilterContext) -> items::BoxedQuery<'a, Pg> {
+        let accessible_lists = user_lists::table
+            .inner_join(lists::table)
+            .filter(user_lists::user_id.eq(context.user_id))
+            .filter(user_lists::archived_at.is_null())
+            .filter(lists::deleted_at.is_null())
+            .select(user_lists::list_id);
+
+        items::table
+            .filter(items::deleted_at.is_null())
+            .filter(items::list_id.eq_any(accessible_lists))
+            .filter(self.compile(context))
+            .into_boxed()
+    }
+
+    /// Compiles this filter into a condition on items. Every condition is either true or false, never null,
+    /// so that `not` always matches exactly the items that its filter doesn't. Empty groups, which only
+    /// filters that weren't validated can have, match everything for `all` and nothing for `any`.
+    fn compile(&self, context: &FilterContext) -> ItemCondition {
+        match self {
+            Filter::All(filters) => filters.iter()
+                .map(|filter| filter.compile(context))
+                .reduce(|all, condition| Box::new(all.and(condition)))
+                .unwrap_or_else(|| Box::new(sql::<Bool>("TRUE"))),
+            Filter::Any(filters) => filters.iter()
+                .map(|filter| filter.compile(context))
+                .reduce(|any, condition| Box::new(any.or(condition)))
+                .unwrap_or_else(|| Box::new(sql::<Bool>("FALSE"))),
+            Filter::Not(filter) => Box::new(not(filter.compile(context))),
+            Filter::Finished(finished) => Box::new(items::finished.eq(*finished)),
+            Filter::Assignee(Assignee::User(user_id)) => Box::new(items::assignee_id.is_not_null()
+                .and(items::assignee_id.eq(*user_id))),
+            Filter::Assignee(Assignee::Keyword(AssigneeKeyword::Me)) => Box::new(items::assignee_id.is_not_null()
+                .and(items::assignee_id.eq(context.user_id))),
+            Filter::Assignee(Assignee::Keyword(AssigneeKeyword::Nobody)) => Box::new(items::assignee_id.is_null()),
+            Filter::Tag(name) => {
+                let tagged_items = item_tags::table
+                    .inner_join(tags::table)
+                    .filter(tags::user_id.eq(context.user_id))
+                    .filter(tags::name.eq(name.trim().to_string()))
+                    .select(item_tags::item_id);
+
+                Box::new(items::item_id.eq_any(tagged_items))
+            }
+            Filter::List(list_id) => Box::new(items::list_uuid.eq(*list_id)),
+            Filter::Due(due) => due.compile(context),
+            Filter::Contains(text) => {
+                let escaped = text
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                let pattern = format!("%{}%", escaped);
+
+                Box::new(items::description.ilike(pattern.clone())
+                    .or(items::notes.is_not_null().and(items::notes.ilike(pattern))))
+            }
+        }
+    }
+}
+
+impl Due {
+    fn compile(&self, context: &FilterContext) -> ItemCondition {
+        let today = context.now.with_timezone(&context.time_zone).naive_local().date();
+        let between = |start: NaiveDate, days: i64| -> ItemCondition {
+            let start_time = start_of_day(context.time_zone, start);
+            let end_time = start_of_day(context.time_zone, start + Duration::days(days));
+
+            Box::new(items::due_at.is_not_null()
+                .and(items::due_at.ge(start_time))
+                .and(items::due_at.lt(end_time)))
+        };
+
+        match self {
+            Due::Overdue => Box::new(items::due_at.is_not_null().and(items::due_at.lt(context.now))),
+            Due::Today => between(today, 1),
+            Due::Tomorrow => between(today + Duration::days(1), 1),
+            Due::ThisWeek => between(today - Duration::days(today.weekday().num_days_from_monday() as i64), 7),
+            Due::Any => Box::new(items::due_at.is_not_null()),
+            Due::None => Box::new(items::due_at.is_null()),
+            Due::Before(time) => Box::new(items::due_at.is_not_null().and(items::due_at.lt(*time))),
+            Due::After(time) => Box::new(items::due_at.is_not_null().and(items::due_at.gt(*time))),
+        }
+    }
+}
+
+/// Finds the time that the given `date` starts in the time zone `tz`. Days that start in a daylight saving
+/// gap start when the gap ends.
+fn start_of_day(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+
+    match tz.from_local_datetime(&midnight).earliest() {
+        Some(time) => time.with_timezone(&Utc),
+        None => tz.from_local_datetime(&(midnight + Duration::hours(1)))
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&midnight)),
+    }
+}
+
+/// The filter behind the built-in "Today" smart list: unfinished items that are due today or overdue.
+pub fn today() -> Filter {
+    Filter::All(vec![
+        Filter::Finished(false),
+        Filter::Any(vec![
+            Filter::Due(Due::Today),
+            Filter::Due(Due::Overdue),
+        ]),
+    ])
+}
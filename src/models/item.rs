@@ -1,54 +1,239 @@
+use chrono::{DateTime, Utc};
 use diesel::pg::Pg;
 use diesel::prelude::*;
+use uuid::Uuid;
 
+use crate::markdown;
+use crate::models::page::{Page, PageRequest, SORT_DESCRIPTION, SORT_UPDATED, SortValue};
+use crate::models::patch;
+use crate::quick_add::QuickAdd;
 use crate::schema::items;
+use crate::schema::lists;
+use crate::schema::user_lists;
 use crate::schema::items::dsl;
 
-/// An object representing a complete row in the items table.
-#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+/// An object representing a complete row in the items table. An item with `deleted_at` set is in the
+/// trash of the user with `deleted_by`, which are only shown in the trash. The `ical_uid` and `dav_name` are
+/// only set for items made by CalDAV clients or from before items had public ids, and aren't shown through
+/// the API. The item and its list are known by their public `uuid` and `list_uuid` outside of the server,
+/// which are shown as its `item_id` and `list_id` in place of the serial keys.
+#[derive(Identifiable, Queryable, Clone, Serialize, Deserialize)]
 #[primary_key("item_id")]
 pub struct Item {
+    #[serde(skip)]
     pub item_id: i32,
+    #[serde(skip)]
     pub list_id: i32,
     pub description: String,
     pub finished: bool,
+    pub notes: Option<String>,
+    pub assignee_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub version: i32,
+    #[serde(skip_serializing)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub deleted_by: Option<i32>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<String>,
+    pub recurrence: Option<String>,
+    #[serde(skip)]
+    pub ical_uid: Option<String>,
+    #[serde(skip)]
+    pub dav_name: Option<String>,
+    #[serde(rename = "item_id")]
+    pub uuid: Uuid,
+    #[serde(rename = "list_id")]
+    pub list_uuid: Uuid,
 }
 
 /// A transfer object representing information for the item that can be updated by the user.
+/// The `notes` are long-form Markdown and may be omitted, as may the `assignee_id` of the list
+/// member responsible for the item and the `due_at` time it should be finished by. The `priority` is
+/// `low`, `medium` or `high`, and the `recurrence` is an iCalendar RRULE such as `FREQ=MONTHLY`, limited
+/// to `FREQ`, `INTERVAL` and a single `BYDAY`. The client may pick the `item_id` of a new item itself, which is
+/// ignored when replacing an item.
 #[derive(Serialize, Deserialize)]
 pub struct ItemDTO {
     pub description: String,
     pub finished: bool,
+    pub notes: Option<String>,
+    pub assignee_id: Option<i32>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<String>,
+    pub recurrence: Option<String>,
+    #[serde(default, rename = "item_id")]
+    pub uuid: Option<Uuid>,
 }
 
-/// An item that can be inserted into the items table.
+/// A transfer object with the fields of an item to change, where any field left out is untouched.
+/// Nullable fields can be cleared by explicitly setting them to null.
+#[derive(AsChangeset, Deserialize)]
+#[table_name = "items"]
+pub struct ItemPatchDTO {
+    pub description: Option<String>,
+    pub finished: Option<bool>,
+    #[serde(default, deserialize_with = "patch::deserialize_some")]
+    pub notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch::deserialize_some")]
+    pub assignee_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "patch::deserialize_some")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "patch::deserialize_some")]
+    pub priority: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch::deserialize_some")]
+    pub recurrence: Option<Option<String>>,
+}
+
+impl ItemPatchDTO {
+    /// Determines whether this patch leaves every field untouched.
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.finished.is_none()
+            && self.notes.is_none()
+            && self.assignee_id.is_none()
+            && self.due_at.is_none()
+            && self.priority.is_none()
+            && self.recurrence.is_none()
+    }
+}
+
+/// A transfer object with the finished state to apply to every item in a list at once.
+#[derive(Serialize, Deserialize)]
+pub struct FinishedDTO {
+    pub finished: bool,
+}
+
+/// The outcome of a batch operation for a single item. The `index` is the position of the item in the
+/// request, for operations that take an array of items.
+#[derive(Serialize, Deserialize)]
+pub struct BatchItemResult {
+    pub index: Option<usize>,
+    pub item_id: Option<Uuid>,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Filters on the items of a list that apply on top of the pagination filters. Only the items assigned to
+/// the user with `assignee_id` are included if it's given, and only the items with the `finished` state
+/// if that's given.
+#[derive(Clone, Copy, Default)]
+pub struct ItemFilter {
+    pub assignee_id: Option<i32>,
+    pub finished: Option<bool>,
+}
+
+/// A transfer object with the single line of text that a user typed to quickly add an item, and the `item_id`
+/// that the client picked for it, if any.
+#[derive(Serialize, Deserialize)]
+pub struct QuickAddDTO {
+    pub text: String,
+    #[serde(default, rename = "item_id")]
+    pub uuid: Option<Uuid>,
+}
+
+/// The item created by a quick-add, along with the fields parsed out of its text so that the client can
+/// confirm them.
+#[derive(Serialize, Deserialize)]
+pub struct QuickAddResult {
+    pub item: Item,
+    pub parsed: QuickAdd,
+}
+
+/// A transfer object naming the list that an item should be moved or copied into.
+#[derive(Serialize, Deserialize)]
+pub struct ItemTransferDTO {
+    pub destination_list_id: Uuid,
+}
+
+/// An item along with its notes rendered to sanitized HTML.
+#[derive(Serialize, Deserialize)]
+pub struct RenderedItem {
+    #[serde(flatten)]
+    pub item: Item,
+    pub notes_html: Option<String>,
+}
+
+/// An item that can be inserted into the items table. The database picks the `uuid` if it's left out, and fills in
+/// the `list_uuid` from the list.
 #[derive(Insertable)]
 #[table_name = "items"]
 pub struct NewItem {
     pub list_id: i32,
     pub description: String,
     pub finished: bool,
+    pub notes: Option<String>,
+    pub assignee_id: Option<i32>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<String>,
+    pub recurrence: Option<String>,
+    pub uuid: Option<Uuid>,
+}
+
+/// The UID and resource name that a CalDAV client gave an item it created.
+#[derive(Insertable)]
+#[table_name = "items"]
+pub struct DavIdentity {
+    pub ical_uid: String,
+    pub dav_name: String,
 }
 
 impl Item {
     /// Attempts to insert the given `item` into the items table with reference to the given
     /// `list_id`. Returns true if successful and false otherwise.
     pub fn create_item_for_list(item: ItemDTO, list_id: i32, conn: &PgConnection) -> bool {
+        Item::insert_item(item, list_id, conn).is_ok()
+    }
+
+    /// Attempts to insert the given `item` into the items table with reference to the given
+    /// `list_id`, returning the new row.
+    pub fn insert_item(item: ItemDTO, list_id: i32, conn: &PgConnection) -> QueryResult<Item> {
         let item = NewItem {
             list_id,
             description: item.description,
             finished: item.finished,
+            notes: item.notes,
+            assignee_id: item.assignee_id,
+            due_at: item.due_at,
+            priority: item.priority,
+            recurrence: item.recurrence,
+            uuid: item.uuid,
         };
 
         diesel::insert_into(dsl::items)
             .values(&item)
-            .execute(conn)
-            .is_ok()
+            .get_result(conn)
+    }
+
+    /// Attempts to insert the given `item` into the items table with reference to the given `list_id`, along
+    /// with the `identity` given to it by the CalDAV client that created it. Returns the new row.
+    pub fn insert_dav_item(item: ItemDTO, list_id: i32, identity: DavIdentity, conn: &PgConnection) -> QueryResult<Item> {
+        let item = NewItem {
+            list_id,
+            description: item.description,
+            finished: item.finished,
+            notes: item.notes,
+            assignee_id: item.assignee_id,
+            due_at: item.due_at,
+            priority: item.priority,
+            recurrence: item.recurrence,
+            uuid: item.uuid,
+        };
+
+        diesel::insert_into(dsl::items)
+            .values((&item, &identity))
+            .get_result(conn)
     }
 
-    /// Determines whether or not the given item is contained in the given list.
+    /// Determines whether or not the given item is contained in the given list. Items in the trash don't
+    /// count.
     pub fn owned_by_list(item_id: i32, list_id: i32, conn: &PgConnection) -> bool {
-        let possible_item = dsl::items.filter(dsl::item_id.eq(item_id)).get_result::<Item>(conn);
+        let possible_item = dsl::items
+            .filter(dsl::item_id.eq(item_id))
+            .filter(dsl::deleted_at.is_null())
+            .get_result::<Item>(conn);
 
         if let Ok(item) = possible_item {
             item.list_id == list_id
@@ -57,11 +242,111 @@ impl Item {
         }
     }
 
-    /// Finds all `Item` objects owned by the list with the given `list_id`.
-    pub fn find_items_for_list(list_id: i32, conn: &PgConnection) -> Vec<Item> {
+    /// Finds a single page of the `Item` objects owned by the list with the given `list_id`, other than those in the
+    /// trash, that match the `filter`, filtered and sorted according to `page`.
+    pub fn find_page_for_list(list_id: i32, filter: ItemFilter, page: PageRequest, conn: &PgConnection) -> QueryResult<Page<Item>> {
+        Item::find_page(|page| Item::filtered_for_list(list_id, filter, page), page, conn)
+    }
+
+    /// Finds a single page of the items in the query built by `filtered`, which is given the filters in `page`,
+    /// sorted according to `page`.
+    pub fn find_page<'a, F>(filtered: F, page: PageRequest, conn: &PgConnection) -> QueryResult<Page<Item>>
+        where F: Fn(&PageRequest) -> items::BoxedQuery<'a, Pg> {
+        let total_count = filtered(&page)
+            .count()
+            .get_result(conn)?;
+
+        let mut query = filtered(&page);
+
+        // Keyset pagination: continue strictly after the last row of the previous page, with ties on the sort
+        // column broken by public id
+        if let Some(cursor) = &page.after {
+            let id = cursor.id;
+            query = match (&cursor.value, page.sort.as_str(), page.descending) {
+                (SortValue::Text(value), _, false) => query.filter(items::description.gt(value.clone())
+                    .or(items::description.eq(value.clone()).and(items::uuid.gt(id)))),
+                (SortValue::Text(value), _, true) => query.filter(items::description.lt(value.clone())
+                    .or(items::description.eq(value.clone()).and(items::uuid.lt(id)))),
+                (SortValue::Time(value), SORT_UPDATED, false) => query.filter(items::updated_at.gt(*value)
+                    .or(items::updated_at.eq(*value).and(items::uuid.gt(id)))),
+                (SortValue::Time(value), SORT_UPDATED, true) => query.filter(items::updated_at.lt(*value)
+                    .or(items::updated_at.eq(*value).and(items::uuid.lt(id)))),
+                (SortValue::Time(value), _, false) => query.filter(items::created_at.gt(*value)
+                    .or(items::created_at.eq(*value).and(items::uuid.gt(id)))),
+                (SortValue::Time(value), _, true) => query.filter(items::created_at.lt(*value)
+                    .or(items::created_at.eq(*value).and(items::uuid.lt(id)))),
+            };
+        }
+
+        query = match (page.sort.as_str(), page.descending) {
+            (SORT_DESCRIPTION, false) => query.order((items::description.asc(), items::uuid.asc())),
+            (SORT_DESCRIPTION, true) => query.order((items::description.desc(), items::uuid.desc())),
+            (SORT_UPDATED, false) => query.order((items::updated_at.asc(), items::uuid.asc())),
+            (SORT_UPDATED, true) => query.order((items::updated_at.desc(), items::uuid.desc())),
+            (_, false) => query.order((items::created_at.asc(), items::uuid.asc())),
+            (_, true) => query.order((items::created_at.desc(), items::uuid.desc())),
+        };
+
+        let rows = query.limit(page.limit + 1).load::<Item>(conn)?;
+        let sort = page.sort.clone();
+
+        Ok(page.into_page(rows, total_count, |item| {
+            let value = match sort.as_str() {
+                SORT_DESCRIPTION => SortValue::Text(item.description.clone()),
+                SORT_UPDATED => SortValue::Time(item.updated_at),
+                _ => SortValue::Time(item.created_at),
+            };
+
+            (value, item.uuid)
+        }))
+    }
+
+    /// Builds the query for the items of the list with `list_id` that match the `filter` and the filters in
+    /// `page`, before any pagination or sorting.
+    fn filtered_for_list<'a>(list_id: i32, filter: ItemFilter, page: &PageRequest) -> items::BoxedQuery<'a, Pg> {
+        let mut query = items::table
+            .filter(items::list_id.eq(list_id))
+            .filter(items::deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(assignee_id) = filter.assignee_id {
+            query = query.filter(items::assignee_id.eq(assignee_id));
+        }
+
+        if let Some(finished) = filter.finished {
+            query = query.filter(items::finished.eq(finished));
+        }
+
+        Item::with_page_filters(query, page)
+    }
+
+    /// Adds the `contains` and `created_after` filters in `page` to the given item `query`.
+    pub fn with_page_filters<'a>(mut query: items::BoxedQuery<'a, Pg>, page: &PageRequest) -> items::BoxedQuery<'a, Pg> {
+        if let Some(pattern) = page.contains_pattern() {
+            query = query.filter(items::description.ilike(pattern.clone())
+                .or(items::notes.ilike(pattern)));
+        }
+
+        if let Some(created_after) = page.created_after {
+            query = query.filter(items::created_at.gt(created_after));
+        }
+
+        query
+    }
+
+    /// Finds every `Item` assigned to the user with the given `user_id`, across all of their lists. Items
+    /// and lists in the trash are left out.
+    pub fn find_items_assigned_to(user_id: i32, conn: &PgConnection) -> Vec<Item> {
         let possible_items = items::table
-            .filter(items::list_id.eq(&list_id))
-            .get_results::<Item>(conn);
+            .inner_join(user_lists::table.on(user_lists::list_id.eq(items::list_id)))
+            .inner_join(lists::table)
+            .filter(user_lists::user_id.eq(user_id))
+            .filter(items::assignee_id.eq(user_id))
+            .filter(items::deleted_at.is_null())
+            .filter(lists::deleted_at.is_null())
+            .select(items::all_columns)
+            .order(items::item_id)
+            .load::<Item>(conn);
 
         match possible_items {
             Ok(result_items) => result_items,
@@ -69,32 +354,221 @@ impl Item {
         }
     }
 
-    /// Attempts to update the item with the given `id` to the values in `new_item`.
-    /// Returns true if successful or false otherwise.
-    pub fn update_item(id: i32, new_item: ItemDTO, conn: &PgConnection) -> bool {
+    /// Finds every `Item` in the list with the given `list_id` that isn't in the trash, oldest first.
+    pub fn find_items_for_list(list_id: i32, conn: &PgConnection) -> QueryResult<Vec<Item>> {
+        items::table
+            .filter(items::list_id.eq(list_id))
+            .filter(items::deleted_at.is_null())
+            .order((items::created_at, items::item_id))
+            .load(conn)
+    }
+
+    /// Finds the item in the list with `list_id` that CalDAV clients know by the resource name `dav_name`,
+    /// other than items in the trash. Other items made without CalDAV are known as `<uuid>.ics`.
+    pub fn find_item_by_dav_name(list_id: i32, dav_name: &str, conn: &PgConnection) -> Option<Item> {
+        let mut query = items::table
+            .filter(items::list_id.eq(list_id))
+            .filter(items::deleted_at.is_null())
+            .into_boxed();
+
+        let uuid = dav_name.strip_suffix(".ics")
+            .and_then(|uuid| Uuid::parse_str(uuid).ok());
+        query = match uuid {
+            Some(uuid) => query.filter(items::uuid.eq(uuid).and(items::dav_name.is_null())
+                .or(items::dav_name.eq(dav_name))),
+            None => query.filter(items::dav_name.eq(dav_name)),
+        };
+
+        query.order(items::item_id)
+            .first::<Item>(conn)
+            .ok()
+    }
+
+    /// Finds the `(item_id, version)` pair of every item in the list with the given `list_id` that isn't
+    /// in the trash, ordered by id.
+    pub fn find_item_versions(list_id: i32, conn: &PgConnection) -> QueryResult<Vec<(i32, i32)>> {
+        items::table
+            .filter(items::list_id.eq(list_id))
+            .filter(items::deleted_at.is_null())
+            .select((items::item_id, items::version))
+            .order(items::item_id)
+            .load(conn)
+    }
+
+    /// Attempts to update the item with the given `id` to the values in `new_item`, returning the
+    /// updated row.
+    pub fn update_item(id: i32, new_item: ItemDTO, conn: &PgConnection) -> QueryResult<Item> {
         diesel::update(items::table.filter(items::item_id.eq(id)))
             .set((
                 items::description.eq(new_item.description),
                 items::finished.eq(new_item.finished),
+                items::notes.eq(new_item.notes),
+                items::assignee_id.eq(new_item.assignee_id),
+                items::due_at.eq(new_item.due_at),
+                items::priority.eq(new_item.priority),
+                items::recurrence.eq(new_item.recurrence),
+            ))
+            .get_result(conn)
+    }
+
+    /// Attempts to apply the fields present in `patch` to the item with the given `id`, returning the
+    /// updated row.
+    pub fn patch_item(id: i32, patch: ItemPatchDTO, conn: &PgConnection) -> QueryResult<Item> {
+        if patch.is_empty() {
+            return dsl::items.filter(dsl::item_id.eq(id)).get_result(conn);
+        }
+
+        diesel::update(items::table.filter(items::item_id.eq(id)))
+            .set(&patch)
+            .get_result(conn)
+    }
+
+    /// Marks every item in the list with `list_id` as finished or unfinished, skipping those in the trash.
+    /// Returns the items that changed.
+    pub fn set_all_finished(list_id: i32, finished: bool, conn: &PgConnection) -> QueryResult<Vec<Item>> {
+        diesel::update(items::table
+            .filter(items::list_id.eq(list_id))
+            .filter(items::deleted_at.is_null())
+            .filter(items::finished.ne(finished)))
+            .set(items::finished.eq(finished))
+            .get_results(conn)
+    }
+
+    /// Moves every finished item in the list with `list_id` into the trash of the user with `user_id`.
+    /// Returns the items that were trashed.
+    pub fn trash_finished_items(list_id: i32, user_id: i32, conn: &PgConnection) -> QueryResult<Vec<Item>> {
+        diesel::update(items::table
+            .filter(items::list_id.eq(list_id))
+            .filter(items::deleted_at.is_null())
+            .filter(items::finished.eq(true)))
+            .set((
+                items::deleted_at.eq(diesel::dsl::now),
+                items::deleted_by.eq(user_id),
+            ))
+            .get_results(conn)
+    }
+
+    /// Attempts to move the item with the given `id` into the trash of the user with `user_id`. Returns
+    /// true if successful or false otherwise.
+    pub fn trash_item(id: i32, user_id: i32, conn: &PgConnection) -> bool {
+        diesel::update(dsl::items.filter(dsl::item_id.eq(id)).filter(dsl::deleted_at.is_null()))
+            .set((
+                dsl::deleted_at.eq(diesel::dsl::now),
+                dsl::deleted_by.eq(user_id),
             ))
             .execute(conn)
             .is_ok()
     }
 
-    /// Attempts to delete the item with the given `id`. Returns true if successful or
-    /// false otherwise.
-    pub fn delete_item(id: i32, conn: &PgConnection) -> bool {
-        diesel::delete(dsl::items.filter(dsl::item_id.eq(id)))
+    /// Finds the `Item` rows in the trash of the user with `user_id`, most recently deleted first.
+    pub fn find_trashed_items_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<Vec<Item>> {
+        dsl::items
+            .filter(dsl::deleted_by.eq(user_id))
+            .filter(dsl::deleted_at.is_not_null())
+            .order(dsl::deleted_at.desc())
+            .load::<Item>(conn)
+    }
+
+    /// Attempts to take the item with the given `id` back out of the trash of the user with `user_id`,
+    /// returning the restored row.
+    pub fn restore_item(id: i32, user_id: i32, conn: &PgConnection) -> QueryResult<Item> {
+        diesel::update(dsl::items
+            .filter(dsl::item_id.eq(id))
+            .filter(dsl::deleted_by.eq(user_id))
+            .filter(dsl::deleted_at.is_not_null()))
+            .set((
+                dsl::deleted_at.eq(None::<DateTime<Utc>>),
+                dsl::deleted_by.eq(None::<i32>),
+            ))
+            .get_result(conn)
+    }
+
+    /// Permanently deletes every item in the trash of the user with `user_id`. Returns the number of
+    /// items deleted.
+    pub fn delete_trashed_items_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(dsl::items
+            .filter(dsl::deleted_by.eq(user_id))
+            .filter(dsl::deleted_at.is_not_null()))
             .execute(conn)
-            .is_ok()
     }
 
-    // pub fn find_item_by_id(id: i32, conn: &PgConnection) -> Option<Item> {
-    //     let possible_item = dsl::items.filter(dsl::item_id.eq(user_id)).get_result::<User>(conn);
-    //     if let Ok(user) = possible_user {
-    //         Some(user)
-    //     } else {
-    //         None
-    //     }
-    // }
+    /// Permanently deletes every item that was moved to the trash before `cutoff`, no matter who deleted
+    /// it. Returns the number of items deleted.
+    pub fn purge_trashed_items(cutoff: DateTime<Utc>, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(dsl::items.filter(dsl::deleted_at.lt(cutoff)))
+            .execute(conn)
+    }
+
+    /// Renders the Markdown notes of this item to sanitized HTML.
+    pub fn render(self) -> RenderedItem {
+        let notes_html = markdown::render_optional_html(&self.notes);
+
+        RenderedItem {
+            item: self,
+            notes_html,
+        }
+    }
+
+    /// Finds the `Item` with the given id, if it exists.
+    pub fn find_item_by_id(id: i32, conn: &PgConnection) -> Option<Item> {
+        dsl::items
+            .filter(dsl::item_id.eq(id))
+            .get_result::<Item>(conn)
+            .ok()
+    }
+
+    /// Finds the id of the item with the public `uuid`, including items in the trash.
+    pub fn find_item_id(uuid: Uuid, conn: &PgConnection) -> Option<i32> {
+        dsl::items
+            .select(dsl::item_id)
+            .filter(dsl::uuid.eq(uuid))
+            .get_result::<i32>(conn)
+            .ok()
+    }
+
+    /// Finds the `Item` with the given id and locks its row until the end of the current transaction,
+    /// so that it can't change between checking and updating it.
+    pub fn lock_item(id: i32, conn: &PgConnection) -> QueryResult<Item> {
+        dsl::items
+            .filter(dsl::item_id.eq(id))
+            .for_update()
+            .get_result::<Item>(conn)
+    }
+
+    /// Attempts to move the item with the given `id` into the list with `list_id`, keeping its id and
+    /// everything attached to it. If `keep_assignee` is false, the item is also unassigned.
+    pub fn move_to_list(id: i32, list_id: i32, keep_assignee: bool, conn: &PgConnection) -> QueryResult<Item> {
+        let update = diesel::update(items::table.filter(items::item_id.eq(id)));
+
+        if keep_assignee {
+            update.set(items::list_id.eq(list_id)).get_result(conn)
+        } else {
+            update
+                .set((
+                    items::list_id.eq(list_id),
+                    items::assignee_id.eq(None::<i32>),
+                ))
+                .get_result(conn)
+        }
+    }
+
+    /// Attempts to insert a copy of this item into the list with `list_id`. If `keep_assignee` is false,
+    /// the copy is left unassigned. Returns the new row.
+    pub fn copy_to_list(&self, list_id: i32, keep_assignee: bool, conn: &PgConnection) -> QueryResult<Item> {
+        let item = NewItem {
+            list_id,
+            description: self.description.clone(),
+            finished: self.finished,
+            notes: self.notes.clone(),
+            assignee_id: if keep_assignee { self.assignee_id } else { None },
+            due_at: self.due_at,
+            priority: self.priority.clone(),
+            recurrence: self.recurrence.clone(),
+            uuid: None,
+        };
+
+        diesel::insert_into(dsl::items)
+            .values(&item)
+            .get_result(conn)
+    }
 }
\ No newline at end of file
@@ -1,57 +1,145 @@
-use chrono::{NaiveDate, Utc};
+use chrono::{DateTime, Utc};
 use diesel::pg::Pg;
 use diesel::prelude::*;
+use ::uuid::Uuid;
 
-use crate::models::item::Item;
+use crate::markdown;
+use crate::models::item::{Item, ItemFilter, RenderedItem};
+use crate::models::page::{Page, PageRequest, SORT_NAME, SORT_UPDATED, SortValue};
+use crate::models::patch;
 use crate::schema::*;
 use crate::schema::lists;
 use crate::schema::lists::dsl::*;
 
-/// An object representing a full row in the lists table.
+/// An object representing a full row in the lists table. A list with `deleted_at` set is in the trash of
+/// the user with `deleted_by`, which are only shown in the trash. The list is known by its public `uuid`
+/// outside of the server, which is shown as its `list_id` in place of the serial key.
 #[derive(Identifiable, Queryable, Serialize, Deserialize)]
 #[primary_key(list_id)]
 pub struct List {
+    #[serde(skip)]
     pub list_id: i32,
     pub name: String,
     pub description: Option<String>,
-    pub date_created: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+    #[serde(skip_serializing)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub deleted_by: Option<i32>,
+    #[serde(rename = "list_id")]
+    pub uuid: Uuid,
 }
 
-/// An object representing all the information needed to insert a new row into the lists table.
+/// An object representing all the information needed to insert a new row into the lists table. The database
+/// picks the `uuid` if it's left out.
 #[derive(Insertable)]
 #[table_name = "lists"]
 pub struct NewList {
     pub name: String,
     pub description: Option<String>,
-    pub date_created: NaiveDate,
+    pub uuid: Option<Uuid>,
 }
 
 /// An object with the information the user needs to provide to create a new list.
+/// The `description` is written in Markdown. The client may pick the `list_id` of a new list itself, which is
+/// ignored when replacing a list.
 #[derive(Serialize, Deserialize)]
 pub struct ListDTO {
     pub name: String,
     pub description: Option<String>,
+    #[serde(default, rename = "list_id")]
+    pub uuid: Option<Uuid>,
 }
 
-/// An object containing information for the complete list, including all of its items.
+/// A transfer object with the fields of a list to change, where any field left out is untouched.
+/// The description can be cleared by explicitly setting it to null.
+#[derive(AsChangeset, Deserialize)]
+#[table_name = "lists"]
+pub struct ListPatchDTO {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "patch::deserialize_some")]
+    pub description: Option<Option<String>>,
+}
+
+/// An object containing information for the complete list, including a page of its items along with the
+/// cursor for the next page and the total number of matching items.
 #[derive(Serialize, Deserialize)]
 pub struct ListWithItems {
-    pub list_id: i32,
+    pub list_id: Uuid,
     pub name: String,
     pub description: Option<String>,
-    pub date_created: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
     pub items: Vec<Item>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+/// A list along with its description rendered to sanitized HTML.
+#[derive(Serialize, Deserialize)]
+pub struct RenderedList {
+    #[serde(flatten)]
+    pub list: List,
+    pub description_html: Option<String>,
+}
+
+/// A complete list where the list description and the notes of every item have been rendered
+/// to sanitized HTML alongside the raw Markdown.
+#[derive(Serialize, Deserialize)]
+pub struct RenderedListWithItems {
+    pub list_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub description_html: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+    pub items: Vec<RenderedItem>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+impl ListWithItems {
+    /// Renders the Markdown description of this list and the notes of each of its items to
+    /// sanitized HTML.
+    pub fn render(self) -> RenderedListWithItems {
+        RenderedListWithItems {
+            list_id: self.list_id,
+            description_html: markdown::render_optional_html(&self.description),
+            name: self.name,
+            description: self.description,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            version: self.version,
+            items: self.items.into_iter().map(Item::render).collect(),
+            next_cursor: self.next_cursor,
+            total_count: self.total_count,
+        }
+    }
 }
 
 impl List {
-    /// Attempts to create a new list with the specified information and the current local
-    /// date for the "date_created" column.
+    /// Renders the Markdown description of this list to sanitized HTML.
+    pub fn render(self) -> RenderedList {
+        let description_html = markdown::render_optional_html(&self.description);
+
+        RenderedList {
+            list: self,
+            description_html,
+        }
+    }
+
+    /// Attempts to create a new list with the specified information. The database fills in the
+    /// "created_at" and "updated_at" columns.
     /// If the row is created, returns its contents, otherwise returns none.
     pub fn create_list(list: ListDTO, conn: &PgConnection) -> Option<List> {
         let list = NewList {
             name: list.name,
             description: list.description,
-            date_created: Utc::now().naive_local().date(),
+            uuid: list.uuid,
         };
 
         let result = diesel::insert_into(lists)
@@ -64,9 +152,12 @@ impl List {
         }
     }
 
-    /// Finds the `List` with the given id, if it exists.
+    /// Finds the `List` with the given id, if it exists and isn't in the trash.
     pub fn find_list_by_id(id: i32, conn: &PgConnection) -> Option<List> {
-        let possible_list = lists.filter(list_id.eq(id)).get_result::<List>(conn);
+        let possible_list = lists
+            .filter(list_id.eq(id))
+            .filter(deleted_at.is_null())
+            .get_result::<List>(conn);
 
         match possible_list {
             Ok(list) => Some(list),
@@ -74,55 +165,215 @@ impl List {
         }
     }
 
-    /// Finds the complete `ListWithItems` for the list with the given `id`, if it exists.
-    pub fn find_complete_list_by_id(id: i32, conn: &PgConnection) -> Option<ListWithItems> {
+    /// Finds the id of the list with the given public id, including lists in the trash.
+    pub fn find_list_id(public_id: Uuid, conn: &PgConnection) -> Option<i32> {
+        lists
+            .select(list_id)
+            .filter(uuid.eq(public_id))
+            .get_result::<i32>(conn)
+            .ok()
+    }
+
+    /// Finds the complete `ListWithItems` for the list with the given `id`, if it exists, with the page of its
+    /// items that match the `filter` described by `page`.
+    pub fn find_complete_list_by_id(id: i32, filter: ItemFilter, page: PageRequest, conn: &PgConnection) -> Option<ListWithItems> {
         if let Some(list) = List::find_list_by_id(id, conn) {
-            let items = Item::find_items_for_list(id, conn);
+            let items = Item::find_page_for_list(id, filter, page, conn).ok()?;
 
             Some(ListWithItems {
-                list_id: list.list_id,
+                list_id: list.uuid,
                 name: list.name,
                 description: list.description,
-                date_created: list.date_created,
-                items,
+                created_at: list.created_at,
+                updated_at: list.updated_at,
+                version: list.version,
+                items: items.results,
+                next_cursor: items.next_cursor,
+                total_count: items.total_count,
             })
         } else {
             None
         }
     }
 
-    /// Finds the `List` rows that the given user can access.
-    pub fn find_lists_for_user(user_id: i32, conn: &PgConnection) -> Option<Vec<List>> {
-        let possible_lists = lists::table
-            .inner_join(user_lists::table.on(lists::list_id.eq(user_lists::list_id)))
-            .into_boxed()
-            .select((lists::list_id, lists::name, lists::description, lists::date_created))
+    /// Finds a single page of the `List` rows that the given user can access, leaving out those in the trash,
+    /// filtered and sorted according to `page`. Only the lists that the user archived are included if `archived`
+    /// is set, and only the others if not.
+    pub fn find_lists_for_user(user_id: i32, archived: bool, page: PageRequest, conn: &PgConnection) -> QueryResult<Page<List>> {
+        let total_count = List::filtered_for_user(user_id, archived, &page)
+            .count()
+            .get_result(conn)?;
+
+        let mut query = List::filtered_for_user(user_id, archived, &page);
+
+        // Keyset pagination: continue strictly after the last row of the previous page, with ties on the sort
+        // column broken by public id
+        if let Some(cursor) = &page.after {
+            let id = cursor.id;
+            query = match (&cursor.value, page.sort.as_str(), page.descending) {
+                (SortValue::Text(value), _, false) => query.filter(lists::name.gt(value.clone())
+                    .or(lists::name.eq(value.clone()).and(lists::uuid.gt(id)))),
+                (SortValue::Text(value), _, true) => query.filter(lists::name.lt(value.clone())
+                    .or(lists::name.eq(value.clone()).and(lists::uuid.lt(id)))),
+                (SortValue::Time(value), SORT_UPDATED, false) => query.filter(lists::updated_at.gt(*value)
+                    .or(lists::updated_at.eq(*value).and(lists::uuid.gt(id)))),
+                (SortValue::Time(value), SORT_UPDATED, true) => query.filter(lists::updated_at.lt(*value)
+                    .or(lists::updated_at.eq(*value).and(lists::uuid.lt(id)))),
+                (SortValue::Time(value), _, false) => query.filter(lists::created_at.gt(*value)
+                    .or(lists::created_at.eq(*value).and(lists::uuid.gt(id)))),
+                (SortValue::Time(value), _, true) => query.filter(lists::created_at.lt(*value)
+                    .or(lists::created_at.eq(*value).and(lists::uuid.lt(id)))),
+            };
+        }
+
+        query = match (page.sort.as_str(), page.descending) {
+            (SORT_NAME, false) => query.order((lists::name.asc(), lists::uuid.asc())),
+            (SORT_NAME, true) => query.order((lists::name.desc(), lists::uuid.desc())),
+            (SORT_UPDATED, false) => query.order((lists::updated_at.asc(), lists::uuid.asc())),
+            (SORT_UPDATED, true) => query.order((lists::updated_at.desc(), lists::uuid.desc())),
+            (_, false) => query.order((lists::created_at.asc(), lists::uuid.asc())),
+            (_, true) => query.order((lists::created_at.desc(), lists::uuid.desc())),
+        };
+
+        let rows = query.limit(page.limit + 1).load::<List>(conn)?;
+        let sort = page.sort.clone();
+
+        Ok(page.into_page(rows, total_count, |list| {
+            let value = match sort.as_str() {
+                SORT_NAME => SortValue::Text(list.name.clone()),
+                SORT_UPDATED => SortValue::Time(list.updated_at),
+                _ => SortValue::Time(list.created_at),
+            };
+
+            (value, list.uuid)
+        }))
+    }
+
+    /// Finds every `List` that the given user can access, other than those in the trash or archived by the user,
+    /// in order of name.
+    pub fn find_all_lists_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<Vec<List>> {
+        let memberships = user_lists::table
+            .select(user_lists::list_id)
             .filter(user_lists::user_id.eq(user_id))
-            .load::<List>(conn);
+            .filter(user_lists::archived_at.is_null());
 
-        match possible_lists {
-            Ok(result_lists) => Some(result_lists),
-            Err(_) => None,
+        lists::table
+            .filter(lists::list_id.eq_any(memberships))
+            .filter(lists::deleted_at.is_null())
+            .order((lists::name, lists::list_id))
+            .load(conn)
+    }
+
+    /// Builds the query for the lists that the given user can access and that match the given filters, before
+    /// any pagination or sorting.
+    fn filtered_for_user<'a>(user_id: i32, archived: bool, page: &PageRequest) -> lists::BoxedQuery<'a, Pg> {
+        let mut memberships = user_lists::table
+            .select(user_lists::list_id)
+            .filter(user_lists::user_id.eq(user_id))
+            .into_boxed();
+
+        memberships = if archived {
+            memberships.filter(user_lists::archived_at.is_not_null())
+        } else {
+            memberships.filter(user_lists::archived_at.is_null())
+        };
+
+        let mut query = lists::table
+            .filter(lists::list_id.eq_any(memberships))
+            .filter(lists::deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(pattern) = page.contains_pattern() {
+            query = query.filter(lists::name.ilike(pattern.clone())
+                .or(lists::description.ilike(pattern)));
+        }
+
+        if let Some(created_after) = page.created_after {
+            query = query.filter(lists::created_at.gt(created_after));
         }
+
+        query
     }
 
-    /// Attempts to update the list with the given `id` to the new values in `new_list`.
-    /// Returns true if successful, or false otherwise.
-    pub fn update_list(id: i32, new_list: ListDTO, conn: &PgConnection) -> bool {
+    /// Finds the `List` with the given id and locks its row until the end of the current transaction,
+    /// so that it can't change between checking and updating it.
+    pub fn lock_list(id: i32, conn: &PgConnection) -> QueryResult<List> {
+        lists.filter(list_id.eq(id))
+            .for_update()
+            .get_result::<List>(conn)
+    }
+
+    /// Attempts to update the list with the given `id` to the new values in `new_list`, returning the
+    /// updated row.
+    pub fn update_list(id: i32, new_list: ListDTO, conn: &PgConnection) -> QueryResult<List> {
         diesel::update(lists.filter(lists::list_id.eq(id)))
             .set((
                 lists::name.eq(new_list.name),
                 lists::description.eq(new_list.description)
             ))
+            .get_result(conn)
+    }
+
+    /// Attempts to apply the fields present in `patch` to the list with the given `id`, returning the
+    /// updated row.
+    pub fn patch_list(id: i32, patch: ListPatchDTO, conn: &PgConnection) -> QueryResult<List> {
+        if patch.name.is_none() && patch.description.is_none() {
+            return lists.filter(list_id.eq(id)).get_result(conn);
+        }
+
+        diesel::update(lists.filter(list_id.eq(id)))
+            .set(&patch)
+            .get_result(conn)
+    }
+
+    /// Attempts to move the `List` with the given primary key into the trash of the user with `user_id`,
+    /// hiding it and its items from every member. Returns true if it succeeded, or false if it failed.
+    pub fn trash_list(id: i32, user_id: i32, conn: &PgConnection) -> bool {
+        diesel::update(lists.filter(list_id.eq(id)).filter(deleted_at.is_null()))
+            .set((
+                deleted_at.eq(diesel::dsl::now),
+                deleted_by.eq(user_id),
+            ))
             .execute(conn)
             .is_ok()
     }
 
-    /// Attempts to delete the `List` with the given primary key, if it exists.
-    /// Returns true if the delete succeeded, or false if it failed.
-    pub fn delete_list(id: i32, conn: &PgConnection) -> bool {
-        diesel::delete(lists.filter(list_id.eq(id)))
+    /// Finds the `List` rows in the trash of the user with `user_id`, most recently deleted first.
+    pub fn find_trashed_lists_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<Vec<List>> {
+        lists
+            .filter(deleted_by.eq(user_id))
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<List>(conn)
+    }
+
+    /// Attempts to take the list with the given `id` back out of the trash of the user with `user_id`,
+    /// returning the restored row.
+    pub fn restore_list(id: i32, user_id: i32, conn: &PgConnection) -> QueryResult<List> {
+        diesel::update(lists
+            .filter(list_id.eq(id))
+            .filter(deleted_by.eq(user_id))
+            .filter(deleted_at.is_not_null()))
+            .set((
+                deleted_at.eq(None::<DateTime<Utc>>),
+                deleted_by.eq(None::<i32>),
+            ))
+            .get_result(conn)
+    }
+
+    /// Permanently deletes every list in the trash of the user with `user_id`, along with their items
+    /// and memberships through cascading. Returns the number of lists deleted.
+    pub fn delete_trashed_lists_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(lists
+            .filter(deleted_by.eq(user_id))
+            .filter(deleted_at.is_not_null()))
+            .execute(conn)
+    }
+
+    /// Permanently deletes every list that was moved to the trash before `cutoff`, no matter who deleted
+    /// it. Returns the number of lists deleted.
+    pub fn purge_trashed_lists(cutoff: DateTime<Utc>, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(lists.filter(deleted_at.lt(cutoff)))
             .execute(conn)
-            .is_ok()
     }
 }
\ No newline at end of file
@@ -2,4 +2,19 @@ pub mod user;
 pub mod response;
 pub mod list;
 pub mod user_lists;
-pub mod item;
\ No newline at end of file
+pub mod item;
+pub mod comment;
+pub mod attachment;
+pub mod patch;
+pub mod trash;
+pub mod page;
+pub mod search;
+pub mod tag;
+pub mod filter;
+pub mod smart_list;
+pub mod portable;
+pub mod calendar_feed;
+pub mod app_password;
+pub mod event;
+pub mod webhook;
+pub mod sync;
\ No newline at end of file
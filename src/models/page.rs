@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+static DEFAULT_PAGE_SIZE: i64 = 100; // Rows in a page when the client doesn't give a limit
+static MAX_PAGE_SIZE: i64 = 500; // Largest page that a client can ask for
+
+pub const SORT_CREATED: &str = "created";
+pub const SORT_UPDATED: &str = "updated";
+pub const SORT_NAME: &str = "name";
+pub const SORT_DESCRIPTION: &str = "description";
+
+/// The pagination, filter and sort parameters that can be given in the query string of a collection endpoint.
+/// `cursor` is the `next_cursor` of the previous page, `order` is either `asc` or `desc`, and `created_after`
+/// is an RFC 3339 timestamp.
+#[derive(Default, FromForm)]
+pub struct PageQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    pub contains: Option<String>,
+    pub created_after: Option<String>,
+}
+
+/// The value of the sort column for a single row.
+#[derive(Serialize, Deserialize)]
+pub enum SortValue {
+    Time(DateTime<Utc>),
+    Text(String),
+}
+
+impl SortValue {
+    /// Determines whether this is the kind of value stored in the column for `sort`.
+    fn is_for(&self, sort: &str) -> bool {
+        match self {
+            SortValue::Time(_) => sort == SORT_CREATED || sort == SORT_UPDATED,
+            SortValue::Text(_) => sort == SORT_NAME || sort == SORT_DESCRIPTION,
+        }
+    }
+}
+
+/// A position in a sorted collection, made of the sort column value and the public id of the last row of the
+/// previous page. Clients only ever see it encoded, so that its contents can change freely.
+#[derive(Serialize, Deserialize)]
+pub struct Cursor {
+    pub sort: String,
+    pub descending: bool,
+    pub value: SortValue,
+    pub id: Uuid,
+}
+
+/// Encodes the given `cursor` into an opaque string that is safe to use in a URL.
+pub fn encode_cursor<T: Serialize>(cursor: &T) -> String {
+    base64::encode_config(serde_json::to_vec(cursor).unwrap(), base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes a cursor previously made by `encode_cursor`, if it's valid.
+pub fn decode_cursor<T: DeserializeOwned>(cursor: &str) -> Option<T> {
+    let bytes = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Validates the page size asked for by a client, using the default size if there isn't one.
+pub fn parse_limit(limit: Option<i64>) -> Option<i64> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    if (1..=MAX_PAGE_SIZE).contains(&limit) {
+        Some(limit)
+    } else {
+        None
+    }
+}
+
+/// The validated pagination, filter and sort parameters for a collection.
+pub struct PageRequest {
+    pub limit: i64,
+    pub sort: String,
+    pub descending: bool,
+    pub after: Option<Cursor>,
+    pub contains: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+/// A single page of a collection, along with the cursor for the next page if there is one and the number
+/// of rows in the whole (filtered) collection.
+#[derive(Serialize, Deserialize)]
+pub struct Page<T> {
+    pub results: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+impl PageQuery {
+    /// Validates these parameters for a collection that can be sorted by any of `sorts`, the first of which
+    /// is the default. Returns none if any of them are invalid, including a cursor from a different sort.
+    pub fn parse(self, sorts: &[&str]) -> Option<PageRequest> {
+        let limit = parse_limit(self.limit)?;
+
+        let sort = match self.sort {
+            Some(sort) if sorts.contains(&sort.as_str()) => sort,
+            Some(_) => return None,
+            None => String::from(sorts[0]),
+        };
+
+        let descending = match self.order.as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(_) => return None,
+        };
+
+        let after = match self.cursor {
+            Some(cursor) => {
+                let cursor: Cursor = decode_cursor(&cursor)?;
+                if cursor.sort != sort || cursor.descending != descending || !cursor.value.is_for(&sort) {
+                    return None;
+                }
+
+                Some(cursor)
+            }
+            None => None,
+        };
+
+        // A `+` in the offset of the timestamp arrives as a space when it isn't percent-encoded
+        let created_after = match self.created_after {
+            Some(time) => Some(DateTime::parse_from_rfc3339(&time.replace(' ', "+")).ok()?.with_timezone(&Utc)),
+            None => None,
+        };
+
+        Some(PageRequest {
+            limit,
+            sort,
+            descending,
+            after,
+            contains: self.contains.filter(|text| !text.is_empty()),
+            created_after,
+        })
+    }
+}
+
+impl PageRequest {
+    /// Describes these parameters in a normalized form, which is the same for every request for the same page
+    /// and different for any other page, filter or sort.
+    pub fn variant(&self) -> String {
+        format!("limit={}&sort={}&descending={}&after={}&contains={}&created_after={}",
+                self.limit,
+                self.sort,
+                self.descending,
+                self.after.as_ref().map(encode_cursor).unwrap_or_default(),
+                self.contains.as_deref().unwrap_or_default(),
+                self.created_after.map(|time| time.to_rfc3339()).unwrap_or_default())
+    }
+
+    /// Builds the `ILIKE` pattern matching any text that contains the `contains` filter literally.
+    pub fn contains_pattern(&self) -> Option<String> {
+        self.contains.as_ref().map(|text| {
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+
+            format!("%{}%", escaped)
+        })
+    }
+
+    /// Builds the page from `rows`, which were loaded with one more row than the limit so that it's known
+    /// whether there is a next page. `position` gives the sort column value and public id of a row.
+    pub fn into_page<T, F>(self, mut rows: Vec<T>, total_count: i64, position: F) -> Page<T>
+        where F: Fn(&T) -> (SortValue, Uuid) {
+        let mut next_cursor = None;
+
+        if rows.len() as i64 > self.limit {
+            rows.truncate(self.limit as usize);
+
+            if let Some(last) = rows.last() {
+                let (value, id) = position(last);
+                next_cursor = Some(encode_cursor(&Cursor {
+                    sort: self.sort,
+                    descending: self.descending,
+                    value,
+                    id,
+                }));
+            }
+        }
+
+        Page {
+            results: rows,
+            next_cursor,
+            total_count,
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes a field that is present in the JSON as `Some`, even if its value is `null`.
+/// Combined with `#[serde(default)]` on an `Option<Option<T>>` field, this tells apart a field that was
+/// left out (`None`, leave the column alone) from one explicitly set to null (`Some(None)`, clear it).
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where T: Deserialize<'de>,
+          D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,180 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Utc};
+use rocket::fs::TempFile;
+use rocket::http::{Accept, QMediaType};
+use uuid::Uuid;
+
+pub const PORTABLE_FORMAT: &str = "todo-backend/list"; // Marks a JSON document as a portable list
+pub const PORTABLE_VERSION: i32 = 1; // Version of the portable list format written by exports
+
+/// A file format that a list can be exported to and imported from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ListFormat {
+    /// The portable JSON format, which keeps everything about the list other than its ids.
+    Json,
+    Csv,
+    /// A Markdown checklist, with one `- [ ]` line per item.
+    Markdown,
+    /// The todo.txt format, with one line per item.
+    TodoTxt,
+}
+
+/// A list and its items in a form that doesn't depend on this server, as written by exports. Tags are those
+/// of the user who exported the list, and assignees are given by username.
+#[derive(Serialize, Deserialize)]
+pub struct PortableList {
+    pub format: String,
+    pub version: i32,
+    #[serde(default)]
+    pub exported_at: Option<DateTime<Utc>>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub items: Vec<PortableItem>,
+}
+
+/// An item in a `PortableList`.
+#[derive(Serialize, Deserialize)]
+pub struct PortableItem {
+    pub description: String,
+    #[serde(default)]
+    pub finished: bool,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// The multipart form used to upload a file to import as a new list.
+#[derive(FromForm)]
+pub struct ImportUpload<'r> {
+    pub file: TempFile<'r>,
+}
+
+/// A line of an imported file that was left out, along with the reason. Lines are counted from 1, or for the
+/// portable JSON format, the items are.
+#[derive(Serialize, Deserialize)]
+pub struct SkippedLine {
+    pub line: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+/// The result of importing a file as a new list. On a dry run nothing is saved, so there is no `list_id`.
+#[derive(Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub list_id: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub items: Vec<PortableItem>,
+    pub skipped: Vec<SkippedLine>,
+}
+
+impl ListFormat {
+    /// Parses the name of a format, as given in `?format=`.
+    pub fn from_name(name: &str) -> Option<ListFormat> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(ListFormat::Json),
+            "csv" => Some(ListFormat::Csv),
+            "markdown" | "md" => Some(ListFormat::Markdown),
+            "todotxt" | "todo.txt" | "txt" => Some(ListFormat::TodoTxt),
+            _ => None,
+        }
+    }
+
+    /// Finds the format with the given media type, such as `text/csv`.
+    pub fn from_media_type(top: &str, sub: &str) -> Option<ListFormat> {
+        match (top.to_lowercase().as_str(), sub.to_lowercase().as_str()) {
+            ("application", "json") => Some(ListFormat::Json),
+            ("text", "csv") => Some(ListFormat::Csv),
+            ("text", "markdown") => Some(ListFormat::Markdown),
+            ("text", "plain") => Some(ListFormat::TodoTxt),
+            _ => None,
+        }
+    }
+
+    /// Finds the format the client prefers in its `Accept` header, trying each media type from the highest
+    /// quality value down, in the order sent when they're equal. Any media type, such as the `*/*` that
+    /// browsers send, gets the portable JSON format. Returns `None` if no media type has a format.
+    pub fn from_accept(accept: &Accept) -> Option<ListFormat> {
+        let mut media_types: Vec<&QMediaType> = accept.iter()
+            .filter(|media_type| media_type.weight_or(1.0) > 0.0)
+            .collect();
+        media_types.sort_by(|a, b| b.weight_or(1.0).partial_cmp(&a.weight_or(1.0)).unwrap_or(Ordering::Equal));
+
+        media_types.into_iter().find_map(|media_type| {
+            let media_type = media_type.media_type();
+            if media_type.is_any() {
+                Some(ListFormat::Json)
+            } else {
+                ListFormat::from_media_type(media_type.top().as_str(), media_type.sub().as_str())
+            }
+        })
+    }
+
+    /// The full content type of files in this format.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ListFormat::Json => "application/json; charset=utf-8",
+            ListFormat::Csv => "text/csv; charset=utf-8",
+            ListFormat::Markdown => "text/markdown; charset=utf-8",
+            ListFormat::TodoTxt => "text/plain; charset=utf-8",
+        }
+    }
+
+    /// The extension of files in this format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ListFormat::Json => "json",
+            ListFormat::Csv => "csv",
+            ListFormat::Markdown => "md",
+            ListFormat::TodoTxt => "txt",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepted(header: &str) -> Option<ListFormat> {
+        ListFormat::from_accept(&header.parse::<Accept>().unwrap())
+    }
+
+    #[test]
+    fn browsers_get_json() {
+        let header = "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8";
+
+        assert!(accepted(header) == Some(ListFormat::Json));
+        assert!(accepted("*/*") == Some(ListFormat::Json));
+    }
+
+    #[test]
+    fn prefers_the_highest_quality_format() {
+        assert!(accepted("text/html, text/csv") == Some(ListFormat::Csv));
+        assert!(accepted("application/json;q=0.5, text/csv") == Some(ListFormat::Csv));
+        assert!(accepted("text/csv;q=0.2, text/markdown;q=0.9, */*;q=0.1") == Some(ListFormat::Markdown));
+        assert!(accepted("text/markdown, text/csv") == Some(ListFormat::Markdown));
+    }
+
+    #[test]
+    fn refuses_formats_it_cant_write() {
+        assert!(accepted("text/html").is_none());
+        assert!(accepted("text/csv;q=0, image/png").is_none());
+    }
+}
@@ -1,4 +1,6 @@
-use rocket::response::status;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, status, Responder};
 use rocket::serde::json::Json;
 use serde_json::Value;
 
@@ -27,4 +29,49 @@ impl ResponseWithStatus {
             },
         }
     }
+}
+
+impl From<ResponseWithStatus> for status::Custom<Json<Response>> {
+    fn from(response: ResponseWithStatus) -> status::Custom<Json<Response>> {
+        status::Custom(
+            Status::from_code(response.status_code).unwrap(),
+            Json(response.response),
+        )
+    }
+}
+
+/// Wraps a response along with the ETag of the resource it describes, if there is one. A response with
+/// the status code 304 is sent without a body.
+#[derive(Debug)]
+pub struct TaggedResponse {
+    pub response: ResponseWithStatus,
+    pub etag: Option<String>,
+}
+
+impl From<ResponseWithStatus> for TaggedResponse {
+    fn from(response: ResponseWithStatus) -> TaggedResponse {
+        TaggedResponse {
+            response,
+            etag: None,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for TaggedResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = Status::from_code(self.response.status_code).unwrap();
+        let mut built = if status == Status::NotModified {
+            rocket::Response::build().status(status).finalize()
+        } else {
+            let mut built = Json(self.response.response).respond_to(request)?;
+            built.set_status(status);
+            built
+        };
+
+        if let Some(etag) = self.etag {
+            built.set_raw_header("ETag", etag);
+        }
+
+        Ok(built)
+    }
 }
\ No newline at end of file
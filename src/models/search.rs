@@ -0,0 +1,135 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Float, Integer, Nullable, Text};
+use uuid::Uuid;
+
+use crate::markdown;
+use crate::models::page::{self, Page};
+
+/// Every list and item that the user given as `$1` can reach and that matches the search query in `$2`,
+/// along with its rank and the `document` text that the matches are highlighted in, without the characters
+/// that mark matches. Lists rank higher on matches in their name than in their description.
+const MATCHES_SQL: &str = "
+    WITH search AS (SELECT websearch_to_tsquery('english', $2) AS query),
+    matches AS (
+        SELECT 'item' AS kind, items.uuid AS id, items.list_uuid AS list_id, items.uuid AS item_id,
+               ts_rank(items.search_vector, search.query) AS rank,
+               translate(items.description, E'\\x02\\x03', '') AS document
+        FROM search, items
+        JOIN lists ON lists.list_id = items.list_id
+        JOIN user_lists ON user_lists.list_id = items.list_id
+        WHERE user_lists.user_id = $1
+          AND items.deleted_at IS NULL
+          AND lists.deleted_at IS NULL
+          AND items.search_vector @@ search.query
+        UNION ALL
+        SELECT 'list' AS kind, lists.uuid AS id, lists.uuid AS list_id, NULL::UUID AS item_id,
+               ts_rank(lists.search_vector, search.query) AS rank,
+               translate(lists.name || E'\\n' || COALESCE(lists.description, ''), E'\\x02\\x03', '') AS document
+        FROM search, lists
+        JOIN user_lists ON user_lists.list_id = lists.list_id
+        WHERE user_lists.user_id = $1
+          AND lists.deleted_at IS NULL
+          AND lists.search_vector @@ search.query
+    )";
+
+/// A single list or item that matched a search, by the public ids of the list and item. `kind` is either `list` or
+/// `item`, and `highlight` is the HTML-escaped text around the matches, with each match wrapped in a `<mark>` element.
+/// The public id of the list or item itself is only kept for the cursor.
+#[derive(QueryableByName, Serialize, Deserialize)]
+pub struct SearchResult {
+    #[sql_type = "Text"]
+    pub kind: String,
+    #[serde(skip)]
+    #[sql_type = "diesel::sql_types::Uuid"]
+    pub id: Uuid,
+    #[sql_type = "diesel::sql_types::Uuid"]
+    pub list_id: Uuid,
+    #[sql_type = "Nullable<diesel::sql_types::Uuid>"]
+    pub item_id: Option<Uuid>,
+    #[sql_type = "Float"]
+    pub rank: f32,
+    #[sql_type = "Text"]
+    pub highlight: String,
+}
+
+/// The number of results of a search.
+#[derive(QueryableByName)]
+struct SearchCount {
+    #[sql_type = "BigInt"]
+    total_count: i64,
+}
+
+/// The position of the last result of the previous page of a search, which is ordered by rank, then kind
+/// and then public id.
+#[derive(Serialize, Deserialize)]
+pub struct SearchCursor {
+    pub rank: f32,
+    pub kind: String,
+    pub id: Uuid,
+}
+
+impl SearchResult {
+    /// Finds a page of `limit` lists and items that the user with `user_id` can reach and that match the
+    /// `query`, best matches first, starting after the position in `after`. The query uses web search syntax,
+    /// so it can contain quoted phrases, `or` and `-` for excluded words.
+    pub fn search(user_id: i32, query: &str, limit: i64, after: Option<SearchCursor>, conn: &PgConnection) -> QueryResult<Page<SearchResult>> {
+        let options = format!(
+            "StartSel=\"{}\", StopSel=\"{}\", MaxFragments=2",
+            markdown::HIGHLIGHT_START,
+            markdown::HIGHLIGHT_END,
+        );
+
+        let total_count = diesel::sql_query(format!("{} SELECT COUNT(*) AS total_count FROM matches", MATCHES_SQL))
+            .bind::<Integer, _>(user_id)
+            .bind::<Text, _>(query)
+            .get_result::<SearchCount>(conn)?
+            .total_count;
+
+        let (after_rank, after_kind, after_id) = match after {
+            Some(cursor) => (Some(cursor.rank), Some(cursor.kind), Some(cursor.id)),
+            None => (None, None, None),
+        };
+
+        // Highlighting is slow, so it's only done for the page of results rather than for every match
+        let mut rows = diesel::sql_query(format!("{}
+            SELECT kind, id, list_id, item_id, rank, ts_headline('english', document, search.query, $3) AS highlight
+            FROM (
+                SELECT * FROM matches
+                WHERE $4::REAL IS NULL OR rank < $4 OR (rank = $4 AND (kind, id) > ($5, $6))
+                ORDER BY rank DESC, kind, id
+                LIMIT $7
+            ) AS page, search
+            ORDER BY rank DESC, kind, id", MATCHES_SQL))
+            .bind::<Integer, _>(user_id)
+            .bind::<Text, _>(query)
+            .bind::<Text, _>(&options)
+            .bind::<Nullable<Float>, _>(after_rank)
+            .bind::<Nullable<Text>, _>(after_kind)
+            .bind::<Nullable<diesel::sql_types::Uuid>, _>(after_id)
+            .bind::<BigInt, _>(limit + 1)
+            .load::<SearchResult>(conn)?;
+
+        for row in rows.iter_mut() {
+            row.highlight = markdown::highlight_html(&row.highlight);
+        }
+
+        let mut next_cursor = None;
+        if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+
+            if let Some(last) = rows.last() {
+                next_cursor = Some(page::encode_cursor(&SearchCursor {
+                    rank: last.rank,
+                    kind: last.kind.clone(),
+                    id: last.id,
+                }));
+            }
+        }
+
+        Ok(Page {
+            results: rows,
+            next_cursor,
+            total_count,
+        })
+    }
+}
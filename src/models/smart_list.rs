@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+use crate::schema::smart_lists;
+
+/// An object representing a full row in the smart_lists table, ie a named item filter saved by a user.
+/// The `filter` is the JSON form of a validated `Filter`.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(smart_list_id)]
+pub struct SmartList {
+    pub smart_list_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub filter: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A transfer object with the information the user provides to save or change a smart list.
+#[derive(Serialize, Deserialize)]
+pub struct SmartListDTO {
+    pub name: String,
+    pub filter: serde_json::Value,
+}
+
+/// A smart list that can be inserted into the smart_lists table.
+#[derive(Insertable)]
+#[table_name = "smart_lists"]
+pub struct NewSmartList {
+    pub user_id: i32,
+    pub name: String,
+    pub filter: serde_json::Value,
+}
+
+/// The items matching a smart list, in the same shape as a complete list. Built-in smart lists have no
+/// `smart_list_id`.
+#[derive(Serialize, Deserialize)]
+pub struct SmartListWithItems<T> {
+    pub smart_list_id: Option<i32>,
+    pub name: String,
+    pub filter: serde_json::Value,
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+impl SmartList {
+    /// Attempts to insert the given `smart_list` for the user with `user_id`. If the row is created, returns
+    /// its contents, otherwise returns none.
+    pub fn create_smart_list(smart_list: SmartListDTO, user_id: i32, conn: &PgConnection) -> Option<SmartList> {
+        let smart_list = NewSmartList {
+            user_id,
+            name: smart_list.name,
+            filter: smart_list.filter,
+        };
+
+        diesel::insert_into(smart_lists::table)
+            .values(&smart_list)
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Finds all of the smart lists of the user with the given `user_id`, ordered by name.
+    pub fn find_smart_lists_for_user(user_id: i32, conn: &PgConnection) -> Vec<SmartList> {
+        let possible_smart_lists = smart_lists::table
+            .filter(smart_lists::user_id.eq(user_id))
+            .order(smart_lists::name)
+            .load::<SmartList>(conn);
+
+        match possible_smart_lists {
+            Ok(result_smart_lists) => result_smart_lists,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Finds the `SmartList` with the given id, if it exists.
+    pub fn find_smart_list_by_id(id: i32, conn: &PgConnection) -> Option<SmartList> {
+        smart_lists::table
+            .filter(smart_lists::smart_list_id.eq(id))
+            .get_result::<SmartList>(conn)
+            .ok()
+    }
+
+    /// Determines whether the user with `user_id` has a smart list called `name`, other than the one with
+    /// `except_id`.
+    pub fn name_taken(user_id: i32, name: &str, except_id: Option<i32>, conn: &PgConnection) -> bool {
+        smart_lists::table
+            .filter(smart_lists::user_id.eq(user_id))
+            .filter(smart_lists::name.eq(name))
+            .filter(smart_lists::smart_list_id.ne(except_id.unwrap_or(0)))
+            .get_result::<SmartList>(conn)
+            .is_ok()
+    }
+
+    /// Attempts to replace the name and filter of the smart list with the given `id`. Returns the changed
+    /// smart list if successful, or none otherwise.
+    pub fn update_smart_list(id: i32, new_smart_list: SmartListDTO, conn: &PgConnection) -> Option<SmartList> {
+        diesel::update(smart_lists::table.filter(smart_lists::smart_list_id.eq(id)))
+            .set((
+                smart_lists::name.eq(new_smart_list.name),
+                smart_lists::filter.eq(new_smart_list.filter),
+            ))
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Attempts to delete the smart list with the given `id`. Returns true if successful or false otherwise.
+    pub fn delete_smart_list(id: i32, conn: &PgConnection) -> bool {
+        diesel::delete(smart_lists::table.filter(smart_lists::smart_list_id.eq(id)))
+            .execute(conn)
+            .is_ok()
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use uuid::Uuid;
+
+use crate::models::item::{Item, ItemDTO, ItemPatchDTO};
+use crate::models::list::{List, ListDTO, ListPatchDTO};
+use crate::schema::{items, lists, sync_changes, sync_mutations, user_lists};
+
+pub static OBJECT_LIST: &str = "list";
+pub static OBJECT_ITEM: &str = "item";
+pub static OBJECT_MEMBERSHIP: &str = "membership";
+
+/// The latest change to a list, item or membership in the list with `list_id`, from the sync_changes table. The
+/// `object_id` of a membership is the id of its user, and it has no `object_uuid`. A deleted object is kept as a
+/// tombstone, along with the public ids that clients know it by.
+#[derive(Queryable)]
+pub struct SyncChange {
+    pub object_type: String,
+    pub object_id: i32,
+    pub list_id: i32,
+    pub is_deleted: bool,
+    pub object_uuid: Option<Uuid>,
+    pub list_uuid: Uuid,
+}
+
+/// The list and item that an offline change a user already synced created or changed, from the sync_mutations table.
+#[derive(Queryable)]
+pub struct SyncMutation {
+    pub list_uuid: Uuid,
+    pub item_uuid: Option<Uuid>,
+}
+
+/// Represents a new row that can be inserted into the sync_mutations table.
+#[derive(Insertable)]
+#[table_name = "sync_mutations"]
+pub struct NewSyncMutation {
+    pub user_id: i32,
+    pub client_id: String,
+    pub list_uuid: Uuid,
+    pub item_uuid: Option<Uuid>,
+}
+
+/// The position that a client synced up to, which clients only see encoded. `xid` is the oldest transaction that
+/// may not have been visible when syncing, and `issued_at` is used to tell whether the deletions since then have
+/// been forgotten.
+#[derive(Serialize, Deserialize)]
+pub struct SyncToken {
+    pub xid: i64,
+    pub issued_at: DateTime<Utc>,
+}
+
+/// A list that the user is a member of, along with when they archived it, if they did.
+#[derive(Serialize)]
+pub struct SyncedList {
+    #[serde(flatten)]
+    pub list: List,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+/// A member of a list, which is sent with the public id of the list.
+#[derive(Queryable, Serialize)]
+pub struct Membership {
+    #[serde(skip)]
+    pub list_id: i32,
+    #[serde(rename = "list_id")]
+    pub list_uuid: Uuid,
+    pub user_id: i32,
+    pub is_owner: bool,
+}
+
+/// A list, item or membership that was deleted, or an item that was moved out of the list with `list_id`. Lists and
+/// items have an `id`, while memberships have the `user_id` of their user instead. A user's own membership being
+/// deleted means they lost the list.
+#[derive(Serialize)]
+pub struct Tombstone {
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub id: Option<Uuid>,
+    pub user_id: Option<i32>,
+    pub list_id: Uuid,
+}
+
+/// Everything that changed for a user since they last synced, and the transaction to sync from next time.
+/// Tombstones are meant to be applied before the changed objects.
+pub struct SyncChanges {
+    pub lists: Vec<SyncedList>,
+    pub items: Vec<Item>,
+    pub memberships: Vec<Membership>,
+    pub deleted: Vec<Tombstone>,
+    pub xid: i64,
+}
+
+/// The changes in a sync response along with the `token` to send with the next sync.
+#[derive(Serialize)]
+pub struct SyncResult {
+    pub lists: Vec<SyncedList>,
+    pub items: Vec<Item>,
+    pub memberships: Vec<Membership>,
+    pub deleted: Vec<Tombstone>,
+    pub token: String,
+}
+
+/// A batch of changes made offline. Each change is applied in order, and then the response has everything that
+/// changed `since` the given token, as with a sync.
+#[derive(Deserialize)]
+pub struct SyncBatchDTO {
+    pub since: Option<String>,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    pub mutations: Vec<Mutation>,
+}
+
+/// What happens to a change made to an older version of a list or item than the one on the server. With
+/// `server_wins` the change isn't applied and comes back as a conflict, and with `client_wins` it's applied
+/// anyway. Changes without a `base_version` are always applied.
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    ServerWins,
+    ClientWins,
+}
+
+// Deriving `Default` for an enum needs a newer compiler than the one the server is built with
+#[allow(clippy::derivable_impls)]
+impl Default for ConflictPolicy {
+    fn default() -> Self {
+        ConflictPolicy::ServerWins
+    }
+}
+
+/// A single change made offline, named by the `client_id` that the client gave it. The `base_version` is the
+/// version of the list or item that the change was made to.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Mutation {
+    CreateList { client_id: String, list: ListDTO },
+    UpdateList { client_id: String, list_id: ObjectRef, base_version: Option<i32>, changes: ListPatchDTO },
+    DeleteList { client_id: String, list_id: ObjectRef, base_version: Option<i32> },
+    CreateItem { client_id: String, list_id: ObjectRef, item: ItemDTO },
+    UpdateItem { client_id: String, list_id: ObjectRef, item_id: ObjectRef, base_version: Option<i32>, changes: ItemPatchDTO },
+    DeleteItem { client_id: String, list_id: ObjectRef, item_id: ObjectRef, base_version: Option<i32> },
+}
+
+/// Refers to a list or item by its public id, or by the `client_id` of the change that created it, which may have been in
+/// the same batch.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ObjectRef {
+    Id(Uuid),
+    ClientId(String),
+}
+
+/// The outcome of a single change in a batch: `applied`, `conflict` or `rejected`. A conflict comes with the
+/// server's copy of the list or item as `current`, and a rejected change with the reason as `message`.
+#[derive(Serialize)]
+pub struct MutationResult {
+    pub client_id: String,
+    pub status: &'static str,
+    pub list_id: Option<Uuid>,
+    pub item_id: Option<Uuid>,
+    pub message: Option<String>,
+    pub current: Option<serde_json::Value>,
+}
+
+/// The outcome of each change in a batch, followed by everything that changed since the last sync.
+#[derive(Serialize)]
+pub struct SyncBatchResult {
+    pub results: Vec<MutationResult>,
+    #[serde(flatten)]
+    pub changes: SyncResult,
+}
+
+impl Mutation {
+    /// Gets the id that the client gave this change.
+    pub fn client_id(&self) -> &str {
+        match self {
+            Mutation::CreateList { client_id, .. } => client_id,
+            Mutation::UpdateList { client_id, .. } => client_id,
+            Mutation::DeleteList { client_id, .. } => client_id,
+            Mutation::CreateItem { client_id, .. } => client_id,
+            Mutation::UpdateItem { client_id, .. } => client_id,
+            Mutation::DeleteItem { client_id, .. } => client_id,
+        }
+    }
+}
+
+impl SyncChange {
+    /// Finds the oldest transaction that may not be visible to the current one. Every change that the current
+    /// transaction can't see was made by that transaction or a newer one.
+    pub fn current_xid(conn: &PgConnection) -> QueryResult<i64> {
+        diesel::select(sql::<BigInt>("txid_snapshot_xmin(txid_current_snapshot())"))
+            .get_result(conn)
+    }
+
+    /// Finds everything that changed in the lists with the given ids, or in the memberships of the user with
+    /// `user_id`, since the transaction `xid`. Changes made by transactions that were still running when the token
+    /// was made are found again.
+    pub fn find_changes_since(xid: i64, list_ids: &[i32], user_id: i32, conn: &PgConnection) -> QueryResult<Vec<SyncChange>> {
+        sync_changes::table
+            .filter(sync_changes::changed_xid.ge(xid))
+            .filter(sync_changes::list_id.eq_any(list_ids)
+                .or(sync_changes::object_type.eq(OBJECT_MEMBERSHIP).and(sync_changes::object_id.eq(user_id))))
+            .select((
+                sync_changes::object_type,
+                sync_changes::object_id,
+                sync_changes::list_id,
+                sync_changes::is_deleted,
+                sync_changes::object_uuid,
+                sync_changes::list_uuid,
+            ))
+            .load(conn)
+    }
+
+    /// Permanently deletes the tombstones of everything deleted before `cutoff`. Returns the number deleted.
+    pub fn purge_tombstones(cutoff: DateTime<Utc>, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(sync_changes::table
+            .filter(sync_changes::is_deleted.eq(true))
+            .filter(sync_changes::changed_at.lt(cutoff)))
+            .execute(conn)
+    }
+}
+
+impl SyncChanges {
+    /// Finds everything that the user with `user_id` needs to know about that changed since the transaction
+    /// `since`, or everything they can access if there is none. Should be run in a repeatable read transaction,
+    /// so that the returned `xid` matches what was found.
+    pub fn find_for_user(user_id: i32, since: Option<i64>, conn: &PgConnection) -> QueryResult<SyncChanges> {
+        let xid = SyncChange::current_xid(conn)?;
+
+        let visible: HashSet<i32> = user_lists::table
+            .inner_join(lists::table)
+            .select(user_lists::list_id)
+            .filter(user_lists::user_id.eq(user_id))
+            .filter(lists::deleted_at.is_null())
+            .load::<i32>(conn)?
+            .into_iter()
+            .collect();
+
+        let changes = match since {
+            Some(since) => {
+                // Lists in the trash are still searched, so that their members hear about them being deleted
+                let member_of = user_lists::table
+                    .select(user_lists::list_id)
+                    .filter(user_lists::user_id.eq(user_id))
+                    .load::<i32>(conn)?;
+
+                SyncChange::find_changes_since(since, &member_of, user_id, conn)?
+            },
+            None => Vec::new(),
+        };
+
+        // Everything in a list is sent when the user joins it, or on the first sync
+        let joined: Vec<i32> = match since {
+            Some(_) => changes.iter()
+                .filter(|change| change.object_type == OBJECT_MEMBERSHIP && change.object_id == user_id)
+                .filter(|change| !change.is_deleted && visible.contains(&change.list_id))
+                .map(|change| change.list_id)
+                .collect(),
+            None => visible.iter().copied().collect(),
+        };
+
+        let changed = |object_type: &str| -> Vec<&SyncChange> {
+            changes.iter()
+                .filter(|change| change.object_type == object_type)
+                .filter(|change| !change.is_deleted && visible.contains(&change.list_id))
+                .collect()
+        };
+
+        let changed_memberships = changed(OBJECT_MEMBERSHIP);
+        let list_ids: Vec<i32> = changed(OBJECT_LIST).iter()
+            .map(|change| change.list_id)
+            .chain(changed_memberships.iter().map(|change| change.list_id))
+            .chain(joined.iter().copied())
+            .collect();
+        let item_ids: Vec<i32> = changed(OBJECT_ITEM).iter().map(|change| change.object_id).collect();
+
+        let lists = user_lists::table
+            .inner_join(lists::table)
+            .select((lists::all_columns, user_lists::archived_at))
+            .filter(user_lists::user_id.eq(user_id))
+            .filter(lists::list_id.eq_any(&list_ids))
+            .filter(lists::deleted_at.is_null())
+            .load::<(List, Option<DateTime<Utc>>)>(conn)?
+            .into_iter()
+            .map(|(list, archived_at)| SyncedList { list, archived_at })
+            .collect();
+
+        let visible_ids: Vec<i32> = visible.iter().copied().collect();
+        let items = items::table
+            .filter(items::list_id.eq_any(&visible_ids))
+            .filter(items::deleted_at.is_null())
+            .filter(items::item_id.eq_any(&item_ids).or(items::list_id.eq_any(&joined)))
+            .order(items::item_id)
+            .load::<Item>(conn)?;
+
+        let changed_members: HashSet<(i32, i32)> = changed_memberships.iter()
+            .map(|change| (change.list_id, change.object_id))
+            .collect();
+        let memberships = user_lists::table
+            .inner_join(lists::table)
+            .select((user_lists::list_id, lists::uuid, user_lists::user_id, user_lists::is_owner))
+            .filter(user_lists::list_id.eq_any(&list_ids))
+            .load::<Membership>(conn)?
+            .into_iter()
+            .filter(|member| joined.contains(&member.list_id) || changed_members.contains(&(member.list_id, member.user_id)))
+            .collect();
+
+        let deleted = changes.into_iter()
+            .filter(|change| change.is_deleted)
+            .map(|change| {
+                let user_id = if change.object_type == OBJECT_MEMBERSHIP { Some(change.object_id) } else { None };
+
+                Tombstone {
+                    object_type: change.object_type,
+                    id: change.object_uuid,
+                    user_id,
+                    list_id: change.list_uuid,
+                }
+            })
+            .collect();
+
+        Ok(SyncChanges { lists, items, memberships, deleted, xid })
+    }
+}
+
+impl SyncMutation {
+    /// Finds the change with `client_id` that the user with `user_id` already synced, if there is one.
+    pub fn find_mutation(user_id: i32, client_id: &str, conn: &PgConnection) -> Option<SyncMutation> {
+        sync_mutations::table
+            .filter(sync_mutations::user_id.eq(user_id))
+            .filter(sync_mutations::client_id.eq(client_id))
+            .select((sync_mutations::list_uuid, sync_mutations::item_uuid))
+            .get_result::<SyncMutation>(conn)
+            .ok()
+    }
+
+    /// Records that the user with `user_id` synced the change with `client_id`, which created or changed the list
+    /// with the public id `list_uuid` and the item with `item_uuid`.
+    pub fn record_mutation(user_id: i32, client_id: &str, list_uuid: Uuid, item_uuid: Option<Uuid>, conn: &PgConnection) -> QueryResult<usize> {
+        let mutation = NewSyncMutation {
+            user_id,
+            client_id: String::from(client_id),
+            list_uuid,
+            item_uuid,
+        };
+
+        diesel::insert_into(sync_mutations::table)
+            .values(&mutation)
+            .execute(conn)
+    }
+
+    /// Permanently deletes the records of changes synced before `cutoff`. Returns the number deleted.
+    pub fn purge_mutations(cutoff: DateTime<Utc>, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(sync_mutations::table.filter(sync_mutations::created_at.lt(cutoff)))
+            .execute(conn)
+    }
+}
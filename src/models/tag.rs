@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+use crate::models::item::Item;
+use crate::schema::{item_tags, items, lists, tags, user_lists};
+
+pub const DEFAULT_COLOR: &str = "#808080"; // Grey, for tags created without a color
+
+/// An object representing a full row in the tags table. Tags belong to a single user, who can put them on
+/// any item they can access.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(tag_id)]
+pub struct Tag {
+    pub tag_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub color: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A transfer object with the information the user provides to create or change a tag. Tags without a
+/// `color` are grey.
+#[derive(Serialize, Deserialize)]
+pub struct TagDTO {
+    pub name: String,
+    pub color: Option<String>,
+}
+
+/// A tag that can be inserted into the tags table.
+#[derive(Insertable)]
+#[table_name = "tags"]
+pub struct NewTag {
+    pub user_id: i32,
+    pub name: String,
+    pub color: String,
+}
+
+impl Tag {
+    /// Attempts to insert the given `tag` for the user with `user_id`. If the row is created, returns its
+    /// contents, otherwise returns none, which includes when the user already has a tag with that name.
+    pub fn create_tag(tag: TagDTO, user_id: i32, conn: &PgConnection) -> Option<Tag> {
+        let tag = NewTag {
+            user_id,
+            name: tag.name,
+            color: tag.color.unwrap_or_else(|| String::from(DEFAULT_COLOR)),
+        };
+
+        diesel::insert_into(tags::table)
+            .values(&tag)
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Finds the tag of the user with `user_id` called `name`, creating it with the default color if they
+    /// don't have one yet.
+    pub fn find_or_create_tag(user_id: i32, name: &str, conn: &PgConnection) -> QueryResult<Tag> {
+        diesel::insert_into(tags::table)
+            .values(&NewTag {
+                user_id,
+                name: String::from(name),
+                color: String::from(DEFAULT_COLOR),
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        tags::table
+            .filter(tags::user_id.eq(user_id))
+            .filter(tags::name.eq(name))
+            .get_result(conn)
+    }
+
+    /// Finds all of the tags of the user with the given `user_id`, ordered by name.
+    pub fn find_tags_for_user(user_id: i32, conn: &PgConnection) -> Vec<Tag> {
+        let possible_tags = tags::table
+            .filter(tags::user_id.eq(user_id))
+            .order(tags::name)
+            .load::<Tag>(conn);
+
+        match possible_tags {
+            Ok(result_tags) => result_tags,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Finds the tag of the user with `user_id` called `name`, if it exists.
+    pub fn find_tag_by_name(user_id: i32, name: &str, conn: &PgConnection) -> Option<Tag> {
+        tags::table
+            .filter(tags::user_id.eq(user_id))
+            .filter(tags::name.eq(name))
+            .get_result::<Tag>(conn)
+            .ok()
+    }
+
+    /// Attempts to rename and recolor the tag with the given `id` to the values in `new_tag`. Returns the
+    /// changed tag if successful, or none otherwise.
+    pub fn update_tag(id: i32, new_tag: TagDTO, conn: &PgConnection) -> Option<Tag> {
+        diesel::update(tags::table.filter(tags::tag_id.eq(id)))
+            .set((
+                tags::name.eq(new_tag.name),
+                tags::color.eq(new_tag.color.unwrap_or_else(|| String::from(DEFAULT_COLOR))),
+            ))
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Attempts to delete the tag with the given `id`, which also removes it from every item. Returns true if
+    /// successful or false otherwise.
+    pub fn delete_tag(id: i32, conn: &PgConnection) -> bool {
+        diesel::delete(tags::table.filter(tags::tag_id.eq(id)))
+            .execute(conn)
+            .is_ok()
+    }
+
+    /// Attempts to put the tag with `tag_id` on the item with `item_id`. Tagging an item that already has the
+    /// tag does nothing. Returns true if successful or false otherwise.
+    pub fn tag_item(tag_id: i32, item_id: i32, conn: &PgConnection) -> bool {
+        diesel::insert_into(item_tags::table)
+            .values((
+                item_tags::item_id.eq(item_id),
+                item_tags::tag_id.eq(tag_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .is_ok()
+    }
+
+    /// Attempts to take the tag with `tag_id` off the item with `item_id`. Returns true if successful or
+    /// false otherwise.
+    pub fn untag_item(tag_id: i32, item_id: i32, conn: &PgConnection) -> bool {
+        diesel::delete(item_tags::table
+            .filter(item_tags::item_id.eq(item_id))
+            .filter(item_tags::tag_id.eq(tag_id)))
+            .execute(conn)
+            .is_ok()
+    }
+
+    /// Attempts to put every tag on the item with `from_item_id` onto the item with `to_item_id`, whoever
+    /// they belong to. Returns the number of tags copied.
+    pub fn copy_tags(from_item_id: i32, to_item_id: i32, conn: &PgConnection) -> QueryResult<usize> {
+        let copies: Vec<_> = item_tags::table
+            .filter(item_tags::item_id.eq(from_item_id))
+            .select(item_tags::tag_id)
+            .load::<i32>(conn)?
+            .into_iter()
+            .map(|tag_id| (
+                item_tags::item_id.eq(to_item_id),
+                item_tags::tag_id.eq(tag_id),
+            ))
+            .collect();
+
+        diesel::insert_into(item_tags::table)
+            .values(&copies)
+            .execute(conn)
+    }
+
+    /// Finds the tags that the user with `user_id` put on the item with `item_id`, ordered by name. Tags put
+    /// on the item by other users aren't included.
+    pub fn find_tags_for_item(item_id: i32, user_id: i32, conn: &PgConnection) -> Vec<Tag> {
+        let possible_tags = tags::table
+            .inner_join(item_tags::table)
+            .filter(item_tags::item_id.eq(item_id))
+            .filter(tags::user_id.eq(user_id))
+            .select(tags::all_columns)
+            .order(tags::name)
+            .load::<Tag>(conn);
+
+        match possible_tags {
+            Ok(result_tags) => result_tags,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Finds the names of the tags that the user with `user_id` put on any of the items with `item_ids`, as
+    /// `(item_id, name)` pairs ordered by name.
+    pub fn find_tag_names_for_items(item_ids: &[i32], user_id: i32, conn: &PgConnection) -> QueryResult<Vec<(i32, String)>> {
+        tags::table
+            .inner_join(item_tags::table)
+            .filter(item_tags::item_id.eq_any(item_ids))
+            .filter(tags::user_id.eq(user_id))
+            .select((item_tags::item_id, tags::name))
+            .order(tags::name)
+            .load(conn)
+    }
+
+    /// Finds every item with the tag with `tag_id` across all of the lists that the user with `user_id` can
+    /// access, leaving out anything in the trash.
+    pub fn find_items_with_tag(tag_id: i32, user_id: i32, conn: &PgConnection) -> Vec<Item> {
+        let possible_items = items::table
+            .inner_join(item_tags::table)
+            .inner_join(user_lists::table.on(user_lists::list_id.eq(items::list_id)))
+            .inner_join(lists::table)
+            .filter(item_tags::tag_id.eq(tag_id))
+            .filter(user_lists::user_id.eq(user_id))
+            .filter(items::deleted_at.is_null())
+            .filter(lists::deleted_at.is_null())
+            .select(items::all_columns)
+            .order(items::item_id)
+            .load::<Item>(conn);
+
+        match possible_items {
+            Ok(result_items) => result_items,
+            Err(_) => vec![],
+        }
+    }
+}
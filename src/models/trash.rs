@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::item::Item;
+use crate::models::list::List;
+
+/// The lists and items in the trash of a single user, most recently deleted first.
+#[derive(Serialize)]
+pub struct Trash {
+    pub lists: Vec<Trashed<List>>,
+    pub items: Vec<Trashed<Item>>,
+}
+
+/// A list or item in the trash, along with when and by whom it was deleted, which are left out everywhere else.
+#[derive(Serialize)]
+pub struct Trashed<T> {
+    #[serde(flatten)]
+    pub object: T,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<i32>,
+}
+
+impl Trash {
+    /// Builds the trash out of the deleted `lists` and `items`.
+    pub fn new(lists: Vec<List>, items: Vec<Item>) -> Trash {
+        Trash {
+            lists: lists.into_iter()
+                .map(|list| Trashed { deleted_at: list.deleted_at, deleted_by: list.deleted_by, object: list })
+                .collect(),
+            items: items.into_iter()
+                .map(|item| Trashed { deleted_at: item.deleted_at, deleted_by: item.deleted_by, object: item })
+                .collect(),
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use bcrypt::{DEFAULT_COST, hash, verify};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use diesel::pg::Pg;
 use diesel::prelude::*;
 use rocket::response::Debug;
@@ -17,6 +19,9 @@ pub struct User {
     pub email: String,
     pub preferred_name: String,
     pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub timezone: String,
 }
 
 /// An object representing the information necessary to construct and insert a new user into the
@@ -61,6 +66,22 @@ pub struct UserInformation {
     pub username: String,
     pub email: String,
     pub preferred_name: String,
+    pub timezone: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<User> for UserInformation {
+    fn from(user: User) -> UserInformation {
+        UserInformation {
+            username: user.username,
+            email: user.email,
+            preferred_name: user.preferred_name,
+            timezone: user.timezone,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
 }
 
 // I'm not sure if JSON objects are necessary/good for these update objects, but I'll go with them
@@ -76,7 +97,23 @@ pub struct UpdatePassword {
     pub password: String,
 }
 
+/// The profile fields of a user to change, where any field left out is untouched. The `timezone` is an
+/// IANA time zone name such as `Europe/Paris`.
+#[derive(AsChangeset, Serialize, Deserialize)]
+#[table_name = "users"]
+pub struct UserPatchDTO {
+    pub email: Option<String>,
+    pub preferred_name: Option<String>,
+    pub timezone: Option<String>,
+}
+
 impl User {
+    /// Gets the time zone that this user's days start in, falling back to UTC if the stored name isn't
+    /// a known time zone.
+    pub fn time_zone(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
     /// Hash the password for the given user and attempt to insert them to the users table.
     pub fn signup(user: UserDTO, conn: &PgConnection) -> bool {
         let new_hash = hash(&user.password, DEFAULT_COST).unwrap();
@@ -146,6 +183,14 @@ impl User {
         }
     }
 
+    /// Finds the usernames of the users with `user_ids`, as `(id, username)` pairs.
+    pub fn find_usernames(user_ids: &[i32], conn: &PgConnection) -> QueryResult<Vec<(i32, String)>> {
+        users
+            .filter(id.eq_any(user_ids))
+            .select((id, username))
+            .load(conn)
+    }
+
     /// Attempts to update the preferred name for the user with username `un` to the new name in `update_name`.
     /// Returns true if successful or false otherwise.
     pub fn update_preferred_name(un: String, update_name: UpdatePreferredName, conn: &PgConnection) -> bool {
@@ -165,4 +210,16 @@ impl User {
             .execute(conn)
             .is_ok()
     }
+
+    /// Attempts to apply the fields present in `patch` to the profile of the user with username `un`,
+    /// returning the updated row.
+    pub fn patch_user(un: String, patch: UserPatchDTO, conn: &PgConnection) -> QueryResult<User> {
+        if patch.email.is_none() && patch.preferred_name.is_none() && patch.timezone.is_none() {
+            return users.filter(username.eq(un)).get_result(conn);
+        }
+
+        diesel::update(users.filter(users::username.eq(un)))
+            .set(&patch)
+            .get_result(conn)
+    }
 }
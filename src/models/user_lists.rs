@@ -1,19 +1,24 @@
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::result::Error;
 
 use crate::models::list::{List, ListDTO};
 use crate::models::user::{User, UserDTO};
+use crate::schema::lists;
 use crate::schema::user_lists;
 use crate::schema::user_lists::dsl;
 
 /// Represents a single row in the user_lists table associating a user with a list that they
-/// have access to.
+/// have access to. The list is archived for that user only when `archived_at` is set.
 #[derive(Identifiable, Queryable, Serialize, Deserialize)]
 pub struct UserList {
     pub id: i32,
     pub user_id: i32,
     pub list_id: i32,
     pub is_owner: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
 /// Represents a new row that can be inserted into the users_lists table.
@@ -40,11 +45,15 @@ impl UserList {
             .is_ok()
     }
 
-    /// Determines whether or not the user with ID `user_id` owns the list with ID `list_id`.
+    /// Determines whether or not the user with ID `user_id` owns the list with ID `list_id`. Lists in
+    /// the trash aren't owned by anyone until they are restored.
     pub fn is_list_owner(list_id: i32, user_id: i32, conn: &PgConnection) -> bool {
         let row: QueryResult<UserList> = dsl::user_lists
+            .inner_join(lists::table)
+            .select(user_lists::all_columns)
             .filter(dsl::user_id.eq(&user_id))
             .filter(dsl::list_id.eq(&list_id))
+            .filter(lists::deleted_at.is_null())
             .get_result::<UserList>(conn);
 
         match row {
@@ -54,11 +63,14 @@ impl UserList {
     }
 
     /// Determines whether or not the user with ID `user_id` has access to edit the list with ID `list_id`.
-    /// Note: a user doesn't have to own a list in order to edit it
+    /// Note: a user doesn't have to own a list in order to edit it, and nobody can edit a list in the trash
     pub fn has_list_access(list_id: i32, user_id: i32, conn: &PgConnection) -> bool {
         let row: QueryResult<UserList> = dsl::user_lists
+            .inner_join(lists::table)
+            .select(user_lists::all_columns)
             .filter(dsl::user_id.eq(&user_id))
             .filter(dsl::list_id.eq(&list_id))
+            .filter(lists::deleted_at.is_null())
             .get_result::<UserList>(conn);
 
         match row {
@@ -67,6 +79,44 @@ impl UserList {
         }
     }
 
+    /// Finds the ids of every list that the user with ID `user_id` has access to, including the lists they
+    /// archived but not those in the trash.
+    pub fn find_list_ids_for_user(user_id: i32, conn: &PgConnection) -> QueryResult<Vec<i32>> {
+        dsl::user_lists
+            .inner_join(lists::table)
+            .select(dsl::list_id)
+            .filter(dsl::user_id.eq(&user_id))
+            .filter(lists::deleted_at.is_null())
+            .load::<i32>(conn)
+    }
+
+    /// Finds the ids of every member of the list with ID `list_id`, whether or not it is in the trash.
+    pub fn find_member_ids(list_id: i32, conn: &PgConnection) -> QueryResult<Vec<i32>> {
+        dsl::user_lists
+            .select(dsl::user_id)
+            .filter(dsl::list_id.eq(&list_id))
+            .order(dsl::user_id)
+            .load::<i32>(conn)
+    }
+
+    /// Archives or unarchives the list with ID `list_id` for the user with ID `user_id` only, leaving the
+    /// list itself untouched. Archiving an already archived list keeps its original `archived_at`.
+    pub fn set_archived(list_id: i32, user_id: i32, archived: bool, conn: &PgConnection) -> QueryResult<usize> {
+        let row = dsl::user_lists
+            .filter(dsl::user_id.eq(&user_id))
+            .filter(dsl::list_id.eq(&list_id));
+
+        if archived {
+            diesel::update(row.filter(dsl::archived_at.is_null()))
+                .set(dsl::archived_at.eq(diesel::dsl::now))
+                .execute(conn)
+        } else {
+            diesel::update(row)
+                .set(dsl::archived_at.eq(None::<DateTime<Utc>>))
+                .execute(conn)
+        }
+    }
+
     /// Finds the `UserList` row connecting the list with the given `list_id` and the user with the
     /// given `user_id` if it exists.
     // pub fn find_connecting_row(list_id: i32, user_id: i32, conn: &PgConnection) -> Option<UserList> {
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Int4, Text};
+use uuid::Uuid;
+
+use crate::models::event::ListEvent;
+use crate::schema::{webhook_deliveries, webhooks};
+
+pub static EVENT_ITEM_FINISHED: &str = "item_finished"; // Filter for updates that mark an item as finished
+pub static DELIVERY_PENDING: &str = "pending";
+pub static DELIVERY_SUCCEEDED: &str = "succeeded";
+pub static DELIVERY_FAILED: &str = "failed";
+
+/// Claims up to `$1` deliveries that are due, whose webhooks are still enabled, by putting off their next attempt
+/// by `$2` seconds so that no other instance sends them in the meantime.
+const CLAIM_SQL: &str = "
+    WITH claimed AS (
+        UPDATE webhook_deliveries SET next_attempt_at = NOW() + $2 * INTERVAL '1 second'
+        WHERE delivery_id IN (
+            SELECT webhook_deliveries.delivery_id FROM webhook_deliveries
+            JOIN webhooks ON webhooks.webhook_id = webhook_deliveries.webhook_id
+            WHERE webhook_deliveries.status = 'pending'
+              AND webhook_deliveries.next_attempt_at <= NOW()
+              AND webhooks.is_enabled
+            ORDER BY webhook_deliveries.next_attempt_at
+            LIMIT $1
+            FOR UPDATE OF webhook_deliveries SKIP LOCKED
+        )
+        RETURNING delivery_id, webhook_id, event_type, payload, attempts
+    )
+    SELECT claimed.*, webhooks.url, webhooks.secret FROM claimed
+    JOIN webhooks ON webhooks.webhook_id = claimed.webhook_id";
+
+/// An object representing a full row in the webhooks table, ie a URL that the changes to a list are sent to.
+/// Only the types of events in `events` are sent, or all of them if it's empty. The secret that signs each
+/// delivery is only shown once, when the webhook is made.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(webhook_id)]
+pub struct Webhook {
+    pub webhook_id: i32,
+    #[serde(skip_serializing)]
+    pub list_id: i32,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub events: Vec<String>,
+    pub is_enabled: bool,
+    pub consecutive_failures: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+/// A transfer object with the URL of a new webhook and the types of events to send to it.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookDTO {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// A transfer object with only the fields of a webhook that should change. Enabling a webhook also clears its
+/// failures, so that one that was disabled for failing can be tried again.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookPatchDTO {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub is_enabled: Option<bool>,
+}
+
+/// The columns that a `WebhookPatchDTO` changes.
+#[derive(AsChangeset)]
+#[table_name = "webhooks"]
+struct WebhookChanges {
+    url: Option<String>,
+    events: Option<Vec<String>>,
+    is_enabled: Option<bool>,
+    consecutive_failures: Option<i32>,
+    disabled_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// A newly made webhook along with the secret that signs its deliveries, which can't be found again later.
+#[derive(Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+/// An object representing a full row in the webhook_deliveries table, ie a single event sent or to be sent to
+/// a webhook, along with the outcome of the last attempt to send it.
+#[derive(Identifiable, Queryable, Serialize, Deserialize)]
+#[primary_key(delivery_id)]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub delivery_id: i32,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A delivery that was claimed for sending, along with the URL and secret of its webhook.
+#[derive(QueryableByName)]
+pub struct PendingDelivery {
+    #[sql_type = "Int4"]
+    pub delivery_id: i32,
+    #[sql_type = "Int4"]
+    pub webhook_id: i32,
+    #[sql_type = "Text"]
+    pub event_type: String,
+    #[sql_type = "Text"]
+    pub payload: String,
+    #[sql_type = "Int4"]
+    pub attempts: i32,
+    #[sql_type = "Text"]
+    pub url: String,
+    #[sql_type = "Text"]
+    pub secret: String,
+}
+
+impl Webhook {
+    /// Attempts to make a new webhook for the list with `list_id`, with a random secret. Returns the new row
+    /// along with the secret.
+    pub fn create_webhook(list_id: i32, webhook: WebhookDTO, conn: &PgConnection) -> QueryResult<CreatedWebhook> {
+        // Two random UUIDs give the secret 244 random bits
+        let secret = format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple());
+
+        let webhook = diesel::insert_into(webhooks::table)
+            .values((
+                webhooks::list_id.eq(list_id),
+                webhooks::url.eq(webhook.url),
+                webhooks::secret.eq(&secret),
+                webhooks::events.eq(webhook.events),
+            ))
+            .get_result(conn)?;
+
+        Ok(CreatedWebhook {
+            webhook,
+            secret,
+        })
+    }
+
+    /// Finds every webhook of the list with `list_id`, oldest first.
+    pub fn find_webhooks_for_list(list_id: i32, conn: &PgConnection) -> QueryResult<Vec<Webhook>> {
+        webhooks::table
+            .filter(webhooks::list_id.eq(list_id))
+            .order(webhooks::webhook_id)
+            .load(conn)
+    }
+
+    /// Finds the webhook with `webhook_id` if it belongs to the list with `list_id`.
+    pub fn find_webhook(webhook_id: i32, list_id: i32, conn: &PgConnection) -> Option<Webhook> {
+        webhooks::table
+            .filter(webhooks::webhook_id.eq(webhook_id))
+            .filter(webhooks::list_id.eq(list_id))
+            .get_result(conn)
+            .ok()
+    }
+
+    /// Updates only the fields present in `patch` for the webhook with `webhook_id` in the list with `list_id`.
+    /// Returns the updated webhook.
+    pub fn patch_webhook(webhook_id: i32, list_id: i32, patch: WebhookPatchDTO, conn: &PgConnection) -> QueryResult<Webhook> {
+        if patch.url.is_none() && patch.events.is_none() && patch.is_enabled.is_none() {
+            return Webhook::find_webhook(webhook_id, list_id, conn).ok_or(diesel::result::Error::NotFound);
+        }
+
+        let changes = WebhookChanges {
+            url: patch.url,
+            events: patch.events,
+            is_enabled: patch.is_enabled,
+            consecutive_failures: patch.is_enabled.filter(|enabled| *enabled).map(|_| 0),
+            disabled_at: patch.is_enabled.map(|enabled| if enabled { None } else { Some(Utc::now()) }),
+        };
+
+        diesel::update(webhooks::table
+            .filter(webhooks::webhook_id.eq(webhook_id))
+            .filter(webhooks::list_id.eq(list_id)))
+            .set(&changes)
+            .get_result(conn)
+    }
+
+    /// Attempts to delete the webhook with `webhook_id` from the list with `list_id`, along with its deliveries.
+    /// Returns true if it was deleted or false otherwise.
+    pub fn delete_webhook(webhook_id: i32, list_id: i32, conn: &PgConnection) -> bool {
+        let deleted = diesel::delete(webhooks::table
+            .filter(webhooks::webhook_id.eq(webhook_id))
+            .filter(webhooks::list_id.eq(list_id)))
+            .execute(conn);
+
+        matches!(deleted, Ok(count) if count > 0)
+    }
+
+    /// Determines whether the given event should be sent to this webhook.
+    pub fn wants(&self, event: &ListEvent) -> bool {
+        self.events.is_empty()
+            || self.events.iter().any(|name| name == event.name())
+            || (event.finishes_item() && self.events.iter().any(|name| name == EVENT_ITEM_FINISHED))
+    }
+
+    /// Counts one more failed delivery to the webhook with `webhook_id`, disabling it once it has failed
+    /// `max_failures` times in a row. Returns true if the webhook was disabled.
+    pub fn record_failure(webhook_id: i32, max_failures: i32, conn: &PgConnection) -> QueryResult<bool> {
+        let failures: i32 = diesel::update(webhooks::table.find(webhook_id))
+            .set(webhooks::consecutive_failures.eq(webhooks::consecutive_failures + 1))
+            .returning(webhooks::consecutive_failures)
+            .get_result(conn)?;
+
+        if failures < max_failures {
+            return Ok(false);
+        }
+
+        diesel::update(webhooks::table.find(webhook_id).filter(webhooks::is_enabled))
+            .set((webhooks::is_enabled.eq(false), webhooks::disabled_at.eq(diesel::dsl::now)))
+            .execute(conn)?;
+
+        Ok(true)
+    }
+
+    /// Clears the failures of the webhook with `webhook_id` after a delivery succeeded.
+    pub fn record_success(webhook_id: i32, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::update(webhooks::table.find(webhook_id))
+            .set(webhooks::consecutive_failures.eq(0))
+            .execute(conn)
+    }
+}
+
+impl WebhookDelivery {
+    /// Queues the given event, serialized as `payload`, for every enabled webhook of its list that wants it.
+    /// Returns the number of deliveries queued.
+    pub fn enqueue(event: &ListEvent, payload: &str, conn: &PgConnection) -> QueryResult<usize> {
+        let webhooks: Vec<Webhook> = webhooks::table
+            .filter(webhooks::list_id.eq(event.list_id()))
+            .filter(webhooks::is_enabled)
+            .load(conn)?;
+
+        let deliveries: Vec<_> = webhooks.iter()
+            .filter(|webhook| webhook.wants(event))
+            .map(|webhook| (
+                webhook_deliveries::webhook_id.eq(webhook.webhook_id),
+                webhook_deliveries::event_type.eq(event.name()),
+                webhook_deliveries::payload.eq(payload),
+            ))
+            .collect();
+
+        if deliveries.is_empty() {
+            return Ok(0);
+        }
+
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .execute(conn)
+    }
+
+    /// Finds the latest `limit` deliveries to the webhook with `webhook_id`, newest first.
+    pub fn find_deliveries_for_webhook(webhook_id: i32, limit: i64, conn: &PgConnection) -> QueryResult<Vec<WebhookDelivery>> {
+        webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+            .order(webhook_deliveries::delivery_id.desc())
+            .limit(limit)
+            .load(conn)
+    }
+
+    /// Claims up to `limit` deliveries that are due to be sent, keeping them from being claimed again for
+    /// `lease_seconds` while they are sent.
+    pub fn claim_due_deliveries(limit: i64, lease_seconds: i64, conn: &PgConnection) -> QueryResult<Vec<PendingDelivery>> {
+        diesel::sql_query(CLAIM_SQL)
+            .bind::<BigInt, _>(limit)
+            .bind::<BigInt, _>(lease_seconds)
+            .load(conn)
+    }
+
+    /// Marks the delivery with `delivery_id` as sent, with the status that the webhook responded with.
+    pub fn mark_succeeded(delivery_id: i32, response_status: i32, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::update(webhook_deliveries::table.find(delivery_id))
+            .set((
+                webhook_deliveries::status.eq(DELIVERY_SUCCEEDED),
+                webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                webhook_deliveries::last_attempt_at.eq(diesel::dsl::now),
+                webhook_deliveries::response_status.eq(Some(response_status)),
+                webhook_deliveries::error.eq(None::<String>),
+            ))
+            .execute(conn)
+    }
+
+    /// Notes a failed attempt to send the delivery with `delivery_id`, with the status that the webhook responded
+    /// with if it responded at all. The delivery is tried again at `retry_at`, or given up on if there is none.
+    pub fn mark_failed(delivery_id: i32, response_status: Option<i32>, error: &str, retry_at: Option<DateTime<Utc>>,
+                       conn: &PgConnection) -> QueryResult<usize> {
+        let status = if retry_at.is_some() { DELIVERY_PENDING } else { DELIVERY_FAILED };
+
+        diesel::update(webhook_deliveries::table.find(delivery_id))
+            .set((
+                webhook_deliveries::status.eq(status),
+                webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                webhook_deliveries::next_attempt_at.eq(retry_at.unwrap_or_else(Utc::now)),
+                webhook_deliveries::last_attempt_at.eq(diesel::dsl::now),
+                webhook_deliveries::response_status.eq(response_status),
+                webhook_deliveries::error.eq(error),
+            ))
+            .execute(conn)
+    }
+
+    /// Gives up on every delivery still waiting to be sent to the webhook with `webhook_id`, noting `error`.
+    pub fn abandon_pending_deliveries(webhook_id: i32, error: &str, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::update(webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+            .filter(webhook_deliveries::status.eq(DELIVERY_PENDING)))
+            .set((
+                webhook_deliveries::status.eq(DELIVERY_FAILED),
+                webhook_deliveries::error.eq(error),
+            ))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,502 @@
+use std::convert::TryFrom;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// The fields of an item parsed out of a quick-add string such as
+/// `Pay rent tomorrow 9am !high #bills @alice every month`. Whatever isn't recognized is left in the
+/// `description`.
+#[derive(Serialize, Deserialize)]
+pub struct QuickAdd {
+    pub description: String,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<String>,
+    pub tags: Vec<String>,
+    pub assignee: Option<String>,
+    pub recurrence: Option<String>,
+}
+
+/// The date, time and recurrence found so far while parsing.
+#[derive(Default)]
+struct Schedule {
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    recurrence: Option<String>,
+    recurrence_day: Option<Weekday>,
+}
+
+/// The largest number of days, weeks, months or years a relative date such as `in 3 days` can be ahead.
+const MAX_RELATIVE_AMOUNT: u32 = 10_000;
+
+static MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// Parses a quick-add `text` typed by a user in the time zone `tz` at the time `now`. Relative dates are
+/// resolved in that time zone, dates without a time are due at the end of the day, and times without a date
+/// are due at their next occurrence. Only the first date, time, priority, assignee and recurrence are used,
+/// so that repeated words stay in the description.
+pub fn parse(text: &str, tz: Tz, now: DateTime<Utc>) -> QuickAdd {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let trimmed: Vec<&str> = words.iter()
+        .map(|word| word.trim_end_matches(&[',', '.'][..]))
+        .collect();
+    let lower: Vec<String> = trimmed.iter().map(|word| word.to_lowercase()).collect();
+
+    let local_now = now.with_timezone(&tz).naive_local();
+    let today = local_now.date();
+
+    let mut description = vec![];
+    let mut priority = None;
+    let mut tags: Vec<String> = vec![];
+    let mut assignee = None;
+    let mut schedule = Schedule::default();
+
+    let mut i = 0;
+    while i < words.len() {
+        let word = lower[i].as_str();
+
+        if let Some(level) = word.strip_prefix('!').and_then(parse_priority) {
+            if priority.is_none() {
+                priority = Some(String::from(level));
+                i += 1;
+                continue;
+            }
+        } else if let Some(tag) = trimmed[i].strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            if !tags.iter().any(|existing| existing == tag) {
+                tags.push(String::from(tag));
+            }
+
+            i += 1;
+            continue;
+        } else if let Some(username) = trimmed[i].strip_prefix('@').filter(|username| !username.is_empty()) {
+            if assignee.is_none() {
+                assignee = Some(String::from(username));
+                i += 1;
+                continue;
+            }
+        } else if let Some(used) = parse_schedule(&lower[i..], today, &mut schedule) {
+            i += used;
+            continue;
+        }
+
+        description.push(words[i]);
+        i += 1;
+    }
+
+    // A recurrence on a day of the week starts on the next such day if there's no other date, which is only
+    // today if its time hasn't passed yet
+    if schedule.date.is_none() {
+        if let Some(day) = schedule.recurrence_day {
+            let time_passed = schedule.time.map_or(false, |time| time <= local_now.time());
+            schedule.date = Some(next_weekday(today, day, !time_passed));
+        }
+    }
+
+    let due_at = match (schedule.date, schedule.time) {
+        (Some(date), Some(time)) => Some(local_time(tz, date, time)),
+        (Some(date), None) => Some(local_time(tz, date, NaiveTime::from_hms_opt(23, 59, 59).unwrap())),
+        (None, Some(time)) if time > local_now.time() => Some(local_time(tz, today, time)),
+        (None, Some(time)) => Some(local_time(tz, today + Duration::days(1), time)),
+        (None, None) => None,
+    };
+
+    QuickAdd {
+        description: description.join(" "),
+        due_at,
+        priority,
+        tags,
+        assignee,
+        recurrence: schedule.recurrence,
+    }
+}
+
+/// Parses a priority level given after a `!`, either by name or as a number where 1 is highest.
+fn parse_priority(level: &str) -> Option<&'static str> {
+    match level {
+        "high" | "h" | "1" | "!!" => Some("high"),
+        "medium" | "med" | "m" | "2" | "!" => Some("medium"),
+        "low" | "l" | "3" => Some("low"),
+        _ => None,
+    }
+}
+
+/// Tries to parse a date, time or recurrence at the start of `words`, which are in lower case, recording it in
+/// the `schedule` if it doesn't already have one. Returns the number of words used, if any.
+fn parse_schedule(words: &[String], today: NaiveDate, schedule: &mut Schedule) -> Option<usize> {
+    let word = words[0].as_str();
+
+    // Connecting words are only dropped along with the date or time that follows them
+    if matches!(word, "on" | "at" | "by" | "due") && words.len() > 1 {
+        let used = parse_schedule(&words[1..], today, schedule)?;
+        return Some(used + 1);
+    }
+
+    if schedule.recurrence.is_none() {
+        if let Some((recurrence, day, used)) = parse_recurrence(words) {
+            schedule.recurrence = Some(recurrence);
+            schedule.recurrence_day = day;
+            return Some(used);
+        }
+    }
+
+    if schedule.date.is_none() {
+        if let Some((date, used)) = parse_date(words, today) {
+            schedule.date = Some(date);
+            return Some(used);
+        }
+    }
+
+    if schedule.time.is_none() {
+        if let Some((time, used)) = parse_time(words) {
+            schedule.time = Some(time);
+            return Some(used);
+        }
+    }
+
+    None
+}
+
+/// Parses a recurrence such as `daily`, `every month`, `every other week`, `every 3 days` or `every monday`
+/// into an RRULE, along with the day of the week it happens on if there is one.
+fn parse_recurrence(words: &[String]) -> Option<(String, Option<Weekday>, usize)> {
+    let rule = |freq: &str, interval: u32| if interval == 1 {
+        format!("FREQ={}", freq)
+    } else {
+        format!("FREQ={};INTERVAL={}", freq, interval)
+    };
+
+    match words[0].as_str() {
+        "daily" => return Some((rule("DAILY", 1), None, 1)),
+        "weekly" => return Some((rule("WEEKLY", 1), None, 1)),
+        "monthly" => return Some((rule("MONTHLY", 1), None, 1)),
+        "yearly" | "annually" => return Some((rule("YEARLY", 1), None, 1)),
+        "every" if words.len() > 1 => {}
+        _ => return None,
+    }
+
+    if let Some(day) = parse_weekday(&words[1]) {
+        let rule = format!("FREQ=WEEKLY;BYDAY={}", weekday_code(day));
+        return Some((rule, Some(day), 2));
+    }
+
+    let (interval, unit_index) = match words[1].as_str() {
+        "other" => (2, 2),
+        number => match number.parse::<u32>() {
+            Ok(interval) if interval > 0 => (interval, 2),
+            Ok(_) => return None,
+            Err(_) => (1, 1),
+        },
+    };
+
+    let freq = parse_unit(words.get(unit_index)?)?;
+    Some((rule(freq, interval), None, unit_index + 1))
+}
+
+/// Parses a unit of time into the matching RRULE frequency.
+fn parse_unit(unit: &str) -> Option<&'static str> {
+    match unit {
+        "day" | "days" => Some("DAILY"),
+        "week" | "weeks" => Some("WEEKLY"),
+        "month" | "months" => Some("MONTHLY"),
+        "year" | "years" => Some("YEARLY"),
+        _ => None,
+    }
+}
+
+/// Parses a date relative to `today`, such as `today`, `tomorrow`, `friday`, `next week` or `in 3 days`, or an
+/// absolute date such as `2021-08-20`, `aug 20` or `20th august`. Absolute dates without a year are in the
+/// next year if they have already passed.
+fn parse_date(words: &[String], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let word = words[0].as_str();
+
+    match word {
+        "today" | "tonight" => return Some((today, 1)),
+        "tomorrow" | "tmrw" | "tmr" => return Some((today + Duration::days(1), 1)),
+        _ => {}
+    }
+
+    if let Some(day) = parse_weekday(word) {
+        return Some((next_weekday(today, day, false), 1));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+        return Some((date, 1));
+    }
+
+    let next = words.get(1).map(String::as_str);
+
+    if word == "next" {
+        return match next? {
+            "week" => Some((next_weekday(today, Weekday::Mon, false), 2)),
+            "month" => Some((first_of_next_month(today)?, 2)),
+            next_word => parse_weekday(next_word).map(|day| (next_weekday(today, day, false), 2)),
+        };
+    }
+
+    // Amounts too large to be meant as dates, or that would leave the range of dates, are left in the
+    // description
+    if word == "in" {
+        let amount = match next? {
+            "a" | "an" | "one" => 1,
+            number => number.parse::<u32>().ok().filter(|amount| (1..=MAX_RELATIVE_AMOUNT).contains(amount))?,
+        };
+
+        let date = match parse_unit(words.get(2)?)? {
+            "DAILY" => today.checked_add_signed(Duration::days(amount.into())),
+            "WEEKLY" => today.checked_add_signed(Duration::weeks(amount.into())),
+            "MONTHLY" => add_months(today, amount),
+            _ => add_months(today, amount.checked_mul(12)?),
+        };
+
+        return Some((date?, 3));
+    }
+
+    // Month names either before or after the day of the month
+    let (month, day) = match (parse_month(word), next.and_then(parse_day)) {
+        (Some(month), Some(day)) => (month, day),
+        _ => match (parse_day(word), next.and_then(parse_month)) {
+            (Some(day), Some(month)) => (month, day),
+            _ => return None,
+        },
+    };
+
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if date < today {
+        Some((NaiveDate::from_ymd_opt(today.year() + 1, month, day)?, 2))
+    } else {
+        Some((date, 2))
+    }
+}
+
+/// Parses a time of day such as `9am`, `9:30 pm`, `21:00` or `noon`. Bare numbers aren't times, since they
+/// are much more likely to be quantities.
+fn parse_time(words: &[String]) -> Option<(NaiveTime, usize)> {
+    let word = words[0].as_str();
+
+    match word {
+        "noon" | "midday" => return Some((NaiveTime::from_hms_opt(12, 0, 0).unwrap(), 1)),
+        "midnight" => return Some((NaiveTime::from_hms_opt(23, 59, 59).unwrap(), 1)),
+        _ => {}
+    }
+
+    let (clock, meridiem, used) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(false), 1)
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(true), 1)
+    } else {
+        match words.get(1).map(String::as_str) {
+            Some("am") => (word, Some(false), 2),
+            Some("pm") => (word, Some(true), 2),
+            _ => (word, None, 1),
+        }
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None if meridiem.is_some() => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match meridiem {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => hour,
+    };
+
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, used))
+}
+
+/// Parses the full or abbreviated name of a day of the week.
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" | "tues" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" | "thur" | "thurs" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Parses the full or abbreviated name of a month into its number.
+fn parse_month(word: &str) -> Option<u32> {
+    if word.len() < 3 {
+        return None;
+    }
+
+    MONTHS.iter()
+        .position(|month| word.starts_with(month) && (word.len() == 3 || full_month_name(month).starts_with(word)))
+        .map(|index| index as u32 + 1)
+}
+
+/// Gets the full name of the month with the given abbreviation.
+fn full_month_name(month: &str) -> &'static str {
+    match month {
+        "jan" => "january",
+        "feb" => "february",
+        "mar" => "march",
+        "apr" => "april",
+        "may" => "may",
+        "jun" => "june",
+        "jul" => "july",
+        "aug" => "august",
+        "sep" => "september",
+        "oct" => "october",
+        "nov" => "november",
+        _ => "december",
+    }
+}
+
+/// Parses a day of the month, with or without an ordinal suffix such as `th`.
+fn parse_day(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+
+    digits.parse::<u32>().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// Finds the next date after `today` that falls on `day`, or `today` itself if `include_today` is set.
+fn next_weekday(today: NaiveDate, day: Weekday, include_today: bool) -> NaiveDate {
+    let days_ahead = (day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64 + 7) % 7;
+
+    if days_ahead == 0 && !include_today {
+        today + Duration::days(7)
+    } else {
+        today + Duration::days(days_ahead)
+    }
+}
+
+/// Finds the first day of the month after the one containing `date`.
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    if date.month() == 12 {
+        NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+    }
+}
+
+/// Adds `months` to `date`, moving to the last day of the month if the day doesn't exist in it. Returns `None`
+/// if the result is out of range.
+fn add_months(date: NaiveDate, months: u32) -> Option<NaiveDate> {
+    let total = (date.year() * 12 + date.month0() as i32).checked_add(i32::try_from(months).ok()?)?;
+    let (year, month) = (total / 12, total as u32 % 12 + 1);
+
+    (1..=date.day()).rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+}
+
+/// Converts a local `date` and `time` in the time zone `tz` to UTC. Times skipped by daylight saving are moved
+/// forward by an hour.
+pub fn local_time(tz: Tz, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    let local = date.and_time(time);
+
+    tz.from_local_datetime(&local).earliest()
+        .or_else(|| local.checked_add_signed(Duration::hours(1)).and_then(|later| tz.from_local_datetime(&later).earliest()))
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&local))
+}
+
+/// Gets the two letter iCalendar code for a day of the week.
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn utc(date: NaiveDate, hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&date.and_hms_opt(hour, minute, second).unwrap())
+    }
+
+    /// Monday 2 August 2021 at 10:00 UTC.
+    fn now() -> DateTime<Utc> {
+        utc(date(2021, 8, 2), 10, 0, 0)
+    }
+
+    fn end_of_day(year: i32, month: u32, day: u32) -> Option<DateTime<Utc>> {
+        Some(utc(date(year, month, day), 23, 59, 59))
+    }
+
+    #[test]
+    fn parses_every_field() {
+        let parsed = parse("Pay rent tomorrow 9am !high #bills @alice every month", Tz::UTC, now());
+
+        assert_eq!(parsed.description, "Pay rent");
+        assert_eq!(parsed.due_at, Some(utc(date(2021, 8, 3), 9, 0, 0)));
+        assert_eq!(parsed.priority.as_deref(), Some("high"));
+        assert_eq!(parsed.tags, vec!["bills"]);
+        assert_eq!(parsed.assignee.as_deref(), Some("alice"));
+        assert_eq!(parsed.recurrence.as_deref(), Some("FREQ=MONTHLY"));
+    }
+
+    #[test]
+    fn parses_relative_dates() {
+        let due_at = |text| parse(text, Tz::UTC, now()).due_at;
+
+        assert_eq!(due_at("Buy milk in 3 days"), end_of_day(2021, 8, 5));
+        assert_eq!(due_at("Buy milk in a week"), end_of_day(2021, 8, 9));
+        assert_eq!(due_at("Buy milk in 2 months"), end_of_day(2021, 10, 2));
+        assert_eq!(due_at("Buy milk in 10000 years"), end_of_day(12021, 8, 2));
+    }
+
+    #[test]
+    fn resolves_dates_in_the_time_zone() {
+        let parsed = parse("Call home tonight", Tz::Pacific__Auckland, now());
+
+        assert_eq!(parsed.due_at, Some(utc(date(2021, 8, 2), 11, 59, 59)));
+    }
+
+    #[test]
+    fn leaves_overflowing_amounts_in_the_description() {
+        for text in &[
+            "Buy milk in 100000000 days",
+            "Buy milk in 999999999 weeks",
+            "Buy milk in 4294967295 months",
+            "Buy milk in 999999999 years",
+            "Buy milk in 99999999999999999999 days",
+            "Buy milk in 10001 days",
+        ] {
+            let parsed = parse(text, Tz::UTC, now());
+
+            assert_eq!(parsed.description, *text);
+            assert_eq!(parsed.due_at, None);
+        }
+    }
+
+    #[test]
+    fn dates_at_the_ends_of_the_calendar_are_kept() {
+        for tz in &[Tz::UTC, Tz::Pacific__Auckland, Tz::America__New_York] {
+            assert!(parse("Bury time capsule +262142-12-31 11pm", *tz, now()).due_at.is_some());
+            assert!(parse("Dig up time capsule -262143-01-01 1am", *tz, now()).due_at.is_some());
+        }
+    }
+
+    #[test]
+    fn adding_months_keeps_to_the_end_of_the_month() {
+        let end_of_january = date(2021, 1, 31);
+
+        assert_eq!(add_months(end_of_january, 1), Some(date(2021, 2, 28)));
+        assert_eq!(add_months(end_of_january, 13), Some(date(2022, 2, 28)));
+        assert_eq!(add_months(end_of_january, u32::MAX), None);
+        assert_eq!(add_months(end_of_january, i32::MAX as u32), None);
+    }
+}
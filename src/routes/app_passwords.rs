@@ -0,0 +1,43 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::models::app_password::AppPasswordDTO;
+use crate::models::response::Response;
+use crate::services::app_password_service;
+
+/// Attempts to get the app passwords of the logged-in user.
+#[get("/users/app-passwords")]
+pub async fn get_app_passwords(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = app_password_service::get_app_passwords(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to make a new app password for the logged-in user, for an app such as a CalDAV client that can
+/// only log in with a username and password.
+#[post("/users/app-passwords", format = "json", data = "<app_password>")]
+pub async fn create_app_password(app_password: Json<AppPasswordDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = app_password_service::create_app_password(token.id, app_password.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to revoke one of the logged-in user's app passwords.
+#[delete("/users/app-passwords/<app_password_id>")]
+pub async fn delete_app_password(app_password_id: i32, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = app_password_service::delete_app_password(token.id, app_password_id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use rocket::form::Form;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::keys::ItemKeys;
+use crate::models::attachment::AttachmentUpload;
+use crate::models::response::Response;
+use crate::services::attachment_service;
+use crate::storage::Storage;
+
+/// The contents of a downloaded attachment, sent with its sniffed content type and original file name. Browsers are
+/// told not to sniff the type again, so that an upload is never rendered as something other than what it was stored as.
+#[derive(Responder)]
+pub struct AttachmentFile {
+    contents: Vec<u8>,
+    content_type: ContentType,
+    content_disposition: Header<'static>,
+    nosniff: Header<'static>,
+}
+
+/// Attempts to get the metadata for every attachment on an item.
+#[get("/lists/<_>/<_>/attachments")]
+pub async fn get_attachments(token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = attachment_service::get_attachments(keys.list_id, keys.item_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to upload a file as a multipart form and attach it to an item.
+#[post("/lists/<_>/<_>/attachments", data = "<upload>")]
+pub async fn post_attachment(upload: Form<AttachmentUpload<'_>>, token: UserToken, keys: ItemKeys,
+                             storage: &State<Arc<dyn Storage>>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let mut upload = upload.into_inner();
+    let response = attachment_service::upload_attachment(keys.list_id, keys.item_id, token.id, &mut upload.file, storage.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to download the contents of an attachment.
+#[get("/lists/<_>/<_>/attachments/<attachment_id>")]
+pub async fn get_attachment(attachment_id: i32, token: UserToken, keys: ItemKeys,
+                            storage: &State<Arc<dyn Storage>>, db: PostgresDbConn) -> Result<AttachmentFile, status::Custom<Json<Response>>> {
+    match attachment_service::download_attachment(keys.list_id, keys.item_id, attachment_id, token.id, storage.inner().clone(), db).await {
+        Ok((attachment, contents)) => Ok(AttachmentFile {
+            contents,
+            content_type: ContentType::parse_flexible(&attachment.content_type).unwrap_or(ContentType::Binary),
+            content_disposition: Header::new("Content-Disposition",
+                                             format!("attachment; filename=\"{}\"", quoted_file_name(&attachment.file_name))),
+            nosniff: Header::new("X-Content-Type-Options", "nosniff"),
+        }),
+        Err(response) => Err(status::Custom(
+            Status::from_code(response.status_code).unwrap(),
+            Json(response.response),
+        )),
+    }
+}
+
+/// Attempts to delete an attachment from an item.
+#[delete("/lists/<_>/<_>/attachments/<attachment_id>")]
+pub async fn delete_attachment(attachment_id: i32, token: UserToken, keys: ItemKeys,
+                               storage: &State<Arc<dyn Storage>>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = attachment_service::delete_attachment(keys.list_id, keys.item_id, attachment_id, token.id, storage.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Replaces anything in `file_name` that can't safely appear inside a quoted header parameter.
+fn quoted_file_name(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,56 @@
+use rocket::http::{ContentType, Status};
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::models::response::Response;
+use crate::services::calendar_service;
+
+/// Attempts to get the secret URL of the logged-in user's calendar feed, making one if they don't have one.
+#[get("/users/calendar")]
+pub async fn get_calendar_feed(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = calendar_service::get_feed(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to replace the secret URL of the logged-in user's calendar feed, revoking the old one.
+#[post("/users/calendar/regenerate")]
+pub async fn regenerate_calendar_feed(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = calendar_service::regenerate_feed(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to turn off the logged-in user's calendar feed.
+#[delete("/users/calendar")]
+pub async fn delete_calendar_feed(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = calendar_service::delete_feed(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get a calendar feed as an iCalendar file. Calendar apps can't send an `Authorization`
+/// header, so the feed is found by the secret token in its file name instead, such as `<token>.ics`.
+#[get("/calendar/<file_name>")]
+pub async fn get_calendar(file_name: &str, db: PostgresDbConn) -> Result<(ContentType, String), status::Custom<Json<Response>>> {
+    let token = file_name.strip_suffix(".ics").unwrap_or(file_name).to_string();
+
+    match calendar_service::render_feed(token, db).await {
+        Ok(calendar) => Ok((ContentType::parse_flexible("text/calendar; charset=utf-8").unwrap(), calendar)),
+        Err(response) => Err(status::Custom(
+            Status::from_code(response.status_code).unwrap(),
+            Json(response.response),
+        )),
+    }
+}
@@ -0,0 +1,54 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::keys::ItemKeys;
+use crate::models::comment::CommentDTO;
+use crate::models::response::Response;
+use crate::services::comment_service;
+
+/// Attempts to get the comment thread on an item.
+#[get("/lists/<_>/<_>/comments")]
+pub async fn get_comments(token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = comment_service::get_comments(keys.list_id, keys.item_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to add a comment to an item.
+#[post("/lists/<_>/<_>/comments", format = "json", data = "<comment>")]
+pub async fn post_comment(comment: Json<CommentDTO>, token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = comment_service::add_comment(keys.list_id, keys.item_id, token.id, comment.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to edit one of the logged-in user's comments.
+#[put("/lists/<_>/<_>/comments/<comment_id>", format = "json", data = "<comment>")]
+pub async fn put_comment(comment_id: i32, comment: Json<CommentDTO>, token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = comment_service::put_comment(keys.list_id, keys.item_id, comment_id, token.id, comment.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to delete a comment from an item.
+#[delete("/lists/<_>/<_>/comments/<comment_id>")]
+pub async fn delete_comment(comment_id: i32, token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = comment_service::delete_comment(keys.list_id, keys.item_id, comment_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
@@ -0,0 +1,33 @@
+use rocket::futures::Stream;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::Json;
+use rocket::State;
+use uuid::Uuid;
+
+use crate::auth::UserToken;
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::events::EventBus;
+use crate::models::response::Response;
+use crate::services::{event_service, list_service};
+
+/// Attempts to follow the changes to a list, or to every list of the logged-in user if no `list_id` is given, as
+/// server-sent events.
+#[get("/events?<list_id>")]
+pub async fn get_events(list_id: Option<Uuid>, token: UserToken, events: &State<EventBus>, pool: &State<PostgresPool>,
+                        db: PostgresDbConn) -> Result<EventStream<impl Stream<Item = Event>>, status::Custom<Json<Response>>> {
+    let list_id = match list_id {
+        Some(list_id) => match list_service::find_list_id(list_id, &db).await {
+            Ok(list_id) => Some(list_id),
+            Err(response) => return Err(response.into()),
+        },
+        None => None,
+    };
+
+    event_service::follow_lists(token.id, list_id, events.inner().clone(), pool.inner().clone(), db).await
+        .map_err(|response| status::Custom(
+            Status::from_code(response.status_code).unwrap(),
+            Json(response.response),
+        ))
+}
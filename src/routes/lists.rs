@@ -1,22 +1,43 @@
-use rocket::http::Status;
+use std::sync::Arc;
+
+use rocket::form::Form;
+use rocket::http::{Accept, ContentType, Header, Status};
 use rocket::response::Debug;
 use rocket::response::status;
 use rocket::response::status::Created;
 use rocket::serde::json::Json;
+use rocket::State;
 use rocket_sync_db_pools::diesel;
 use rocket_sync_db_pools::diesel::prelude::*;
 
 use crate::auth::UserToken;
+use crate::constants;
 use crate::database::PostgresDbConn;
-use crate::models::item::ItemDTO;
-use crate::models::list::ListDTO;
-use crate::models::response::Response;
-use crate::services::list_service;
+use crate::etag::Preconditions;
+use crate::events::EventBus;
+use crate::keys::{ItemKeys, ListKey};
+use crate::markdown;
+use crate::models::item::{FinishedDTO, ItemDTO, ItemFilter, ItemPatchDTO, ItemTransferDTO, QuickAddDTO};
+use crate::models::list::{ListDTO, ListPatchDTO};
+use crate::models::page::PageQuery;
+use crate::models::portable::{ImportUpload, ListFormat};
+use crate::models::response::{Response, ResponseWithStatus, TaggedResponse};
+use crate::services::{export_service, import_service, list_service};
+use crate::storage::Storage;
+
+/// The contents of an exported list, sent with the content type of its format and a file name made from
+/// the name of the list.
+#[derive(Responder)]
+pub struct ExportFile {
+    contents: String,
+    content_type: ContentType,
+    content_disposition: Header<'static>,
+}
 
 /// Attempts to create a new list
 #[post("/lists", format = "json", data = "<new_list>")]
-pub async fn create_list(new_list: Json<ListDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::create_list(new_list.into_inner(), token.username, db).await;
+pub async fn create_list(new_list: Json<ListDTO>, token: UserToken, events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::create_list(new_list.into_inner(), token.username, events.inner().clone(), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -25,10 +46,73 @@ pub async fn create_list(new_list: Json<ListDTO>, token: UserToken, db: Postgres
 }
 
 /// Attempts to add an item to an existing list.
-#[post("/lists/<list_id>/add", format = "json", data = "<new_item>")]
-pub async fn post_item(list_id: i32, new_item: Json<ItemDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+#[post("/lists/<_>/add", format = "json", data = "<new_item>")]
+pub async fn post_item(new_item: Json<ItemDTO>, token: UserToken, list: ListKey,
+                       events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
     let response =
-        list_service::add_item_to_list(list_id, token.id, new_item.into_inner(), db).await;
+        list_service::add_item_to_list(list.list_id, token.id, new_item.into_inner(), events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to add an item to an existing list from a single line of text, such as
+/// `Pay rent tomorrow 9am !high #bills @alice every month`.
+#[post("/lists/<_>/quick-add", format = "json", data = "<quick_add>")]
+pub async fn quick_add_item(quick_add: Json<QuickAddDTO>, token: UserToken, list: ListKey,
+                            events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::quick_add_item(list.list_id, token.id, quick_add.into_inner(), events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to add many items to an existing list at once.
+#[post("/lists/<_>/batch/add", format = "json", data = "<new_items>")]
+pub async fn post_items(new_items: Json<Vec<ItemDTO>>, token: UserToken, list: ListKey,
+                        events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::add_items_to_list(list.list_id, token.id, new_items.into_inner(), events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to mark every item in a list as finished or unfinished.
+#[put("/lists/<_>/batch/finished", format = "json", data = "<finished>")]
+pub async fn put_all_finished(finished: Json<FinishedDTO>, token: UserToken, list: ListKey,
+                              events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::set_all_items_finished(list.list_id, token.id, finished.into_inner(), events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to delete every finished item in a list.
+#[delete("/lists/<_>/batch/finished")]
+pub async fn delete_finished(token: UserToken, list: ListKey, events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::clear_finished_items(list.list_id, token.id, events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get a page of the lists that the logged-in user can access, other than those they archived.
+/// Passing `?archived=true` gets only the archived lists instead, and passing `?render=html` also includes
+/// the sanitized HTML rendering of each list's Markdown description. The lists can be paginated, filtered
+/// and sorted by `created`, `updated` or `name` with the parameters in `PageQuery`.
+#[get("/lists?<archived>&<render>&<page..>")]
+pub async fn get_lists(archived: Option<bool>, render: Option<String>, page: PageQuery, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::get_lists_for_user(token.id, archived.unwrap_or(false), page, markdown::is_html(&render), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -36,10 +120,36 @@ pub async fn post_item(list_id: i32, new_item: Json<ItemDTO>, token: UserToken,
     )
 }
 
-/// Attempts to get the lists that the logged-in user can access.
-#[get("/lists")]
-pub async fn get_lists(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::get_lists_for_user(token.id, db).await;
+/// Attempts to get a full single list with a page of its items for the logged-in user. Passing `?render=html`
+/// also includes the sanitized HTML rendering of the list description and item notes, passing
+/// `?assignee=<user_id>` only includes the items assigned to that user, and passing `?finished=<bool>` only
+/// includes the items with that finished state. The items can be paginated, filtered and sorted by `created`,
+/// `updated` or `description` with the parameters in `PageQuery`. Responds with an ETag, and with no content
+/// if it matches the `If-None-Match` header.
+#[get("/lists/<_>?<assignee>&<finished>&<render>&<page..>")]
+pub async fn get_list(assignee: Option<i32>, finished: Option<bool>, render: Option<String>, page: PageQuery,
+                      preconditions: Preconditions, token: UserToken, list: ListKey, db: PostgresDbConn) -> TaggedResponse {
+    let filter = ItemFilter {
+        assignee_id: assignee,
+        finished,
+    };
+
+    list_service::get_list(list.list_id, token.id, filter, page, markdown::is_html(&render), preconditions, db).await
+}
+
+/// Attempts to get a single item from a list. Passing `?render=html` also includes the sanitized HTML
+/// rendering of the item notes. Responds with an ETag, and with no content if it matches the
+/// `If-None-Match` header.
+#[get("/lists/<_>/<_>?<render>")]
+pub async fn get_item(render: Option<String>, preconditions: Preconditions,
+                      token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> TaggedResponse {
+    list_service::get_item(keys.list_id, keys.item_id, token.id, markdown::is_html(&render), preconditions, db).await
+}
+
+/// Attempts to get every item assigned to the logged-in user across all of their lists.
+#[get("/items/assigned?<render>")]
+pub async fn get_assigned_items(render: Option<String>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::get_assigned_items(token.id, markdown::is_html(&render), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -47,10 +157,43 @@ pub async fn get_lists(token: UserToken, db: PostgresDbConn) -> status::Custom<J
     )
 }
 
-/// Attempts to get a full single list with items for the logged-in user.
-#[get("/lists/<list_id>")]
-pub async fn get_list(list_id: i32, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::get_list(list_id, token.id, db).await;
+/// Attempts to update the specified list with the new values. If an `If-Match` header is given, the list
+/// is only updated if it hasn't changed since.
+#[put("/lists/<_>", format = "json", data = "<new_list>")]
+pub async fn put_list(new_list: Json<ListDTO>, preconditions: Preconditions, token: UserToken, list: ListKey,
+                      events: &State<EventBus>, db: PostgresDbConn) -> TaggedResponse {
+    list_service::put_list(list.list_id, token.id, new_list.into_inner(), preconditions, events.inner().clone(), db).await
+}
+
+/// Attempts to update the specified item with the new values. If an `If-Match` header is given, the item
+/// is only updated if it hasn't changed since.
+#[put("/lists/<_>/<_>", format = "json", data = "<new_item>")]
+pub async fn put_item(new_item: Json<ItemDTO>, preconditions: Preconditions, token: UserToken, keys: ItemKeys,
+                      events: &State<EventBus>, db: PostgresDbConn) -> TaggedResponse {
+    list_service::put_item_for_list(keys.list_id, token.id, keys.item_id, new_item.into_inner(), preconditions, events.inner().clone(), db).await
+}
+
+/// Attempts to update only the given fields of the specified list. If an `If-Match` header is given, the
+/// list is only updated if it hasn't changed since.
+#[patch("/lists/<_>", format = "json", data = "<patch>")]
+pub async fn patch_list(patch: Json<ListPatchDTO>, preconditions: Preconditions, token: UserToken, list: ListKey,
+                        events: &State<EventBus>, db: PostgresDbConn) -> TaggedResponse {
+    list_service::patch_list(list.list_id, token.id, patch.into_inner(), preconditions, events.inner().clone(), db).await
+}
+
+/// Attempts to update only the given fields of the specified item. If an `If-Match` header is given, the
+/// item is only updated if it hasn't changed since.
+#[patch("/lists/<_>/<_>", format = "json", data = "<patch>")]
+pub async fn patch_item(patch: Json<ItemPatchDTO>, preconditions: Preconditions, token: UserToken, keys: ItemKeys,
+                        events: &State<EventBus>, db: PostgresDbConn) -> TaggedResponse {
+    list_service::patch_item_for_list(keys.list_id, token.id, keys.item_id, patch.into_inner(), preconditions, events.inner().clone(), db).await
+}
+
+/// Attempts to move an item into another list, keeping its id.
+#[post("/lists/<_>/<_>/move", format = "json", data = "<transfer>")]
+pub async fn move_item(transfer: Json<ItemTransferDTO>, token: UserToken, keys: ItemKeys,
+                       events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::move_item(keys.list_id, keys.item_id, token.id, transfer.into_inner(), events.inner().clone(), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -58,10 +201,12 @@ pub async fn get_list(list_id: i32, token: UserToken, db: PostgresDbConn) -> sta
     )
 }
 
-/// Attempts to update the specified list with the new values.
-#[put("/lists/<list_id>", format = "json", data = "<new_list>")]
-pub async fn put_list(list_id: i32, new_list: Json<ListDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::put_list(list_id, token.id, new_list.into_inner(), db).await;
+/// Attempts to copy an item into another list.
+#[post("/lists/<_>/<_>/copy", format = "json", data = "<transfer>")]
+pub async fn copy_item(transfer: Json<ItemTransferDTO>, token: UserToken, keys: ItemKeys,
+                       storage: &State<Arc<dyn Storage>>, events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::copy_item(keys.list_id, keys.item_id, token.id, transfer.into_inner(), storage.inner().clone(),
+                                           events.inner().clone(), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -69,10 +214,78 @@ pub async fn put_list(list_id: i32, new_list: Json<ListDTO>, token: UserToken, d
     )
 }
 
-/// Attempts to update the specified item with the new values.
-#[put("/lists/<list_id>/<item_id>", format = "json", data = "<new_item>")]
-pub async fn put_item(list_id: i32, item_id: i32, new_item: Json<ItemDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::put_item_for_list(list_id, token.id, item_id, new_item.into_inner(), db).await;
+/// Attempts to archive a list for the logged-in user, hiding it from their lists.
+#[post("/lists/<_>/archive")]
+pub async fn archive_list(token: UserToken, list: ListKey, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::set_list_archived(list.list_id, token.id, true, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to unarchive a list for the logged-in user, showing it in their lists again.
+#[post("/lists/<_>/unarchive")]
+pub async fn unarchive_list(token: UserToken, list: ListKey, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::set_list_archived(list.list_id, token.id, false, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to export a list as a file, in the format named by `format` (`json`, `csv`, `markdown` or
+/// `todotxt`) or else the one preferred by the `Accept` header, defaulting to the portable JSON format.
+#[get("/lists/<_>/export?<format>", rank = 2)]
+pub async fn export_list(format: Option<String>, accept: Option<&Accept>, token: UserToken, list: ListKey,
+                         db: PostgresDbConn) -> Result<ExportFile, status::Custom<Json<Response>>> {
+    let format = match format {
+        Some(name) => ListFormat::from_name(&name).ok_or(Status::BadRequest),
+        None => match accept {
+            Some(accept) => ListFormat::from_accept(accept).ok_or(Status::NotAcceptable),
+            None => Ok(ListFormat::Json),
+        },
+    };
+
+    let result = match format {
+        Ok(format) => export_service::export_list(list.list_id, token.id, format, db).await
+            .map(|(file_name, contents)| (format, file_name, contents)),
+        Err(status) => Err(ResponseWithStatus::with(status.code, constants::MESSAGE_INVALID_EXPORT_FORMAT)),
+    };
+
+    match result {
+        Ok((format, file_name, contents)) => Ok(ExportFile {
+            contents,
+            content_type: ContentType::parse_flexible(format.content_type()).unwrap(),
+            content_disposition: Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", file_name)),
+        }),
+        Err(response) => Err(status::Custom(
+            Status::from_code(response.status_code).unwrap(),
+            Json(response.response),
+        )),
+    }
+}
+
+/// Attempts to import a file uploaded as a multipart form as a new list. The format is named by `format`
+/// (`json`, `csv`, `markdown` or `todotxt`) or else given by the content type of the file. If `dry_run` is
+/// set, responds with what would be imported without saving anything.
+#[post("/lists/import?<format>&<name>&<dry_run>", data = "<upload>")]
+pub async fn import_list(format: Option<String>, name: Option<String>, dry_run: Option<bool>, upload: Form<ImportUpload<'_>>,
+                         token: UserToken, events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let mut upload = upload.into_inner();
+    let format = match format {
+        Some(name) => ListFormat::from_name(&name),
+        None => upload.file.content_type()
+            .and_then(|content_type| ListFormat::from_media_type(content_type.top().as_str(), content_type.sub().as_str())),
+    };
+
+    let response = match format {
+        Some(format) => import_service::import_list(token.id, format, &mut upload.file, name, dry_run.unwrap_or(false),
+                                                         events.inner().clone(), db).await,
+        None => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_IMPORT_FORMAT),
+    };
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -80,10 +293,12 @@ pub async fn put_item(list_id: i32, item_id: i32, new_item: Json<ItemDTO>, token
     )
 }
 
-/// Attempts to delete an existing list.
-#[delete("/lists/<list_id>")]
-pub async fn delete_list(list_id: i32, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::delete_list(list_id, token.id, db).await;
+/// Attempts to delete an existing list. If an `If-Match` header is given, the list is only deleted if it
+/// hasn't changed since.
+#[delete("/lists/<_>")]
+pub async fn delete_list(preconditions: Preconditions, token: UserToken, list: ListKey, events: &State<EventBus>,
+                         db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::delete_list(list.list_id, token.id, preconditions, events.inner().clone(), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
@@ -91,10 +306,12 @@ pub async fn delete_list(list_id: i32, token: UserToken, db: PostgresDbConn) ->
     )
 }
 
-/// Attempts to delete an item from a list.
-#[delete("/lists/<list_id>/<item_id>")]
-pub async fn delete_item(list_id: i32, item_id: i32, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
-    let response = list_service::delete_item(list_id, token.id, item_id, db).await;
+/// Attempts to delete an item from a list. If an `If-Match` header is given, the item is only deleted if
+/// it hasn't changed since.
+#[delete("/lists/<_>/<_>")]
+pub async fn delete_item(preconditions: Preconditions, token: UserToken, keys: ItemKeys,
+                         events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = list_service::delete_item(keys.list_id, token.id, keys.item_id, preconditions, events.inner().clone(), db).await;
 
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
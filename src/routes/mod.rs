@@ -1,2 +1,13 @@
 pub mod users;
-pub mod lists;
\ No newline at end of file
+pub mod lists;
+pub mod comments;
+pub mod attachments;
+pub mod trash;
+pub mod search;
+pub mod tags;
+pub mod smart_lists;
+pub mod calendar;
+pub mod app_passwords;
+pub mod events;
+pub mod webhooks;
+pub mod sync;
\ No newline at end of file
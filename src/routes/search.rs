@@ -0,0 +1,20 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::models::response::Response;
+use crate::services::search_service;
+
+/// Attempts to search the names and descriptions of every list and item that the logged-in user can reach.
+/// Results are paginated with `limit` and the `next_cursor` of the previous page as `cursor`.
+#[get("/search?<q>&<limit>&<cursor>")]
+pub async fn search(q: String, limit: Option<i64>, cursor: Option<String>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = search_service::search(token.id, q, limit, cursor, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
@@ -0,0 +1,79 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::markdown;
+use crate::models::page::PageQuery;
+use crate::models::response::Response;
+use crate::models::smart_list::SmartListDTO;
+use crate::services::smart_list_service;
+
+/// Attempts to get every smart list saved by the logged-in user.
+#[get("/smart-lists")]
+pub async fn get_smart_lists(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = smart_list_service::get_smart_lists(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to save a new smart list for the logged-in user.
+#[post("/smart-lists", format = "json", data = "<smart_list>")]
+pub async fn create_smart_list(smart_list: Json<SmartListDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = smart_list_service::create_smart_list(token.id, smart_list.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get the built-in "Today" smart list of the logged-in user. The items can be paginated,
+/// filtered and sorted like the items of a list.
+#[get("/smart-lists/today?<render>&<page..>")]
+pub async fn get_today(render: Option<String>, page: PageQuery, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = smart_list_service::get_today(token.id, page, markdown::is_html(&render), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get the items matching one of the logged-in user's smart lists across all of their lists.
+/// The items can be paginated, filtered and sorted like the items of a list.
+#[get("/smart-lists/<smart_list_id>?<render>&<page..>")]
+pub async fn get_smart_list(smart_list_id: i32, render: Option<String>, page: PageQuery, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = smart_list_service::get_smart_list(smart_list_id, token.id, page, markdown::is_html(&render), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to change the name and filter of one of the logged-in user's smart lists.
+#[put("/smart-lists/<smart_list_id>", format = "json", data = "<smart_list>")]
+pub async fn put_smart_list(smart_list_id: i32, smart_list: Json<SmartListDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = smart_list_service::put_smart_list(smart_list_id, token.id, smart_list.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to delete one of the logged-in user's smart lists.
+#[delete("/smart-lists/<smart_list_id>")]
+pub async fn delete_smart_list(smart_list_id: i32, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = smart_list_service::delete_smart_list(smart_list_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
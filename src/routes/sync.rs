@@ -0,0 +1,36 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::events::EventBus;
+use crate::models::response::Response;
+use crate::models::sync::SyncBatchDTO;
+use crate::services::sync_service;
+
+/// Attempts to get everything that changed in the lists of the logged-in user since the sync that gave them the
+/// `since` token, or everything in their lists if no token is given.
+#[get("/sync?<since>")]
+pub async fn get_sync(since: Option<String>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = sync_service::get_changes(token.id, since, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to apply a batch of changes that the logged-in user made offline, then gets everything that changed
+/// since their last sync.
+#[post("/sync", format = "json", data = "<batch>")]
+pub async fn post_sync(batch: Json<SyncBatchDTO>, token: UserToken, events: &State<EventBus>,
+                       db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = sync_service::push_changes(token.id, batch.into_inner(), events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
@@ -0,0 +1,99 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::keys::ItemKeys;
+use crate::markdown;
+use crate::models::response::Response;
+use crate::models::tag::TagDTO;
+use crate::services::tag_service;
+
+/// Attempts to get every tag of the logged-in user.
+#[get("/tags")]
+pub async fn get_tags(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::get_tags(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to create a new tag for the logged-in user.
+#[post("/tags", format = "json", data = "<tag>")]
+pub async fn create_tag(tag: Json<TagDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::create_tag(token.id, tag.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to rename and recolor one of the logged-in user's tags.
+#[put("/tags/<name>", format = "json", data = "<tag>")]
+pub async fn put_tag(name: String, tag: Json<TagDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::put_tag(token.id, name, tag.into_inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to delete one of the logged-in user's tags, taking it off every item.
+#[delete("/tags/<name>")]
+pub async fn delete_tag(name: String, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::delete_tag(token.id, name, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get every item with one of the logged-in user's tags across all of their lists.
+#[get("/tags/<name>/items?<render>")]
+pub async fn get_tagged_items(name: String, render: Option<String>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::get_tagged_items(token.id, name, markdown::is_html(&render), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get the logged-in user's tags on an item.
+#[get("/lists/<_>/<_>/tags")]
+pub async fn get_item_tags(token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::get_item_tags(keys.list_id, keys.item_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to put one of the logged-in user's tags on an item.
+#[post("/lists/<_>/<_>/tags/<name>")]
+pub async fn tag_item(name: String, token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::tag_item(keys.list_id, keys.item_id, token.id, name, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to take one of the logged-in user's tags off an item.
+#[delete("/lists/<_>/<_>/tags/<name>")]
+pub async fn untag_item(name: String, token: UserToken, keys: ItemKeys, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = tag_service::untag_item(keys.list_id, keys.item_id, token.id, name, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
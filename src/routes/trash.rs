@@ -0,0 +1,55 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use uuid::Uuid;
+
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::events::EventBus;
+use crate::models::response::Response;
+use crate::services::trash_service;
+
+/// Attempts to get the lists and items in the trash of the logged-in user.
+#[get("/trash")]
+pub async fn get_trash(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = trash_service::get_trash(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to restore a list that the logged-in user deleted, along with its items.
+#[post("/trash/lists/<list_id>/restore")]
+pub async fn restore_list(list_id: Uuid, token: UserToken, events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = trash_service::restore_list(list_id, token.id, events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to restore an item that the logged-in user deleted.
+#[post("/trash/items/<item_id>/restore")]
+pub async fn restore_item(item_id: Uuid, token: UserToken, events: &State<EventBus>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = trash_service::restore_item(item_id, token.id, events.inner().clone(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to permanently delete everything in the trash of the logged-in user.
+#[delete("/trash")]
+pub async fn empty_trash(token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = trash_service::empty_trash(token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
@@ -11,7 +11,7 @@ use crate::auth::UserToken;
 use crate::constants;
 use crate::database::PostgresDbConn;
 use crate::models::response::{Response, ResponseWithStatus};
-use crate::models::user::{LoginDTO, UpdatePassword, UpdatePreferredName, User, UserDTO};
+use crate::models::user::{LoginDTO, UpdatePassword, UpdatePreferredName, User, UserDTO, UserPatchDTO};
 use crate::schema::users;
 use crate::services::account_service;
 
@@ -67,6 +67,17 @@ pub async fn put_preferred_name(preferred_name: Json<UpdatePreferredName>, token
 pub async fn put_password(password: Json<UpdatePassword>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
     let response = account_service::put_password(token.username, password.into_inner(), db).await;
 
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// A route to update only the given fields of a user's profile.
+#[patch("/users", format = "json", data = "<patch>")]
+pub async fn patch_user(patch: Json<UserPatchDTO>, token: UserToken, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = account_service::patch_user(token.username, patch.into_inner(), db).await;
+
     status::Custom(
         Status::from_code(response.status_code).unwrap(),
         Json(response.response),
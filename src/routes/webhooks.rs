@@ -0,0 +1,69 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::addresses::AddressPolicy;
+use crate::auth::UserToken;
+use crate::database::PostgresDbConn;
+use crate::keys::ListKey;
+use crate::models::response::Response;
+use crate::models::webhook::{WebhookDTO, WebhookPatchDTO};
+use crate::services::webhook_service;
+
+/// Attempts to get the webhooks of a list that the logged-in user owns.
+#[get("/lists/<_>/webhooks")]
+pub async fn get_webhooks(token: UserToken, list: ListKey, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = webhook_service::get_webhooks(list.list_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to register a webhook that is sent the changes to a list that the logged-in user owns.
+#[post("/lists/<_>/webhooks", format = "json", data = "<webhook>")]
+pub async fn create_webhook(webhook: Json<WebhookDTO>, token: UserToken, list: ListKey, addresses: &State<AddressPolicy>,
+                            db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = webhook_service::create_webhook(list.list_id, token.id, webhook.into_inner(), *addresses.inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to change the URL or events of a webhook, or to disable or enable it again.
+#[patch("/lists/<_>/webhooks/<webhook_id>", format = "json", data = "<patch>")]
+pub async fn patch_webhook(webhook_id: i32, patch: Json<WebhookPatchDTO>, token: UserToken, list: ListKey,
+                           addresses: &State<AddressPolicy>, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = webhook_service::patch_webhook(list.list_id, webhook_id, token.id, patch.into_inner(), *addresses.inner(), db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to delete a webhook.
+#[delete("/lists/<_>/webhooks/<webhook_id>")]
+pub async fn delete_webhook(webhook_id: i32, token: UserToken, list: ListKey, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = webhook_service::delete_webhook(list.list_id, webhook_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
+
+/// Attempts to get the latest deliveries to a webhook, with the outcome of each.
+#[get("/lists/<_>/webhooks/<webhook_id>/deliveries", rank = 2)]
+pub async fn get_deliveries(webhook_id: i32, token: UserToken, list: ListKey, db: PostgresDbConn) -> status::Custom<Json<Response>> {
+    let response = webhook_service::get_deliveries(list.list_id, webhook_id, token.id, db).await;
+
+    status::Custom(
+        Status::from_code(response.status_code).unwrap(),
+        Json(response.response),
+    )
+}
@@ -1,9 +1,77 @@
+table! {
+    app_passwords (app_password_id) {
+        app_password_id -> Int4,
+        user_id -> Int4,
+        name -> Text,
+        password_hash -> Text,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    attachments (attachment_id) {
+        attachment_id -> Int4,
+        item_id -> Int4,
+        uploader_id -> Int4,
+        file_name -> Text,
+        content_type -> Text,
+        size_bytes -> Int8,
+        storage_key -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    calendar_feeds (user_id) {
+        user_id -> Int4,
+        token -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    comments (comment_id) {
+        comment_id -> Int4,
+        item_id -> Int4,
+        author_id -> Int4,
+        body -> Text,
+        created_at -> Timestamptz,
+        edited_at -> Nullable<Timestamptz>,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    item_tags (item_id, tag_id) {
+        item_id -> Int4,
+        tag_id -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     items (item_id) {
         item_id -> Int4,
         list_id -> Int4,
         description -> Text,
         finished -> Bool,
+        notes -> Nullable<Text>,
+        assignee_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        version -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Int4>,
+        due_at -> Nullable<Timestamptz>,
+        priority -> Nullable<Text>,
+        recurrence -> Nullable<Text>,
+        ical_uid -> Nullable<Text>,
+        dav_name -> Nullable<Text>,
+        uuid -> Uuid,
+        list_uuid -> Uuid,
     }
 }
 
@@ -12,7 +80,57 @@ table! {
         list_id -> Int4,
         name -> Text,
         description -> Nullable<Text>,
-        date_created -> Date,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        version -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Int4>,
+        uuid -> Uuid,
+    }
+}
+
+table! {
+    smart_lists (smart_list_id) {
+        smart_list_id -> Int4,
+        user_id -> Int4,
+        name -> Text,
+        filter -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    sync_changes (object_type, object_id, list_id) {
+        object_type -> Text,
+        object_id -> Int4,
+        list_id -> Int4,
+        is_deleted -> Bool,
+        changed_xid -> Int8,
+        changed_at -> Timestamptz,
+        object_uuid -> Nullable<Uuid>,
+        list_uuid -> Uuid,
+    }
+}
+
+table! {
+    sync_mutations (user_id, client_id) {
+        user_id -> Int4,
+        client_id -> Text,
+        created_at -> Timestamptz,
+        list_uuid -> Uuid,
+        item_uuid -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    tags (tag_id) {
+        tag_id -> Int4,
+        user_id -> Int4,
+        name -> Text,
+        color -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -22,6 +140,9 @@ table! {
         user_id -> Int4,
         list_id -> Int4,
         is_owner -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        archived_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -32,16 +153,76 @@ table! {
         email -> Varchar,
         preferred_name -> Varchar,
         password_hash -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        timezone -> Text,
+    }
+}
+
+table! {
+    webhook_deliveries (delivery_id) {
+        delivery_id -> Int4,
+        webhook_id -> Int4,
+        event_type -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_attempt_at -> Nullable<Timestamptz>,
+        response_status -> Nullable<Int4>,
+        error -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    webhooks (webhook_id) {
+        webhook_id -> Int4,
+        list_id -> Int4,
+        url -> Text,
+        secret -> Text,
+        events -> Array<Text>,
+        is_enabled -> Bool,
+        consecutive_failures -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        disabled_at -> Nullable<Timestamptz>,
     }
 }
 
+joinable!(app_passwords -> users (user_id));
+joinable!(attachments -> items (item_id));
+joinable!(attachments -> users (uploader_id));
+joinable!(calendar_feeds -> users (user_id));
+joinable!(comments -> items (item_id));
+joinable!(comments -> users (author_id));
+joinable!(item_tags -> items (item_id));
+joinable!(item_tags -> tags (tag_id));
 joinable!(items -> lists (list_id));
+joinable!(items -> users (assignee_id));
+joinable!(lists -> users (deleted_by));
+joinable!(smart_lists -> users (user_id));
+joinable!(sync_mutations -> users (user_id));
+joinable!(tags -> users (user_id));
 joinable!(user_lists -> lists (list_id));
 joinable!(user_lists -> users (user_id));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> lists (list_id));
 
 allow_tables_to_appear_in_same_query!(
+    app_passwords,
+    attachments,
+    calendar_feeds,
+    comments,
+    item_tags,
     items,
     lists,
+    smart_lists,
+    sync_changes,
+    sync_mutations,
+    tags,
     user_lists,
     users,
+    webhook_deliveries,
+    webhooks,
 );
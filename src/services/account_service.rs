@@ -1,3 +1,4 @@
+use chrono_tz::Tz;
 use rocket::http::Status;
 use serde_json::json;
 
@@ -5,7 +6,7 @@ use crate::auth;
 use crate::constants;
 use crate::database::PostgresDbConn;
 use crate::models::response::{Response, ResponseWithStatus};
-use crate::models::user::{LoginDTO, UpdatePassword, UpdatePreferredName, User, UserDTO, UserInformation};
+use crate::models::user::{LoginDTO, UpdatePassword, UpdatePreferredName, User, UserDTO, UserInformation, UserPatchDTO};
 
 /// Attempts to signup a new user with the specified user information.
 /// If successful, informs the user that the account has been created successfully.
@@ -48,11 +49,7 @@ pub async fn login(login: LoginDTO, db: PostgresDbConn) -> ResponseWithStatus {
 pub async fn get_user(username: String, db: PostgresDbConn) -> ResponseWithStatus {
     db.run(move |conn| {
         if let Some(user) = User::find_user_by_username(username.as_str(), conn) {
-            let result_user = UserInformation {
-                username: user.username,
-                email: user.email,
-                preferred_name: user.preferred_name,
-            };
+            let result_user = UserInformation::from(user);
 
             ResponseWithStatus {
                 status_code: Status::Ok.code,
@@ -90,3 +87,27 @@ pub async fn put_password(username: String, password: UpdatePassword, db: Postgr
         }
     }).await
 }
+
+/// Attempts to update only the profile fields present in `patch` for the user with the given `username`.
+/// Responds with the updated profile.
+pub async fn patch_user(username: String, patch: UserPatchDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    if let Some(timezone) = &patch.timezone {
+        if timezone.parse::<Tz>().is_err() {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_TIMEZONE);
+        }
+    }
+
+    db.run(move |conn| {
+        if let Ok(user) = User::patch_user(username, patch, conn) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                    data: serde_json::to_value(UserInformation::from(user)).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED)
+        }
+    }).await
+}
@@ -0,0 +1,55 @@
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::models::app_password::{AppPassword, AppPasswordDTO};
+use crate::models::response::{Response, ResponseWithStatus};
+
+/// Responds with every app password of the user with `user_id`, without the passwords themselves.
+pub async fn get_app_passwords(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        match AppPassword::find_app_passwords_for_user(user_id, conn) {
+            Ok(app_passwords) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(app_passwords).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_APP_PASSWORDS_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to make a new app password for the user with `user_id`. Responds with the password, which is the
+/// only time it's shown.
+pub async fn create_app_password(user_id: i32, app_password: AppPasswordDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    let name = app_password.name.trim().to_string();
+    if name.is_empty() {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_APP_PASSWORD_NAME);
+    }
+
+    db.run(move |conn| {
+        match AppPassword::create_app_password(user_id, &name, conn) {
+            Ok(created) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_CREATE_APP_PASSWORD_SUCCESS),
+                    data: serde_json::to_value(created).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_APP_PASSWORD_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to revoke the app password with `app_password_id` of the user with `user_id`.
+pub async fn delete_app_password(user_id: i32, app_password_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if AppPassword::delete_app_password(app_password_id, user_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_APP_PASSWORD_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_APP_PASSWORD_FAILED)
+        }
+    }).await
+}
@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use diesel::{Connection, PgConnection, QueryResult};
+use rocket::fs::TempFile;
+use rocket::http::Status;
+use uuid::Uuid;
+
+use crate::constants;
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::models::attachment::{Attachment, NewAttachment};
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::services::list_service::check_item_access;
+use crate::storage::Storage;
+
+static USER_QUOTA_BYTES: i64 = 100 * 1024 * 1024; // 100 MiB of attachments per user
+static CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60); // Check for orphaned files hourly
+static ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60); // Skip files that may still be uploading
+static MAX_EXTENSION_LENGTH: usize = 10; // Longest file extension kept from the name of an upload
+
+/// Responds with the metadata for every attachment on the item with `item_id`, as long as that item is in
+/// the list with `list_id` and the user with `user_id` has access to that list.
+pub async fn get_attachments(list_id: i32, item_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data: serde_json::to_value(Attachment::find_attachments_for_item(item_id, conn)).unwrap(),
+            },
+        }
+    }).await
+}
+
+/// Attempts to store the uploaded `file` as an attachment on the item with `item_id`, uploaded by the user
+/// with `user_id`. The content type is sniffed from the file contents rather than trusted from the client,
+/// falling back to the extension of the uploaded file name, and the upload is refused if it would put the user
+/// over their storage quota.
+pub async fn upload_attachment(list_id: i32, item_id: i32, user_id: i32, file: &mut TempFile<'_>,
+                               storage: Arc<dyn Storage>, db: PostgresDbConn) -> ResponseWithStatus {
+    let staged_path = std::env::temp_dir().join(Uuid::new_v4().to_simple().to_string());
+    if file.persist_to(&staged_path).await.is_err() {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPLOAD_ATTACHMENT_FAILED);
+    }
+
+    // Rocket strips the extension from the sanitized name, so it's taken from the name that was sent
+    let file_stem = file.name().unwrap_or("attachment").to_string();
+    let sent_extension = file.raw_name().and_then(|name| file_extension(name.dangerous_unsafe_unsanitized_raw().as_str()));
+
+    db.run(move |conn| {
+        let contents = fs::read(&staged_path);
+        let _ = fs::remove_file(&staged_path);
+
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        let contents = match contents {
+            Ok(contents) => contents,
+            Err(_) => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPLOAD_ATTACHMENT_FAILED),
+        };
+
+        let size_bytes = contents.len() as i64;
+        let (content_type, extension) = sniff_content_type(&contents);
+        let file_name = match extension.map(String::from).or(sent_extension) {
+            Some(extension) => format!("{}.{}", file_stem, extension),
+            None => file_stem,
+        };
+
+        let storage_key = Uuid::new_v4().to_simple().to_string();
+        let attachment = NewAttachment {
+            item_id,
+            uploader_id: user_id,
+            file_name,
+            content_type,
+            size_bytes,
+            storage_key: storage_key.clone(),
+        };
+
+        // The quota is checked and used up with the uploader locked, so that uploads made at the same time are
+        // counted one after another
+        let result = conn.transaction(|| {
+            Attachment::lock_quota(user_id, conn)?;
+            if Attachment::total_bytes_for_user(user_id, conn)? + size_bytes > USER_QUOTA_BYTES {
+                return Ok(None);
+            }
+
+            storage.put(&storage_key, &contents)
+                .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+
+            Attachment::create_attachment(attachment, conn)
+                .map(Some)
+                .ok_or(diesel::result::Error::RollbackTransaction)
+        });
+
+        match result {
+            Ok(Some(result_attachment)) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_UPLOAD_ATTACHMENT_SUCCESS),
+                    data: serde_json::to_value(result_attachment).unwrap(),
+                },
+            },
+            Ok(None) => ResponseWithStatus::with(Status::PayloadTooLarge.code, constants::MESSAGE_ATTACHMENT_QUOTA_EXCEEDED),
+            Err(_) => {
+                let _ = storage.delete(&storage_key);
+                ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPLOAD_ATTACHMENT_FAILED)
+            },
+        }
+    }).await
+}
+
+/// Attempts to read back the attachment with `attachment_id` along with its contents, as long as the user
+/// with `user_id` can access it through the item with `item_id` in the list with `list_id`.
+pub async fn download_attachment(list_id: i32, item_id: i32, attachment_id: i32, user_id: i32,
+                                 storage: Arc<dyn Storage>, db: PostgresDbConn) -> Result<(Attachment, Vec<u8>), ResponseWithStatus> {
+    db.run(move |conn| {
+        let attachment = find_attachment_on_item(list_id, item_id, attachment_id, user_id, conn)?;
+
+        match storage.get(&attachment.storage_key) {
+            Ok(contents) => Ok((attachment, contents)),
+            Err(_) => Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_ATTACHMENT_FAILED)),
+        }
+    }).await
+}
+
+/// Attempts to delete the attachment with `attachment_id` along with its file, as long as the user with
+/// `user_id` can access it through the item with `item_id` in the list with `list_id`.
+pub async fn delete_attachment(list_id: i32, item_id: i32, attachment_id: i32, user_id: i32,
+                               storage: Arc<dyn Storage>, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let attachment = match find_attachment_on_item(list_id, item_id, attachment_id, user_id, conn) {
+            Ok(attachment) => attachment,
+            Err(response) => return response,
+        };
+
+        if Attachment::delete_attachment(attachment_id, conn) {
+            // If this fails, the file is left for the orphaned file cleanup to catch
+            let _ = storage.delete(&attachment.storage_key);
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_ATTACHMENT_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_ATTACHMENT_FAILED)
+        }
+    }).await
+}
+
+/// Copies every attachment on the item with `from_item_id`, including its file, onto the item with `to_item_id`.
+/// The copies count against the quota of the user with `uploader_id`, who is checked to have room for them.
+/// Meant to be run inside a transaction; if anything fails, the files already copied are removed again.
+pub fn copy_attachments(from_item_id: i32, to_item_id: i32, uploader_id: i32, storage: &dyn Storage, conn: &PgConnection) -> QueryResult<()> {
+    Attachment::lock_quota(uploader_id, conn)?;
+    let used = Attachment::total_bytes_for_user(uploader_id, conn)?;
+    if used + Attachment::total_bytes_for_item(from_item_id, conn)? > USER_QUOTA_BYTES {
+        return Err(diesel::result::Error::RollbackTransaction);
+    }
+
+    let mut written_keys = vec![];
+    let result = Attachment::find_attachments_for_item(from_item_id, conn)
+        .into_iter()
+        .try_for_each(|attachment| {
+            let contents = storage.get(&attachment.storage_key)
+                .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+
+            let storage_key = Uuid::new_v4().to_simple().to_string();
+            storage.put(&storage_key, &contents)
+                .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+            written_keys.push(storage_key.clone());
+
+            let copy = NewAttachment {
+                item_id: to_item_id,
+                uploader_id,
+                file_name: attachment.file_name,
+                content_type: attachment.content_type,
+                size_bytes: attachment.size_bytes,
+                storage_key,
+            };
+
+            Attachment::create_attachment(copy, conn)
+                .map(|_| ())
+                .ok_or(diesel::result::Error::RollbackTransaction)
+        });
+
+    if result.is_err() {
+        for key in written_keys {
+            let _ = storage.delete(&key);
+        }
+    }
+
+    result
+}
+
+/// Periodically removes files from the `storage` backend that no longer have a row in the attachments
+/// table, eg because their item or list was deleted and the rows were removed through cascading.
+pub async fn clean_up_orphaned_files(storage: Arc<dyn Storage>, pool: PostgresPool) {
+    let mut interval = rocket::tokio::time::interval(CLEANUP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Some(conn) = pool.get().await {
+            let storage = storage.clone();
+            let _ = conn.run(move |conn| remove_orphaned_files(&*storage, conn)).await;
+        }
+    }
+}
+
+/// Removes every file in the `storage` backend without a matching attachment row, other than recently
+/// written ones. Returns the number of files removed.
+fn remove_orphaned_files(storage: &dyn Storage, conn: &PgConnection) -> io::Result<usize> {
+    let known_keys: HashSet<String> = Attachment::find_all_storage_keys(conn)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+        .into_iter()
+        .collect();
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for object in storage.list()? {
+        let age = now.duration_since(object.last_modified).unwrap_or_default();
+
+        if age >= ORPHAN_GRACE_PERIOD && !known_keys.contains(&object.key) {
+            storage.delete(&object.key)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Determines the content type of a file from its `contents`, along with the usual file extension for that
+/// type if it's recognized. Unrecognized files are treated as plain text if they're valid UTF-8.
+fn sniff_content_type(contents: &[u8]) -> (String, Option<&'static str>) {
+    match infer::get(contents) {
+        Some(kind) => (String::from(kind.mime_type()), Some(kind.extension())),
+        None if std::str::from_utf8(contents).is_ok() => (String::from("text/plain"), None),
+        None => (String::from("application/octet-stream"), None),
+    }
+}
+
+/// Finds the extension at the end of the file name that a client sent, as long as it looks like one: short and
+/// only letters and digits.
+fn file_extension(sent_name: &str) -> Option<String> {
+    let base_name = sent_name.rsplit(|c| c == '/' || c == '\\').next()?;
+    let (stem, extension) = base_name.rsplit_once('.')?;
+
+    let is_extension = !stem.is_empty() && !extension.is_empty() && extension.len() <= MAX_EXTENSION_LENGTH
+        && extension.chars().all(|c| c.is_ascii_alphanumeric());
+
+    if is_extension {
+        Some(extension.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Finds the attachment with `attachment_id` after checking that the user with `user_id` can access it
+/// through the item with `item_id` in the list with `list_id`. Returns the failed response to send if not.
+fn find_attachment_on_item(list_id: i32, item_id: i32, attachment_id: i32, user_id: i32, conn: &PgConnection) -> Result<Attachment, ResponseWithStatus> {
+    if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+        return Err(response);
+    }
+
+    match Attachment::find_attachment_by_id(attachment_id, conn) {
+        Some(attachment) if attachment.item_id == item_id => Ok(attachment),
+        _ => Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ATTACHMENT_NOT_OWNED_BY_ITEM)),
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use hyper::StatusCode;
+use uuid::Uuid;
+
+use crate::auth;
+use crate::caldav::xml::{self, Multistatus, PropName, PropRequest, Report};
+use crate::caldav::{DavPath, DavResponse, Depth};
+use crate::etag::{self, Preconditions};
+use crate::events::EventBus;
+use crate::ical::{self, Todo};
+use crate::models::app_password::AppPassword;
+use crate::models::event::ListEvent;
+use crate::models::item::{DavIdentity, Item, ItemDTO};
+use crate::models::list::List;
+use crate::models::tag::Tag;
+use crate::models::user::User;
+use crate::models::user_lists::UserList;
+use crate::services::{list_service, tag_service};
+
+static CONTENT_TYPE: &str = "text/calendar; charset=utf-8"; // Content type of VTODO object resources
+
+/// The properties sent for a `DAV:allprop` PROPFIND. The calendar data of objects is left out, as it's only
+/// meant to be sent when asked for by name.
+static ALL_PROPS: &[(&str, &str)] = &[
+    ("DAV:", "resourcetype"),
+    ("DAV:", "displayname"),
+    ("DAV:", "current-user-principal"),
+    ("DAV:", "principal-URL"),
+    ("DAV:", "getetag"),
+    ("DAV:", "getcontenttype"),
+    ("DAV:", "getlastmodified"),
+    ("DAV:", "supported-report-set"),
+    ("DAV:", "current-user-privilege-set"),
+    ("urn:ietf:params:xml:ns:caldav", "calendar-home-set"),
+    ("urn:ietf:params:xml:ns:caldav", "calendar-user-address-set"),
+    ("urn:ietf:params:xml:ns:caldav", "calendar-description"),
+    ("urn:ietf:params:xml:ns:caldav", "supported-calendar-component-set"),
+    ("http://calendarserver.org/ns/", "getctag"),
+];
+
+/// A resource along with what's needed to give its properties. Calendars have the ETag of their list, which is
+/// also their CTag, and objects have their item along with the user's tags on it.
+enum Resource<'a> {
+    Root,
+    Principal,
+    Home,
+    Calendar(&'a List, String),
+    Object(&'a Item, &'a [String]),
+}
+
+/// Finds the user called `username` if `password` is one of their app passwords or a personal token of theirs.
+pub fn authenticate(username: &str, password: &str, conn: &PgConnection) -> Option<User> {
+    let user = User::find_user_by_username(username, conn)?;
+
+    let user_id = AppPassword::find_user_id_by_password(password, conn).or_else(|| {
+        auth::decode_token(String::from(password)).ok()
+            .map(|token_data| token_data.claims)
+            .filter(|token| User::is_valid_login_token(token, conn))
+            .map(|token| token.id)
+    })?;
+
+    if user_id == user.id {
+        Some(user)
+    } else {
+        None
+    }
+}
+
+/// Responds to a PROPFIND for the resource at `path` with the properties asked for in `body`, along with those
+/// of its members if `depth` is one.
+pub fn propfind(user: &User, path: &DavPath, depth: Depth, body: &str, conn: &PgConnection) -> DavResponse {
+    let props = match xml::parse_propfind(body) {
+        Ok(props) => props,
+        Err(_) => return DavResponse::status(StatusCode::BAD_REQUEST),
+    };
+
+    let mut multistatus = Multistatus::new();
+    let principal = DavPath::Principal(user.username.clone());
+    let home = DavPath::Home(user.username.clone());
+
+    match path {
+        DavPath::Root => {
+            add_resource(&mut multistatus, user, path, &Resource::Root, &props);
+            if depth == Depth::One {
+                add_resource(&mut multistatus, user, &principal, &Resource::Principal, &props);
+                add_resource(&mut multistatus, user, &home, &Resource::Home, &props);
+            }
+        }
+        DavPath::Principal(_) => add_resource(&mut multistatus, user, path, &Resource::Principal, &props),
+        DavPath::Home(_) => {
+            add_resource(&mut multistatus, user, path, &Resource::Home, &props);
+            if depth == Depth::One {
+                let lists = match List::find_all_lists_for_user(user.id, conn) {
+                    Ok(lists) => lists,
+                    Err(_) => return DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+                };
+
+                for list in &lists {
+                    let calendar = DavPath::Calendar(user.username.clone(), list.uuid);
+                    match list_etag(list, conn) {
+                        Some(etag) => add_resource(&mut multistatus, user, &calendar, &Resource::Calendar(list, etag), &props),
+                        None => return DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+                    }
+                }
+            }
+        }
+        DavPath::Calendar(_, list_id) => {
+            let list = match find_calendar(*list_id, user, conn) {
+                Some(list) => list,
+                None => return DavResponse::status(StatusCode::NOT_FOUND),
+            };
+
+            match list_etag(&list, conn) {
+                Some(etag) => add_resource(&mut multistatus, user, path, &Resource::Calendar(&list, etag), &props),
+                None => return DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+
+            if depth == Depth::One {
+                let todos = match find_todos(list.list_id, user, conn) {
+                    Some(todos) => todos,
+                    None => return DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+                };
+
+                for (item, categories) in &todos {
+                    add_resource(&mut multistatus, user, &object_path(user, item), &Resource::Object(item, categories), &props);
+                }
+            }
+        }
+        DavPath::Object(_, list_id, name) => {
+            let (item, categories) = match find_object(*list_id, name, user, conn) {
+                Some(object) => object,
+                None => return DavResponse::status(StatusCode::NOT_FOUND),
+            };
+
+            add_resource(&mut multistatus, user, path, &Resource::Object(&item, &categories), &props);
+        }
+    }
+
+    DavResponse::multistatus(multistatus.finish())
+}
+
+/// Responds to a REPORT on the calendar at `path`, which can be a `calendar-query` for every VTODO in it or a
+/// `calendar-multiget` for some of them. Filters other than on the component type are ignored, so a query can
+/// return more than it asked for, which clients allow.
+pub fn report(user: &User, path: &DavPath, body: &str, conn: &PgConnection) -> DavResponse {
+    let list_id = match path {
+        DavPath::Calendar(_, list_id) => *list_id,
+        _ => return DavResponse::error(StatusCode::FORBIDDEN, &PropName::new(xml::DAV, "supported-report")),
+    };
+
+    let report = match xml::parse_report(body) {
+        Ok(report) => report,
+        Err(_) => return DavResponse::status(StatusCode::BAD_REQUEST),
+    };
+
+    let list = match find_calendar(list_id, user, conn) {
+        Some(list) => list,
+        None => return DavResponse::status(StatusCode::NOT_FOUND),
+    };
+
+    let mut multistatus = Multistatus::new();
+    match report {
+        Report::CalendarQuery { props, component } => {
+            if matches!(component.as_deref(), None | Some("VTODO")) {
+                let todos = match find_todos(list.list_id, user, conn) {
+                    Some(todos) => todos,
+                    None => return DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+                };
+
+                for (item, categories) in &todos {
+                    add_resource(&mut multistatus, user, &object_path(user, item), &Resource::Object(item, categories), &props);
+                }
+            }
+        }
+        Report::CalendarMultiget { props, hrefs } => {
+            for href in hrefs {
+                let object = match DavPath::parse_href(&href) {
+                    Some(DavPath::Object(username, object_list_id, name)) if username == user.username && object_list_id == list_id => {
+                        find_object(list_id, &name, user, conn)
+                    }
+                    _ => None,
+                };
+
+                match object {
+                    Some((item, categories)) => {
+                        add_resource(&mut multistatus, user, &object_path(user, &item), &Resource::Object(&item, &categories), &props);
+                    }
+                    None => multistatus.add_status(&href, "HTTP/1.1 404 Not Found"),
+                }
+            }
+        }
+        Report::Unsupported => return DavResponse::error(StatusCode::FORBIDDEN, &PropName::new(xml::DAV, "supported-report")),
+    }
+
+    DavResponse::multistatus(multistatus.finish())
+}
+
+/// Responds with the VTODO called `name` in the list with the public `list_id` as an iCalendar object, unless the
+/// client already has the current version according to the `preconditions`.
+pub fn get_object(user: &User, list_id: Uuid, name: &str, preconditions: &Preconditions, conn: &PgConnection) -> DavResponse {
+    let (item, categories) = match find_object(list_id, name, user, conn) {
+        Some(object) => object,
+        None => return DavResponse::status(StatusCode::NOT_FOUND),
+    };
+
+    let etag = etag::item_etag(&item);
+    if preconditions.not_modified(&etag) {
+        return DavResponse::status(StatusCode::NOT_MODIFIED).with_header("ETag", etag);
+    }
+
+    DavResponse {
+        status: StatusCode::OK.as_u16(),
+        headers: vec![
+            ("Content-Type", String::from(CONTENT_TYPE)),
+            ("ETag", etag),
+        ],
+        body: ical::object(&item, &categories, Utc::now()),
+    }
+}
+
+/// Attempts to save the VTODO in `body` as the object called `name` in the list with the public `list_id`, creating
+/// a new item if there isn't one called that yet. New items are unassigned, and changed items keep their assignee. The
+/// user's tags on the item are replaced by the VTODO's categories, leaving out those that aren't valid tag names.
+/// VTODOs that items can't hold, such as those with a blank summary, are refused. An `If-Match` header only lets an
+/// existing item be changed, and `If-None-Match: *` only lets one be created.
+pub fn put_object(user: &User, list_id: Uuid, name: &str, body: &str, preconditions: &Preconditions, events: &EventBus,
+                  conn: &PgConnection) -> DavResponse {
+    let list = match find_calendar(list_id, user, conn) {
+        Some(list) => list,
+        None => return DavResponse::status(StatusCode::NOT_FOUND),
+    };
+
+    let todo = match ical::parse_vtodo(body, user.time_zone()) {
+        Ok(todo) => todo,
+        Err(_) => return DavResponse::error(StatusCode::FORBIDDEN, &PropName::new(xml::CALDAV, "valid-calendar-data")),
+    };
+
+    // The same checks as every other way of writing items, such as a summary that isn't blank or too long
+    if list_service::check_item(&item_dto(&todo, None)).is_err() {
+        return DavResponse::error(StatusCode::FORBIDDEN, &PropName::new(xml::CALDAV, "valid-calendar-object-resource"));
+    }
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        let existing = match Item::find_item_by_dav_name(list.list_id, name, conn) {
+            Some(item) => Some(Item::lock_item(item.item_id, conn)?),
+            None => None,
+        };
+
+        let (item, status) = match existing {
+            Some(item) => {
+                let etag = etag::item_etag(&item);
+                if !preconditions.allows_write(&etag) || preconditions.not_modified(&etag) {
+                    return Ok(None);
+                }
+
+                let item = Item::update_item(item.item_id, item_dto(&todo, item.assignee_id), conn)?;
+                (item, StatusCode::NO_CONTENT)
+            }
+            None => {
+                if preconditions.if_match.is_some() {
+                    return Ok(None);
+                }
+
+                let identity = DavIdentity {
+                    ical_uid: todo.uid.clone().unwrap_or_else(|| format!("{}@todo-backend", Uuid::new_v4())),
+                    dav_name: String::from(name),
+                };
+                let item = Item::insert_dav_item(item_dto(&todo, None), list.list_id, identity, conn)?;
+                (item, StatusCode::CREATED)
+            }
+        };
+
+        set_categories(item.item_id, user.id, &todo.categories, conn)?;
+
+        let etag = etag::item_etag(&item);
+        let event = if status == StatusCode::CREATED {
+            ListEvent::item_created(item)
+        } else {
+            ListEvent::item_updated(item)
+        };
+        events.publish(event, conn)?;
+
+        Ok(Some((etag, status)))
+    });
+
+    match result {
+        Ok(Some((etag, status))) => DavResponse::status(status).with_header("ETag", etag),
+        Ok(None) => DavResponse::status(StatusCode::PRECONDITION_FAILED),
+        Err(_) => DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Attempts to move the item that is the object called `name` in the list with the public `list_id` to the user's
+/// trash, as long as it still matches the `preconditions`.
+pub fn delete_object(user: &User, list_id: Uuid, name: &str, preconditions: &Preconditions, events: &EventBus,
+                     conn: &PgConnection) -> DavResponse {
+    let item = match find_calendar(list_id, user, conn).and_then(|list| Item::find_item_by_dav_name(list.list_id, name, conn)) {
+        Some(item) => item,
+        None => return DavResponse::status(StatusCode::NOT_FOUND),
+    };
+
+    if !preconditions.allows_write(&etag::item_etag(&item)) {
+        return DavResponse::status(StatusCode::PRECONDITION_FAILED);
+    }
+
+    let result = conn.transaction(|| {
+        if Item::trash_item(item.item_id, user.id, conn) {
+            events.publish(ListEvent::item_deleted(&item), conn)
+        } else {
+            Err(diesel::result::Error::RollbackTransaction)
+        }
+    });
+
+    match result {
+        Ok(()) => DavResponse::status(StatusCode::NO_CONTENT),
+        Err(_) => DavResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Adds a resource to a multistatus response with the properties that were asked for.
+fn add_resource(multistatus: &mut Multistatus, user: &User, path: &DavPath, resource: &Resource, props: &PropRequest) {
+    let mut found = vec![];
+    let mut missing = vec![];
+
+    match props {
+        PropRequest::All => {
+            for (namespace, name) in ALL_PROPS {
+                let prop = PropName::new(namespace, name);
+                if let Some(value) = property(user, resource, &prop) {
+                    found.push((prop, value));
+                }
+            }
+        }
+        PropRequest::Named(names) => {
+            for prop in names {
+                match property(user, resource, prop) {
+                    Some(value) => found.push((prop.clone(), value)),
+                    None => missing.push(prop.clone()),
+                }
+            }
+        }
+    }
+
+    multistatus.add(&path.href(), &found, &missing);
+}
+
+/// Gets the value of a property of a resource as XML, if the resource has it.
+fn property(user: &User, resource: &Resource, prop: &PropName) -> Option<String> {
+    let href = |path: DavPath| format!("<d:href>{}</d:href>", xml::escape(&path.href()));
+    let privileges = |names: &[&str]| names.iter()
+        .map(|name| format!("<d:privilege><d:{}/></d:privilege>", name))
+        .collect::<String>();
+
+    let value = match (prop.namespace.as_str(), prop.name.as_str(), resource) {
+        ("DAV:", "resourcetype", Resource::Root) | ("DAV:", "resourcetype", Resource::Home) => String::from("<d:collection/>"),
+        ("DAV:", "resourcetype", Resource::Principal) => String::from("<d:collection/><d:principal/>"),
+        ("DAV:", "resourcetype", Resource::Calendar(_, _)) => String::from("<d:collection/><c:calendar/>"),
+        ("DAV:", "resourcetype", Resource::Object(_, _)) => String::new(),
+        ("DAV:", "displayname", Resource::Principal) => xml::escape(&user.preferred_name),
+        ("DAV:", "displayname", Resource::Calendar(list, _)) => xml::escape(&list.name),
+        ("DAV:", "current-user-principal", _) => href(DavPath::Principal(user.username.clone())),
+        ("DAV:", "principal-URL", Resource::Principal) => href(DavPath::Principal(user.username.clone())),
+        ("DAV:", "getetag", Resource::Calendar(_, etag)) => xml::escape(etag),
+        ("DAV:", "getetag", Resource::Object(item, _)) => xml::escape(&etag::item_etag(item)),
+        ("DAV:", "getcontenttype", Resource::Object(_, _)) => format!("{}; component=VTODO", CONTENT_TYPE),
+        ("DAV:", "getlastmodified", Resource::Object(item, _)) => item.updated_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        ("DAV:", "supported-report-set", Resource::Calendar(_, _)) => String::from(
+            "<d:supported-report><d:report><c:calendar-query/></d:report></d:supported-report>\
+             <d:supported-report><d:report><c:calendar-multiget/></d:report></d:supported-report>"),
+        ("DAV:", "current-user-privilege-set", Resource::Calendar(_, _)) | ("DAV:", "current-user-privilege-set", Resource::Object(_, _)) => {
+            privileges(&["read", "write", "write-content", "bind", "unbind"])
+        }
+        ("DAV:", "current-user-privilege-set", _) => privileges(&["read"]),
+        ("urn:ietf:params:xml:ns:caldav", "calendar-home-set", Resource::Root)
+        | ("urn:ietf:params:xml:ns:caldav", "calendar-home-set", Resource::Principal) => href(DavPath::Home(user.username.clone())),
+        ("urn:ietf:params:xml:ns:caldav", "calendar-user-address-set", Resource::Principal) => {
+            format!("<d:href>mailto:{}</d:href>", xml::escape(&user.email))
+        }
+        ("urn:ietf:params:xml:ns:caldav", "calendar-description", Resource::Calendar(list, _)) => xml::escape(list.description.as_ref()?),
+        ("urn:ietf:params:xml:ns:caldav", "supported-calendar-component-set", Resource::Calendar(_, _)) => String::from("<c:comp name=\"VTODO\"/>"),
+        ("urn:ietf:params:xml:ns:caldav", "calendar-data", Resource::Object(item, categories)) => xml::escape(&ical::object(item, categories, Utc::now())),
+        ("http://calendarserver.org/ns/", "getctag", Resource::Calendar(_, etag)) => xml::escape(etag),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Finds the list with the public `list_id` if the user can access it and it isn't in the trash.
+fn find_calendar(list_id: Uuid, user: &User, conn: &PgConnection) -> Option<List> {
+    let list_id = List::find_list_id(list_id, conn)?;
+    if !UserList::has_list_access(list_id, user.id, conn) {
+        return None;
+    }
+
+    List::find_list_by_id(list_id, conn)
+}
+
+/// Builds the ETag of a list, which is also the CTag of its calendar.
+fn list_etag(list: &List, conn: &PgConnection) -> Option<String> {
+    let item_versions = Item::find_item_versions(list.list_id, conn).ok()?;
+    Some(etag::list_etag(list.uuid, list.version, &item_versions))
+}
+
+/// Finds every item in the list with `list_id` that isn't in the trash, along with the user's tags on each.
+fn find_todos(list_id: i32, user: &User, conn: &PgConnection) -> Option<Vec<(Item, Vec<String>)>> {
+    let items = Item::find_items_for_list(list_id, conn).ok()?;
+    let item_ids: Vec<i32> = items.iter().map(|item| item.item_id).collect();
+
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (item_id, name) in Tag::find_tag_names_for_items(&item_ids, user.id, conn).ok()? {
+        tags.entry(item_id).or_default().push(name);
+    }
+
+    Some(items.into_iter()
+        .map(|item| {
+            let item_tags = tags.remove(&item.item_id).unwrap_or_default();
+            (item, item_tags)
+        })
+        .collect())
+}
+
+/// Finds the item that is the object called `name` in the list with the public `list_id`, along with the user's tags
+/// on it, if the user can access the list.
+fn find_object(list_id: Uuid, name: &str, user: &User, conn: &PgConnection) -> Option<(Item, Vec<String>)> {
+    let list = find_calendar(list_id, user, conn)?;
+    let item = Item::find_item_by_dav_name(list.list_id, name, conn)?;
+    let categories = Tag::find_tags_for_item(item.item_id, user.id, conn).into_iter()
+        .map(|tag| tag.name)
+        .collect();
+
+    Some((item, categories))
+}
+
+/// Builds the path of the object resource for an item, named by the client that created it or else by its public id.
+fn object_path(user: &User, item: &Item) -> DavPath {
+    let name = item.dav_name.clone().unwrap_or_else(|| format!("{}.ics", item.uuid));
+    DavPath::Object(user.username.clone(), item.list_uuid, name)
+}
+
+/// Builds the item for a VTODO, with the given assignee.
+fn item_dto(todo: &Todo, assignee_id: Option<i32>) -> ItemDTO {
+    ItemDTO {
+        description: todo.summary.clone(),
+        finished: todo.completed,
+        notes: todo.description.clone(),
+        assignee_id,
+        due_at: todo.due_at,
+        priority: todo.priority.clone(),
+        recurrence: todo.recurrence.clone(),
+        uuid: None,
+    }
+}
+
+/// Replaces the tags that the user with `user_id` put on the item with `item_id` with tags called `categories`,
+/// making any they don't have yet.
+fn set_categories(item_id: i32, user_id: i32, categories: &[String], conn: &PgConnection) -> QueryResult<()> {
+    let categories: Vec<&String> = categories.iter()
+        .filter(|category| tag_service::is_valid_tag_name(category))
+        .collect();
+
+    for tag in Tag::find_tags_for_item(item_id, user_id, conn) {
+        if !categories.contains(&&tag.name) && !Tag::untag_item(tag.tag_id, item_id, conn) {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+    }
+
+    for category in categories {
+        let tag = Tag::find_or_create_tag(user_id, category, conn)?;
+        if !Tag::tag_item(tag.tag_id, item_id, conn) {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::ical;
+use crate::models::calendar_feed::{CalendarFeed, CalendarFeedInfo};
+use crate::models::filter::{Due, Filter, FilterContext};
+use crate::models::item::Item;
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::tag::Tag;
+use crate::models::user::User;
+use crate::schema::items;
+
+static CALENDAR_NAME: &str = "Todo"; // Name that calendar apps show for a feed by default
+
+/// Responds with the calendar feed of the user with `user_id`, giving them one if they don't have one yet.
+pub async fn get_feed(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let feed = match CalendarFeed::find_feed_for_user(user_id, conn) {
+            Some(feed) => Ok(feed),
+            None => CalendarFeed::regenerate_feed(user_id, conn),
+        };
+
+        feed_response(feed, constants::MESSAGE_OK)
+    }).await
+}
+
+/// Attempts to give the user with `user_id` a new calendar feed token, so that the URL of their old feed
+/// stops working. Responds with the new feed.
+pub async fn regenerate_feed(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        feed_response(CalendarFeed::regenerate_feed(user_id, conn), constants::MESSAGE_REGENERATE_CALENDAR_FEED_SUCCESS)
+    }).await
+}
+
+/// Attempts to turn off the calendar feed of the user with `user_id`. A new one is made the next time they
+/// get their feed.
+pub async fn delete_feed(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if CalendarFeed::delete_feed(user_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_CALENDAR_FEED_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_CALENDAR_FEED_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to write the calendar feed with the secret `token` as an iCalendar file, with a VTODO for every
+/// item with a due date in the lists that its user can access, other than archived ones. Each item has that
+/// user's tags as its categories.
+pub async fn render_feed(token: String, db: PostgresDbConn) -> Result<String, ResponseWithStatus> {
+    db.run(move |conn| {
+        let user = match CalendarFeed::find_feed_by_token(&token, conn).and_then(|feed| User::find_user_by_id(feed.user_id, conn)) {
+            Some(user) => user,
+            None => return Err(ResponseWithStatus::with(Status::NotFound.code, constants::MESSAGE_CALENDAR_FEED_NOT_FOUND)),
+        };
+
+        let now = Utc::now();
+        let context = FilterContext {
+            user_id: user.id,
+            time_zone: user.time_zone(),
+            now,
+        };
+
+        let items = Filter::Due(Due::Any).matching_items(&context)
+            .order((items::due_at, items::item_id))
+            .load::<Item>(conn);
+        let items = match items {
+            Ok(items) => items,
+            Err(_) => return Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CALENDAR_FEED_FAILED)),
+        };
+
+        let item_ids: Vec<i32> = items.iter().map(|item| item.item_id).collect();
+        let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+        for (item_id, name) in Tag::find_tag_names_for_items(&item_ids, user.id, conn).unwrap_or_default() {
+            tags.entry(item_id).or_default().push(name);
+        }
+
+        let todos: Vec<(Item, Vec<String>)> = items.into_iter()
+            .map(|item| {
+                let item_tags = tags.remove(&item.item_id).unwrap_or_default();
+                (item, item_tags)
+            })
+            .collect();
+
+        Ok(ical::calendar(CALENDAR_NAME, &todos, now))
+    }).await
+}
+
+/// Builds the response for a calendar feed that was found or made, with the given success `message`.
+fn feed_response(feed: QueryResult<CalendarFeed>, message: &str) -> ResponseWithStatus {
+    match feed {
+        Ok(feed) => ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(message),
+                data: serde_json::to_value(CalendarFeedInfo::from(feed)).unwrap(),
+            },
+        },
+        Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CALENDAR_FEED_FAILED),
+    }
+}
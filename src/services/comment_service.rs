@@ -0,0 +1,119 @@
+use diesel::PgConnection;
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::models::comment::{Comment, CommentDTO};
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::user_lists::UserList;
+use crate::services::list_service::check_item_access;
+
+static MAX_BODY_LENGTH: usize = 2000; // Longest comment, in characters
+
+/// Responds with the comments on the item with `item_id`, as long as that item is in the list with `list_id`
+/// and the user with `user_id` has access to that list.
+pub async fn get_comments(list_id: i32, item_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data: serde_json::to_value(Comment::find_comments_for_item(item_id, conn)).unwrap(),
+            },
+        }
+    }).await
+}
+
+/// Attempts to add the given `comment` by the user with `user_id` to the item with `item_id`, as long as
+/// that item is in the list with `list_id` and the user has access to that list. Responds with the new comment.
+pub async fn add_comment(list_id: i32, item_id: i32, user_id: i32, comment: CommentDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        if !is_valid_body(&comment.body) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_COMMENT_BODY);
+        }
+
+        if let Some(result_comment) = Comment::create_comment(comment, item_id, user_id, conn) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_CREATE_COMMENT_SUCCESS),
+                    data: serde_json::to_value(result_comment).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_COMMENT_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to edit the comment with `comment_id` to the new values in `new_comment`. Only the author of a
+/// comment may edit it, and they must still have access to the list containing the item.
+pub async fn put_comment(list_id: i32, item_id: i32, comment_id: i32, user_id: i32, new_comment: CommentDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let comment = match find_comment_on_item(list_id, item_id, comment_id, user_id, conn) {
+            Ok(comment) => comment,
+            Err(response) => return response,
+        };
+
+        if comment.author_id != user_id {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_COMMENT_NOT_AUTHOR);
+        }
+
+        if !is_valid_body(&new_comment.body) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_COMMENT_BODY);
+        }
+
+        if Comment::update_comment(comment_id, new_comment, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_UPDATE_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to delete the comment with `comment_id`. Comments can be deleted by their author, or moderated
+/// by an owner of the list containing the item.
+pub async fn delete_comment(list_id: i32, item_id: i32, comment_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let comment = match find_comment_on_item(list_id, item_id, comment_id, user_id, conn) {
+            Ok(comment) => comment,
+            Err(response) => return response,
+        };
+
+        if comment.author_id != user_id && !UserList::is_list_owner(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_COMMENT_NOT_AUTHOR_OR_OWNER);
+        }
+
+        if Comment::delete_comment(comment_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_COMMENT_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_COMMENT_FAILED)
+        }
+    }).await
+}
+
+/// Finds the comment with `comment_id` after checking that the user with `user_id` can access it through the
+/// item with `item_id` in the list with `list_id`. Returns the failed response to send if not.
+fn find_comment_on_item(list_id: i32, item_id: i32, comment_id: i32, user_id: i32, conn: &PgConnection) -> Result<Comment, ResponseWithStatus> {
+    if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+        return Err(response);
+    }
+
+    match Comment::find_comment_by_id(comment_id, conn) {
+        Some(comment) if comment.item_id == item_id => Ok(comment),
+        _ => Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_COMMENT_NOT_OWNED_BY_ITEM)),
+    }
+}
+
+/// Determines whether `body` can be the text of a comment, which must not be blank or longer than `MAX_BODY_LENGTH`.
+fn is_valid_body(body: &str) -> bool {
+    !body.trim().is_empty() && body.chars().count() <= MAX_BODY_LENGTH
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use rocket::futures::Stream;
+use rocket::http::Status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::sync::broadcast::error::RecvError;
+
+use crate::constants;
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::events::EventBus;
+use crate::models::event::ListEvent;
+use crate::models::response::ResponseWithStatus;
+use crate::models::user_lists::UserList;
+
+/// Attempts to follow the changes to the list with `list_id`, or to every list the user with `user_id` has access to
+/// if there isn't one, as a stream of server-sent events named by their type. When the user is added to a list or
+/// removed from one, which includes the list being trashed or restored, their access is checked again, so a stream of
+/// every list picks up new lists, the user stops seeing a list as soon as they are removed from it, and a stream of a
+/// single list ends. If the user falls too far behind, a `lagged` event with the number of missed events is sent
+/// instead, and the user should get the lists again.
+pub async fn follow_lists(user_id: i32, list_id: Option<i32>, events: EventBus, pool: PostgresPool, db: PostgresDbConn)
+                          -> Result<EventStream<impl Stream<Item = Event>>, ResponseWithStatus> {
+    // Subscribe before finding the lists, so that changes made in between aren't missed
+    let mut receiver = events.subscribe();
+
+    let list_ids = db.run(move |conn| match list_id {
+        Some(list_id) if UserList::has_list_access(list_id, user_id, conn) => Ok(vec![list_id]),
+        Some(_) => Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS)),
+        None => UserList::find_list_ids_for_user(user_id, conn)
+            .map_err(|_| ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_SUBSCRIBE_EVENTS_FAILED)),
+    }).await?;
+
+    let mut visible: HashSet<i32> = list_ids.into_iter().collect();
+
+    Ok(EventStream! {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    yield Event::data(missed.to_string()).event("lagged");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let changed_list_id = event.list_id();
+            let was_visible = visible.contains(&changed_list_id);
+
+            let is_own_membership = match &event {
+                ListEvent::MemberAdded { user_id: member_id, .. } | ListEvent::MemberRemoved { user_id: member_id, .. } => {
+                    *member_id == user_id
+                }
+                _ => false,
+            };
+
+            if is_own_membership && (list_id.is_none() || list_id == Some(changed_list_id)) {
+                if has_list_access(changed_list_id, user_id, &pool).await {
+                    visible.insert(changed_list_id);
+                } else {
+                    visible.remove(&changed_list_id);
+                }
+            }
+
+            // The user still hears that they were removed from a list, even though they can no longer see it
+            if was_visible || visible.contains(&changed_list_id) {
+                yield Event::json(&event).event(event.name());
+            }
+
+            // Following a single list ends once the user can no longer see it
+            if list_id.is_some() && visible.is_empty() {
+                break;
+            }
+        }
+    })
+}
+
+/// Determines whether the user with `user_id` can currently access the list with `list_id`, treating a database that
+/// can't be reached as no access.
+async fn has_list_access(list_id: i32, user_id: i32, pool: &PostgresPool) -> bool {
+    match pool.get().await {
+        Some(conn) => conn.run(move |conn| UserList::has_list_access(list_id, user_id, conn)).await,
+        None => false,
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use diesel::PgConnection;
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::export;
+use crate::models::item::Item;
+use crate::models::list::List;
+use crate::models::portable::{ListFormat, PORTABLE_FORMAT, PORTABLE_VERSION, PortableItem, PortableList};
+use crate::models::response::ResponseWithStatus;
+use crate::models::tag::Tag;
+use crate::models::user::User;
+use crate::models::user_lists::UserList;
+
+/// Attempts to export the list with `list_id` in the given `format`, as long as the user with `user_id` has
+/// access to it. The export includes that user's tags on each item, and dates in their time zone where the
+/// format needs them. Returns the name of the file along with its contents.
+pub async fn export_list(list_id: i32, user_id: i32, format: ListFormat, db: PostgresDbConn) -> Result<(String, String), ResponseWithStatus> {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS));
+        }
+
+        let list = match find_portable_list(list_id, user_id, conn) {
+            Some(list) => list,
+            None => return Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_EXPORT_FAILED)),
+        };
+
+        let time_zone = match User::find_user_by_id(user_id, conn) {
+            Some(user) => user.time_zone(),
+            None => Tz::UTC,
+        };
+
+        Ok((export::file_name(&list.name, format), export::render(&list, format, time_zone)))
+    }).await
+}
+
+/// Gathers the list with `list_id` and its items, other than those in the trash, into a `PortableList`
+/// with the tags of the user with `user_id`.
+fn find_portable_list(list_id: i32, user_id: i32, conn: &PgConnection) -> Option<PortableList> {
+    let list = List::find_list_by_id(list_id, conn)?;
+    let items = Item::find_items_for_list(list_id, conn).ok()?;
+
+    let item_ids: Vec<i32> = items.iter().map(|item| item.item_id).collect();
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (item_id, name) in Tag::find_tag_names_for_items(&item_ids, user_id, conn).ok()? {
+        tags.entry(item_id).or_default().push(name);
+    }
+
+    let assignee_ids: Vec<i32> = items.iter().filter_map(|item| item.assignee_id).collect();
+    let usernames: HashMap<i32, String> = User::find_usernames(&assignee_ids, conn).ok()?.into_iter().collect();
+
+    let items = items.into_iter()
+        .map(|item| PortableItem {
+            tags: tags.remove(&item.item_id).unwrap_or_default(),
+            assignee: item.assignee_id.and_then(|assignee_id| usernames.get(&assignee_id).cloned()),
+            description: item.description,
+            finished: item.finished,
+            notes: item.notes,
+            due_at: item.due_at,
+            priority: item.priority,
+            recurrence: item.recurrence,
+            created_at: Some(item.created_at),
+            completed_at: item.completed_at,
+        })
+        .collect();
+
+    Some(PortableList {
+        format: String::from(PORTABLE_FORMAT),
+        version: PORTABLE_VERSION,
+        exported_at: Some(Utc::now()),
+        name: list.name,
+        description: list.description,
+        items,
+    })
+}
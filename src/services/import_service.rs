@@ -0,0 +1,173 @@
+use std::fs;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use rocket::fs::TempFile;
+use rocket::http::Status;
+use uuid::Uuid;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::events::EventBus;
+use crate::import::{self, ImportedList};
+use crate::models::event::ListEvent;
+use crate::models::item::{Item, ItemDTO};
+use crate::models::list::{List, ListDTO};
+use crate::models::portable::{ImportReport, ListFormat};
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::tag::Tag;
+use crate::models::user::User;
+use crate::models::user_lists::UserList;
+use crate::services::{list_service, tag_service};
+
+static DEFAULT_LIST_NAME: &str = "Imported list"; // Name of imported lists when neither the request nor the file gives one
+
+/// Attempts to import the uploaded `file` in the given `format` as a new list owned by the user with `user_id`,
+/// along with its items, in a single transaction. The list is called `name` if given, or else by the name in
+/// the file or the name it was uploaded as. Items are only assigned if they are assigned to the user, since
+/// nobody else can access the new list yet. Responds with a report of the imported items and the skipped
+/// lines, and if `dry_run` is set, only builds the report without saving anything.
+pub async fn import_list(user_id: i32, format: ListFormat, file: &mut TempFile<'_>, name: Option<String>, dry_run: bool,
+                         events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    let staged_path = std::env::temp_dir().join(Uuid::new_v4().to_simple().to_string());
+    if file.persist_to(&staged_path).await.is_err() {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_IMPORT_FAILED);
+    }
+
+    let file_name = file.name().map(String::from);
+
+    db.run(move |conn| {
+        let contents = fs::read_to_string(&staged_path);
+        let _ = fs::remove_file(&staged_path);
+
+        let contents = match contents {
+            Ok(contents) => contents,
+            Err(_) => return invalid_import(String::from("the file must be UTF-8 text")),
+        };
+
+        let user = match User::find_user_by_id(user_id, conn) {
+            Some(user) => user,
+            None => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_IMPORT_FAILED),
+        };
+
+        let mut imported = match import::parse(contents.trim_start_matches('\u{feff}'), format, user.time_zone(), Utc::now()) {
+            Ok(imported) => imported,
+            Err(reason) => return invalid_import(reason),
+        };
+
+        let name = [name, imported.name.take(), file_name].iter()
+            .flatten()
+            .map(|name| name.trim())
+            .find(|name| !name.is_empty())
+            .map(String::from)
+            .unwrap_or_else(|| String::from(DEFAULT_LIST_NAME));
+
+        let imported = check_items(imported, &user);
+        let mut report = ImportReport {
+            dry_run,
+            list_id: None,
+            name,
+            description: imported.description,
+            items: imported.items.into_iter().map(|imported_item| imported_item.item).collect(),
+            skipped: imported.skipped,
+        };
+
+        if !dry_run {
+            let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+                let mut created_items = vec![];
+                let list = List::create_list(ListDTO {
+                    name: report.name.clone(),
+                    description: report.description.clone(),
+                    uuid: None,
+                }, conn).ok_or(diesel::result::Error::RollbackTransaction)?;
+                let list_id = list.list_id;
+                let list_uuid = list.uuid;
+                let added = ListEvent::member_added(&list, user_id);
+
+                if !UserList::associate_list(list, user, true, conn) {
+                    return Err(diesel::result::Error::RollbackTransaction);
+                }
+
+                for portable_item in &report.items {
+                    let item = Item::insert_item(ItemDTO {
+                        description: portable_item.description.clone(),
+                        finished: portable_item.finished,
+                        notes: portable_item.notes.clone(),
+                        assignee_id: portable_item.assignee.as_ref().map(|_| user_id),
+                        due_at: portable_item.due_at,
+                        priority: portable_item.priority.clone(),
+                        recurrence: portable_item.recurrence.clone(),
+                        uuid: None,
+                    }, list_id, conn)?;
+
+                    for name in &portable_item.tags {
+                        let tag = Tag::find_or_create_tag(user_id, name, conn)?;
+                        if !Tag::tag_item(tag.tag_id, item.item_id, conn) {
+                            return Err(diesel::result::Error::RollbackTransaction);
+                        }
+                    }
+
+                    created_items.push(item);
+                }
+
+                events.publish(added, conn)?;
+                events.publish_all(ListEvent::items_created(created_items), conn)?;
+                Ok(list_uuid)
+            });
+
+            match result {
+                Ok(list_uuid) => report.list_id = Some(list_uuid),
+                Err(_) => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_IMPORT_FAILED),
+            }
+        }
+
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(if dry_run { constants::MESSAGE_IMPORT_PREVIEW } else { constants::MESSAGE_IMPORT_SUCCESS }),
+                data: serde_json::to_value(report).unwrap(),
+            },
+        }
+    }).await
+}
+
+/// Skips the imported items with descriptions that are too long or tags that aren't valid tag names, and takes off
+/// assignees other than the `user` importing the list.
+fn check_items(mut imported: ImportedList, user: &User) -> ImportedList {
+    let items = std::mem::take(&mut imported.items);
+
+    for mut imported_item in items {
+        if !list_service::is_valid_description(&imported_item.item.description) {
+            let reason = String::from("the description is longer than 500 characters");
+            imported.skip(imported_item.line, &imported_item.text, reason);
+            continue;
+        }
+
+        imported_item.item.tags = imported_item.item.tags.iter().map(|tag| tag.trim().to_string()).collect();
+        if let Some(tag) = imported_item.item.tags.iter().find(|tag| !tag_service::is_valid_tag_name(tag)) {
+            let reason = format!("`{}` is not a valid tag name", tag);
+            imported.skip(imported_item.line, &imported_item.text, reason);
+            continue;
+        }
+
+        if imported_item.item.assignee.as_deref() != Some(user.username.as_str()) {
+            imported_item.item.assignee = None;
+        }
+
+        imported.items.push(imported_item);
+    }
+
+    imported.skipped.sort_by_key(|skipped| skipped.line);
+    imported
+}
+
+/// Builds the response for a file that can't be imported, giving the `reason` in the data.
+fn invalid_import(reason: String) -> ResponseWithStatus {
+    ResponseWithStatus {
+        status_code: Status::BadRequest.code,
+        response: Response {
+            message: String::from(constants::MESSAGE_INVALID_IMPORT),
+            data: serde_json::Value::String(reason),
+        },
+    }
+}
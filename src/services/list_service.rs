@@ -1,66 +1,88 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use diesel::{Connection, PgConnection, QueryResult};
 use rocket::http::Status;
+use uuid::Uuid;
 
 use crate::constants;
 use crate::database::PostgresDbConn;
-use crate::models::item::{Item, ItemDTO};
-use crate::models::list::{List, ListDTO};
-use crate::models::response::{Response, ResponseWithStatus};
+use crate::etag::{self, Preconditions};
+use crate::events::EventBus;
+use crate::import;
+use crate::models::comment::Comment;
+use crate::models::event::ListEvent;
+use crate::models::item::{BatchItemResult, FinishedDTO, Item, ItemDTO, ItemFilter, ItemPatchDTO, ItemTransferDTO, QuickAddDTO, QuickAddResult,
+                         RenderedItem};
+use crate::models::list::{List, ListDTO, ListPatchDTO, RenderedList};
+use crate::models::page::{Page, PageQuery, SORT_CREATED, SORT_DESCRIPTION, SORT_NAME, SORT_UPDATED};
+use crate::models::response::{Response, ResponseWithStatus, TaggedResponse};
+use crate::models::tag::Tag;
 use crate::models::user::{User, UserDTO};
 use crate::models::user_lists::UserList;
+use crate::quick_add;
+use crate::services::{attachment_service, tag_service};
+use crate::storage::Storage;
+
+static MAX_DESCRIPTION_LENGTH: usize = 500; // Longest item description, in characters
 
 /// Attempts to create the list with the specified information and associate it with its owner.
 /// Responds with the appropriate status.
-pub async fn create_list(list: ListDTO, owner_username: String, db: PostgresDbConn) -> ResponseWithStatus {
+pub async fn create_list(list: ListDTO, owner_username: String, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
     db.run(move |conn| {
-        if let Some(user) = User::find_user_by_username(&owner_username, conn) {
-            if let Some(result_list) = List::create_list(list, conn) {
-                if UserList::associate_list(result_list, user, true, conn) {
-                    ResponseWithStatus {
-                        status_code: Status::Ok.code,
-                        response: Response {
-                            message: String::from(constants::MESSAGE_CREATE_LIST_SUCCESS),
-                            data: serde_json::to_value("").unwrap(),
-                        },
-                    }
-                } else {
-                    ResponseWithStatus {
-                        status_code: Status::BadRequest.code,
-                        response: Response {
-                            message: String::from(constants::MESSAGE_CREATE_LIST_ASSOCIATE_FAIL),
-                            data: serde_json::to_value("").unwrap(),
-                        },
-                    }
-                }
-            } else {
-                ResponseWithStatus {
-                    status_code: Status::BadRequest.code,
-                    response: Response {
-                        message: String::from(constants::MESSAGE_CREATE_LIST_FAILED),
-                        data: serde_json::to_value("").unwrap(),
-                    },
-                }
-            }
-        } else {
-            ResponseWithStatus {
-                status_code: Status::BadRequest.code,
-                response: Response {
-                    message: String::from(constants::MESSAGE_USER_NOT_FOUND),
-                    data: serde_json::to_value("").unwrap(),
-                },
+        let user = match User::find_user_by_username(&owner_username, conn) {
+            Some(user) => user,
+            None => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_USER_NOT_FOUND),
+        };
+
+        let mut failure = constants::MESSAGE_CREATE_LIST_FAILED;
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let list = List::create_list(list, conn).ok_or(diesel::result::Error::RollbackTransaction)?;
+            let added = ListEvent::member_added(&list, user.id);
+
+            if !UserList::associate_list(list, user, true, conn) {
+                failure = constants::MESSAGE_CREATE_LIST_ASSOCIATE_FAIL;
+                return Err(diesel::result::Error::RollbackTransaction);
             }
+
+            events.publish(added, conn)
+        });
+
+        match result {
+            Ok(()) => ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_CREATE_LIST_SUCCESS),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, failure),
         }
     }).await
 }
 
-/// Responds with a JSON object containing basic information about each list that the given user has access to.
-pub async fn get_lists_for_user(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+/// Responds with a JSON object containing basic information about a page of the lists that the given user has access
+/// to, filtered and sorted according to `page`. Only the lists they archived are included if `archived` is set, and
+/// only the others if not. If `render_html` is set, each list description is also rendered to sanitized HTML.
+pub async fn get_lists_for_user(user_id: i32, archived: bool, page: PageQuery, render_html: bool, db: PostgresDbConn) -> ResponseWithStatus {
+    let page = match page.parse(&[SORT_CREATED, SORT_UPDATED, SORT_NAME]) {
+        Some(page) => page,
+        None => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_PAGE),
+    };
+
     db.run(move |conn| {
-        if let Some(lists) = List::find_lists_for_user(user_id, conn) {
+        if let Ok(lists) = List::find_lists_for_user(user_id, archived, page, conn) {
+            let data = if render_html {
+                let rendered: Page<RenderedList> = Page {
+                    results: lists.results.into_iter().map(List::render).collect(),
+                    next_cursor: lists.next_cursor,
+                    total_count: lists.total_count,
+                };
+                serde_json::to_value(rendered).unwrap()
+            } else {
+                serde_json::to_value(lists).unwrap()
+            };
+
             ResponseWithStatus {
                 status_code: Status::Ok.code,
                 response: Response {
                     message: String::from(constants::MESSAGE_OK),
-                    data: serde_json::to_value(lists).unwrap(),
+                    data,
                 },
             }
         } else {
@@ -69,74 +91,291 @@ pub async fn get_lists_for_user(user_id: i32, db: PostgresDbConn) -> ResponseWit
     }).await
 }
 
-/// Attempts to get the full list (with items) with the given `list_id`, as long as the user with `user_id` has
-/// access to it.
-pub async fn get_list(list_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+/// Attempts to get the full list (with a page of its items) with the given `list_id`, as long as the user with `user_id`
+/// has access to it. The items are filtered and sorted according to `page`. If `render_html` is set, the Markdown in the
+/// list and its items is also rendered to sanitized HTML. Only the items that match the `filter` are included.
+/// Responds with the ETag of this page of the list, or with no content if it matches the one in `preconditions`.
+/// Each page, filter and rendering of the list has its own ETag.
+pub async fn get_list(list_id: i32, user_id: i32, filter: ItemFilter, page: PageQuery, render_html: bool,
+                      preconditions: Preconditions, db: PostgresDbConn) -> TaggedResponse {
+    let page = match page.parse(&[SORT_CREATED, SORT_UPDATED, SORT_DESCRIPTION]) {
+        Some(page) => page,
+        None => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_PAGE).into(),
+    };
+
+    let variant = format!("{}&assignee={:?}&finished={:?}&render_html={}", page.variant(), filter.assignee_id, filter.finished, render_html);
+
     db.run(move |conn| {
         if !UserList::has_list_access(list_id, user_id, conn) {
-            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS).into();
         }
 
-        if let Some(list) = List::find_complete_list_by_id(list_id, conn) {
-            ResponseWithStatus {
-                status_code: Status::Ok.code,
-                response: Response {
-                    message: String::from(constants::MESSAGE_GET_LIST_SUCCESS),
-                    data: serde_json::to_value(list).unwrap(),
-                },
+        // The list and its ETag are read from the same snapshot, so that they always agree
+        let result = conn.build_transaction().repeatable_read().read_only().run::<_, diesel::result::Error, _>(|| {
+            let list = List::find_complete_list_by_id(list_id, filter, page, conn)
+                .ok_or(diesel::result::Error::NotFound)?;
+            let etag = etag::list_etag(list.list_id, list.version, &Item::find_item_versions(list_id, conn)?);
+
+            Ok((list, etag::variant_etag(&etag, &variant)))
+        });
+
+        match result {
+            Ok((_, etag)) if preconditions.not_modified(&etag) => not_modified(etag),
+            Ok((list, etag)) => {
+                let data = if render_html {
+                    serde_json::to_value(list.render()).unwrap()
+                } else {
+                    serde_json::to_value(list).unwrap()
+                };
+
+                TaggedResponse {
+                    response: ResponseWithStatus {
+                        status_code: Status::Ok.code,
+                        response: Response {
+                            message: String::from(constants::MESSAGE_GET_LIST_SUCCESS),
+                            data,
+                        },
+                    },
+                    etag: Some(etag),
+                }
             }
-        } else {
-            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_LIST_FAILED)
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_LIST_FAILED).into(),
+        }
+    }).await
+}
+
+/// Attempts to get the item with `item_id`, as long as that item is in a list with id `list_id` able to be
+/// accessed by the user with `user_id`. If `render_html` is set, the notes of the item are also rendered to
+/// sanitized HTML. Responds with the ETag of the item as rendered, or with no content if it matches the one in
+/// `preconditions`.
+pub async fn get_item(list_id: i32, item_id: i32, user_id: i32, render_html: bool,
+                      preconditions: Preconditions, db: PostgresDbConn) -> TaggedResponse {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response.into();
+        }
+
+        match Item::find_item_by_id(item_id, conn) {
+            Some(item) => {
+                let etag = etag::variant_etag(&etag::item_etag(&item), &format!("render_html={}", render_html));
+                if preconditions.not_modified(&etag) {
+                    return not_modified(etag);
+                }
+
+                let data = if render_html {
+                    serde_json::to_value(item.render()).unwrap()
+                } else {
+                    serde_json::to_value(item).unwrap()
+                };
+
+                TaggedResponse {
+                    response: ResponseWithStatus {
+                        status_code: Status::Ok.code,
+                        response: Response {
+                            message: String::from(constants::MESSAGE_GET_ITEM_SUCCESS),
+                            data,
+                        },
+                    },
+                    etag: Some(etag),
+                }
+            }
+            None => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ITEM_NOT_OWNED_BY_LIST).into(),
         }
     }).await
 }
 
 /// Attempts to update the list with the given `list_id` with the new values in `new_list`, as long as the
-/// user with `user_id` has access to it.
-pub async fn put_list(list_id: i32, user_id: i32, new_list: ListDTO, db: PostgresDbConn) -> ResponseWithStatus {
+/// user with `user_id` has access to it and the list still matches the `preconditions`. Responds with the new ETag.
+pub async fn put_list(list_id: i32, user_id: i32, new_list: ListDTO, preconditions: Preconditions,
+                      events: EventBus, db: PostgresDbConn) -> TaggedResponse {
     db.run(move |conn| {
         if !UserList::has_list_access(list_id, user_id, conn) {
-            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS).into();
         }
 
-        if List::update_list(list_id, new_list, conn) {
-            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_UPDATE_SUCCESS)
-        } else {
-            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED)
+        let result = change_list_if(list_id, &preconditions, conn, || {
+            let old_name = List::lock_list(list_id, conn)?.name;
+            let list = List::update_list(list_id, new_list, conn)?;
+            events.publish_all(renamed_event(&old_name, &list), conn)?;
+
+            current_list_etag(&list, conn)
+        });
+
+        match result {
+            Ok(Some(etag)) => TaggedResponse {
+                response: ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_UPDATE_SUCCESS),
+                etag: Some(etag),
+            },
+            Ok(None) => precondition_failed(),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED).into(),
         }
     }).await
 }
 
 /// Attempts to update the item with `item_id` with the new values in `new_item`, as long as that item is in a list with id
-/// `list_id` able to be accessed by the user with `user_id`.
-pub async fn put_item_for_list(list_id: i32, user_id: i32, item_id: i32, new_item: ItemDTO, db: PostgresDbConn) -> ResponseWithStatus {
+/// `list_id` able to be accessed by the user with `user_id` and it still matches the `preconditions`. Responds with
+/// the new ETag.
+pub async fn put_item_for_list(list_id: i32, user_id: i32, item_id: i32, new_item: ItemDTO,
+                               preconditions: Preconditions, events: EventBus, db: PostgresDbConn) -> TaggedResponse {
     db.run(move |conn| {
         if !UserList::has_list_access(list_id, user_id, conn) {
-            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS).into();
         }
 
         if !Item::owned_by_list(item_id, list_id, conn) {
-            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ITEM_NOT_OWNED_BY_LIST);
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ITEM_NOT_OWNED_BY_LIST).into();
         }
 
-        if Item::update_item(item_id, new_item, conn) {
-            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_UPDATE_SUCCESS)
+        if let Err(message) = check_item(&new_item) {
+            return ResponseWithStatus::with(Status::BadRequest.code, message).into();
+        }
+
+        if !assignee_has_access(new_item.assignee_id, list_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ASSIGNEE_NO_ACCESS).into();
+        }
+
+        let result = change_item_if(item_id, &preconditions, conn, || {
+            let item = Item::update_item(item_id, new_item, conn)?;
+            let etag = etag::item_etag(&item);
+            events.publish(ListEvent::item_updated(item), conn)?;
+
+            Ok(etag)
+        });
+
+        match result {
+            Ok(Some(etag)) => TaggedResponse {
+                response: ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_UPDATE_SUCCESS),
+                etag: Some(etag),
+            },
+            Ok(None) => precondition_failed(),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED).into(),
+        }
+    }).await
+}
+
+/// Attempts to update only the fields present in `patch` for the list with the given `list_id`, as long as the
+/// user with `user_id` has access to it and the list still matches the `preconditions`. Responds with the updated
+/// list and its new ETag.
+pub async fn patch_list(list_id: i32, user_id: i32, patch: ListPatchDTO, preconditions: Preconditions,
+                        events: EventBus, db: PostgresDbConn) -> TaggedResponse {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS).into();
+        }
+
+        let result = change_list_if(list_id, &preconditions, conn, || {
+            let old_name = List::lock_list(list_id, conn)?.name;
+            let list = List::patch_list(list_id, patch, conn)?;
+            events.publish_all(renamed_event(&old_name, &list), conn)?;
+            let etag = current_list_etag(&list, conn)?;
+
+            Ok((list, etag))
+        });
+
+        match result {
+            Ok(Some((list, etag))) => TaggedResponse {
+                response: ResponseWithStatus {
+                    status_code: Status::Ok.code,
+                    response: Response {
+                        message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                        data: serde_json::to_value(list).unwrap(),
+                    },
+                },
+                etag: Some(etag),
+            },
+            Ok(None) => precondition_failed(),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED).into(),
+        }
+    }).await
+}
+
+/// Attempts to update only the fields present in `patch` for the item with `item_id`, as long as that item is in
+/// a list with id `list_id` able to be accessed by the user with `user_id` and it still matches the `preconditions`.
+/// Responds with the updated item and its new ETag.
+pub async fn patch_item_for_list(list_id: i32, user_id: i32, item_id: i32, patch: ItemPatchDTO,
+                                 preconditions: Preconditions, events: EventBus, db: PostgresDbConn) -> TaggedResponse {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response.into();
+        }
+
+        if let Err(message) = check_item_patch(&patch) {
+            return ResponseWithStatus::with(Status::BadRequest.code, message).into();
+        }
+
+        if let Some(assignee_id) = patch.assignee_id {
+            if !assignee_has_access(assignee_id, list_id, conn) {
+                return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ASSIGNEE_NO_ACCESS).into();
+            }
+        }
+
+        let result = change_item_if(item_id, &preconditions, conn, || {
+            let item = Item::patch_item(item_id, patch, conn)?;
+            events.publish(ListEvent::item_updated(item.clone()), conn)?;
+
+            Ok(item)
+        });
+
+        match result {
+            Ok(Some(item)) => TaggedResponse {
+                etag: Some(etag::item_etag(&item)),
+                response: ResponseWithStatus {
+                    status_code: Status::Ok.code,
+                    response: Response {
+                        message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                        data: serde_json::to_value(item).unwrap(),
+                    },
+                },
+            },
+            Ok(None) => precondition_failed(),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED).into(),
+        }
+    }).await
+}
+
+/// Archives or unarchives the list with the given `list_id` for the user with `user_id` only, as long as they have
+/// access to it. Other members still see the list as usual, and the list itself is left untouched.
+pub async fn set_list_archived(list_id: i32, user_id: i32, archived: bool, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+        }
+
+        let message = if archived {
+            constants::MESSAGE_ARCHIVE_LIST_SUCCESS
         } else {
-            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED)
+            constants::MESSAGE_UNARCHIVE_LIST_SUCCESS
+        };
+
+        match UserList::set_archived(list_id, user_id, archived, conn) {
+            Ok(_) => ResponseWithStatus::with(Status::Ok.code, message),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED),
         }
     }).await
 }
 
-/// Attempts to delete the list with the given `list_id`. Makes sure that the user with `user_id`
-/// owns the list before deleting it. Also, through cascading, deletes any `UserList` or `Item`s
-/// related to the list.
-pub async fn delete_list(list_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+/// Attempts to delete the list with the given `list_id` by moving it into the trash of the user with `user_id`,
+/// which hides it and its items from every member until it is restored. Makes sure that the user owns the list
+/// before deleting it. The list is only deleted if it still matches the `preconditions`. Every member is told that
+/// they lost access to the list, which ends their streams of it.
+pub async fn delete_list(list_id: i32, user_id: i32, preconditions: Preconditions, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
     db.run(move |conn| {
         if UserList::is_list_owner(list_id, user_id, conn) {
-            if List::delete_list(list_id, conn) {
-                ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_LIST_SUCCESS)
-            } else {
-                ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_LIST_FAILED)
+            let result = change_list_if(list_id, &preconditions, conn, || {
+                let list = List::lock_list(list_id, conn)?;
+                let member_ids = UserList::find_member_ids(list_id, conn)?;
+
+                if List::trash_list(list_id, user_id, conn) {
+                    events.publish_all(ListEvent::list_and_members_trashed(&list, member_ids), conn)
+                } else {
+                    Err(diesel::result::Error::RollbackTransaction)
+                }
+            });
+
+            match result {
+                Ok(Some(())) => ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_LIST_SUCCESS),
+                Ok(None) => ResponseWithStatus::with(Status::PreconditionFailed.code, constants::MESSAGE_PRECONDITION_FAILED),
+                _ => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_LIST_FAILED),
             }
         } else {
             ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_NOT_OWNER)
@@ -146,32 +385,596 @@ pub async fn delete_list(list_id: i32, user_id: i32, db: PostgresDbConn) -> Resp
 
 /// Attempts to add the given `item` to the list with the specified `list_id`. Responds with the
 /// appropriate status. Makes sure that the user with `user_id` has access to that list.
-pub async fn add_item_to_list(list_id: i32, user_id: i32, item: ItemDTO, db: PostgresDbConn) -> ResponseWithStatus {
+pub async fn add_item_to_list(list_id: i32, user_id: i32, item: ItemDTO, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+        }
+
+        if let Err(message) = check_item(&item) {
+            return ResponseWithStatus::with(Status::BadRequest.code, message);
+        }
+
+        if !assignee_has_access(item.assignee_id, list_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ASSIGNEE_NO_ACCESS);
+        }
+
+        let result = conn.transaction(|| {
+            let item = Item::insert_item(item, list_id, conn)?;
+            events.publish(ListEvent::item_created(item), conn)
+        });
+
+        match result {
+            Ok(()) => ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_CREATE_ITEM_SUCCESS),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_ITEM_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to add an item to the list with `list_id` from the single line of text in `entry`, parsing its due date,
+/// priority, tags, assignee and recurrence out of the text with `quick_add::parse`. Tags that the user with `user_id`
+/// doesn't have yet are created. Responds with the new item and the parsed fields.
+pub async fn quick_add_item(list_id: i32, user_id: i32, entry: QuickAddDTO, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+        }
+
+        let time_zone = match User::find_user_by_id(user_id, conn) {
+            Some(user) => user.time_zone(),
+            None => Tz::UTC,
+        };
+
+        let parsed = quick_add::parse(&entry.text, time_zone, Utc::now());
+        if parsed.description.is_empty() {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_QUICK_ADD_EMPTY);
+        }
+
+        if !is_valid_description(&parsed.description) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_ITEM_DESCRIPTION);
+        }
+
+        let assignee_id = match &parsed.assignee {
+            Some(username) => match User::find_user_by_username(username, conn) {
+                Some(assignee) => Some(assignee.id),
+                None => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_QUICK_ADD_UNKNOWN_ASSIGNEE),
+            },
+            None => None,
+        };
+
+        if !assignee_has_access(assignee_id, list_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ASSIGNEE_NO_ACCESS);
+        }
+
+        if !parsed.tags.iter().all(|tag| tag_service::is_valid_tag_name(tag)) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_TAG_NAME);
+        }
+
+        let item = ItemDTO {
+            description: parsed.description.clone(),
+            finished: false,
+            notes: None,
+            assignee_id,
+            due_at: parsed.due_at,
+            priority: parsed.priority.clone(),
+            recurrence: parsed.recurrence.clone(),
+            uuid: entry.uuid,
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let item = Item::insert_item(item, list_id, conn)?;
+
+            for name in &parsed.tags {
+                let tag = Tag::find_or_create_tag(user_id, name, conn)?;
+                if !Tag::tag_item(tag.tag_id, item.item_id, conn) {
+                    return Err(diesel::result::Error::RollbackTransaction);
+                }
+            }
+
+            events.publish(ListEvent::item_created(item.clone()), conn)?;
+            Ok(item)
+        });
+
+        match result {
+            Ok(item) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_CREATE_ITEM_SUCCESS),
+                    data: serde_json::to_value(QuickAddResult { item, parsed }).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_ITEM_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to delete the item with the given `item_id` by moving it into the trash of the user with `user_id`. First,
+/// ensures that the user has access to the containing list and that the item still matches the `preconditions`.
+pub async fn delete_item(list_id: i32, user_id: i32, item_id: i32, preconditions: Preconditions,
+                         events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
     db.run(move |conn| {
         if !UserList::has_list_access(list_id, user_id, conn) {
             return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
         }
 
-        if Item::create_item_for_list(item, list_id, conn) {
-            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_CREATE_ITEM_SUCCESS)
+        if !Item::owned_by_list(item_id, list_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_ITEM_FAILED);
+        }
+
+        let result = change_item_if(item_id, &preconditions, conn, || {
+            if !Item::trash_item(item_id, user_id, conn) {
+                return Ok(false);
+            }
+
+            if let Some(item) = Item::find_item_by_id(item_id, conn) {
+                events.publish(ListEvent::item_deleted(&item), conn)?;
+            }
+
+            Ok(true)
+        });
+
+        match result {
+            Ok(Some(true)) => ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_ITEM_SUCCESS),
+            Ok(None) => ResponseWithStatus::with(Status::PreconditionFailed.code, constants::MESSAGE_PRECONDITION_FAILED),
+            _ => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_ITEM_FAILED),
+        }
+    }).await
+}
+
+/// Responds with every item assigned to the user with `user_id`, across all of the lists they can access.
+/// If `render_html` is set, the notes of each item are also rendered to sanitized HTML.
+pub async fn get_assigned_items(user_id: i32, render_html: bool, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let items = Item::find_items_assigned_to(user_id, conn);
+        let data = if render_html {
+            let rendered: Vec<RenderedItem> = items.into_iter().map(Item::render).collect();
+            serde_json::to_value(rendered).unwrap()
         } else {
-            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_ITEM_FAILED)
+            serde_json::to_value(items).unwrap()
+        };
+
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data,
+            },
         }
     }).await
 }
 
-/// Attempts to delete the item with the given `item_id`. First, ensures that the user has access to the containing list
-pub async fn delete_item(list_id: i32, user_id: i32, item_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+/// Attempts to mark every item in the list with `list_id` as finished or unfinished according to `finished`,
+/// in a single transaction. The user with `user_id` must have access to the list. Responds with a result for
+/// each item that changed.
+pub async fn set_all_items_finished(list_id: i32, user_id: i32, finished: FinishedDTO, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
     db.run(move |conn| {
         if !UserList::has_list_access(list_id, user_id, conn) {
             return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
         }
 
-        if Item::owned_by_list(item_id, list_id, conn)
-            && Item::delete_item(item_id, conn) {
-            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_ITEM_SUCCESS)
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let changed_items = Item::set_all_finished(list_id, finished.finished, conn)?;
+            events.publish_all(changed_items.iter().cloned().map(ListEvent::item_updated), conn)?;
+
+            Ok(changed_items)
+        });
+
+        match result {
+            Ok(changed_items) => {
+                let results = changed_items.iter()
+                    .map(|item| BatchItemResult {
+                        index: None,
+                        item_id: Some(item.uuid),
+                        success: true,
+                        message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                    })
+                    .collect();
+
+                batch_response(results)
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_BATCH_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to move every finished item in the list with `list_id` into the trash of the user with `user_id`, in a
+/// single transaction. The user must have access to the list. Responds with a result for each deleted item.
+pub async fn clear_finished_items(list_id: i32, user_id: i32, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let deleted_items = Item::trash_finished_items(list_id, user_id, conn)?;
+            events.publish_all(deleted_items.iter().map(ListEvent::item_deleted), conn)?;
+
+            Ok(deleted_items)
+        });
+
+        match result {
+            Ok(deleted_items) => {
+                batch_response(deleted_items.into_iter()
+                    .map(|item| BatchItemResult {
+                        index: None,
+                        item_id: Some(item.uuid),
+                        success: true,
+                        message: String::from(constants::MESSAGE_DELETE_ITEM_SUCCESS),
+                    })
+                    .collect())
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_BATCH_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to add each of the given `items` to the list with `list_id` in a single transaction. Items that
+/// can't be created are skipped and reported, while the rest are still added. The user with `user_id` must
+/// have access to the list. Responds with a result for each item in the request.
+pub async fn add_items_to_list(list_id: i32, user_id: i32, items: Vec<ItemDTO>, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::has_list_access(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS);
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let mut results = vec![];
+            let mut created_items = vec![];
+
+            for (index, item) in items.into_iter().enumerate() {
+                let (item_id, message) = if let Err(message) = check_item(&item) {
+                    (None, message)
+                } else if !assignee_has_access(item.assignee_id, list_id, conn) {
+                    (None, constants::MESSAGE_ASSIGNEE_NO_ACCESS)
+                } else {
+                    // Each insert gets its own savepoint, so one failure doesn't abort the whole batch
+                    match conn.transaction(|| Item::insert_item(item, list_id, conn)) {
+                        Ok(new_item) => {
+                            let item_id = new_item.uuid;
+                            created_items.push(new_item);
+                            (Some(item_id), constants::MESSAGE_CREATE_ITEM_SUCCESS)
+                        },
+                        Err(_) => (None, constants::MESSAGE_CREATE_ITEM_FAILED),
+                    }
+                };
+
+                results.push(BatchItemResult {
+                    index: Some(index),
+                    item_id,
+                    success: item_id.is_some(),
+                    message: String::from(message),
+                });
+            }
+
+            events.publish_all(ListEvent::items_created(created_items), conn)?;
+            Ok(results)
+        });
+
+        match result {
+            Ok(results) => batch_response(results),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_BATCH_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to move the item with `item_id` out of the list with `list_id` and into the destination list in
+/// `transfer`, keeping its id, comments and attachments. The user with `user_id` needs access to both lists,
+/// and the item is unassigned if its assignee can't access the destination.
+pub async fn move_item(list_id: i32, item_id: i32, user_id: i32, transfer: ItemTransferDTO,
+                       events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        let destination_id = match List::find_list_id(transfer.destination_list_id, conn) {
+            Some(destination_id) if UserList::has_list_access(destination_id, user_id, conn) => destination_id,
+            _ => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS),
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let item = Item::find_item_by_id(item_id, conn).ok_or(diesel::result::Error::NotFound)?;
+            let keep_assignee = assignee_has_access(item.assignee_id, destination_id, conn);
+            let moved_item = Item::move_to_list(item_id, destination_id, keep_assignee, conn)?;
+
+            events.publish(ListEvent::item_deleted(&item), conn)?;
+            events.publish(ListEvent::item_created(moved_item.clone()), conn)?;
+            Ok(moved_item)
+        });
+
+        match result {
+            Ok(moved_item) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_MOVE_ITEM_SUCCESS),
+                    data: serde_json::to_value(moved_item).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_MOVE_ITEM_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to copy the item with `item_id` from the list with `list_id` into the destination list in `transfer`,
+/// along with its comments, attachments and tags, in a single transaction. The user with `user_id` needs access to
+/// both lists, and becomes the uploader of the copied attachments.
+pub async fn copy_item(list_id: i32, item_id: i32, user_id: i32, transfer: ItemTransferDTO,
+                       storage: Arc<dyn Storage>, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        let destination_id = match List::find_list_id(transfer.destination_list_id, conn) {
+            Some(destination_id) if UserList::has_list_access(destination_id, user_id, conn) => destination_id,
+            _ => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS),
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let item = Item::find_item_by_id(item_id, conn).ok_or(diesel::result::Error::NotFound)?;
+            let keep_assignee = assignee_has_access(item.assignee_id, destination_id, conn);
+            let copied_item = item.copy_to_list(destination_id, keep_assignee, conn)?;
+
+            Comment::copy_comments(item_id, copied_item.item_id, conn)?;
+            Tag::copy_tags(item_id, copied_item.item_id, conn)?;
+            attachment_service::copy_attachments(item_id, copied_item.item_id, user_id, &*storage, conn)?;
+
+            events.publish(ListEvent::item_created(copied_item.clone()), conn)?;
+            Ok(copied_item)
+        });
+
+        match result {
+            Ok(copied_item) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_COPY_ITEM_SUCCESS),
+                    data: serde_json::to_value(copied_item).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_COPY_ITEM_FAILED),
+        }
+    }).await
+}
+
+/// Builds the response for a batch operation that finished with the given per-item `results`.
+fn batch_response(results: Vec<BatchItemResult>) -> ResponseWithStatus {
+    ResponseWithStatus {
+        status_code: Status::Ok.code,
+        response: Response {
+            message: String::from(constants::MESSAGE_BATCH_SUCCESS),
+            data: serde_json::to_value(results).unwrap(),
+        },
+    }
+}
+
+/// Builds the event for a change that renamed the list from `old_name`, if its name actually changed.
+pub fn renamed_event(old_name: &str, list: &List) -> Option<ListEvent> {
+    if list.name == old_name {
+        None
+    } else {
+        Some(ListEvent::list_renamed(list))
+    }
+}
+
+/// Builds the current ETag of the given list, which also covers its items.
+fn current_list_etag(list: &List, conn: &PgConnection) -> QueryResult<String> {
+    Ok(etag::list_etag(list.uuid, list.version, &Item::find_item_versions(list.list_id, conn)?))
+}
+
+/// Locks the list with `list_id` and runs `change` only if the current ETag of the list satisfies the `preconditions`,
+/// all in a single transaction. Returns `None` if the preconditions failed and nothing was changed.
+fn change_list_if<T, F>(list_id: i32, preconditions: &Preconditions, conn: &PgConnection, change: F) -> QueryResult<Option<T>>
+    where F: FnOnce() -> QueryResult<T> {
+    conn.transaction(|| {
+        let list = List::lock_list(list_id, conn)?;
+
+        if preconditions.allows_write(&current_list_etag(&list, conn)?) {
+            change().map(Some)
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+/// Locks the item with `item_id` and runs `change` only if the current ETag of the item satisfies the `preconditions`,
+/// all in a single transaction. Returns `None` if the preconditions failed and nothing was changed.
+fn change_item_if<T, F>(item_id: i32, preconditions: &Preconditions, conn: &PgConnection, change: F) -> QueryResult<Option<T>>
+    where F: FnOnce() -> QueryResult<T> {
+    conn.transaction(|| {
+        let item = Item::lock_item(item_id, conn)?;
+
+        if preconditions.allows_write(&etag::item_etag(&item)) {
+            change().map(Some)
         } else {
-            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_ITEM_FAILED)
+            Ok(None)
         }
+    })
+}
+
+/// Builds the response for a conditional get where the client already has the resource with the given `etag`.
+fn not_modified(etag: String) -> TaggedResponse {
+    TaggedResponse {
+        response: ResponseWithStatus::with(Status::NotModified.code, constants::MESSAGE_NOT_MODIFIED),
+        etag: Some(etag),
+    }
+}
+
+/// Builds the response for a change that was rejected because the resource no longer matches the client's ETag.
+fn precondition_failed() -> TaggedResponse {
+    ResponseWithStatus::with(Status::PreconditionFailed.code, constants::MESSAGE_PRECONDITION_FAILED).into()
+}
+
+/// Finds the id of the list with the public `list_id`, for a route that refers to it. Responds as if the user
+/// couldn't access the list if there is none.
+pub async fn find_list_id(list_id: Uuid, db: &PostgresDbConn) -> Result<i32, ResponseWithStatus> {
+    db.run(move |conn| List::find_list_id(list_id, conn)).await
+        .ok_or_else(|| ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS))
+}
+
+/// Finds the ids of the list with the public `list_id` and the item with the public `item_id`, for a route that
+/// refers to them. Responds as if the user couldn't access the list, or the item wasn't in it, if either is missing.
+pub async fn find_item_ids(list_id: Uuid, item_id: Uuid, db: &PostgresDbConn) -> Result<(i32, i32), ResponseWithStatus> {
+    db.run(move |conn| {
+        let list_id = List::find_list_id(list_id, conn)
+            .ok_or_else(|| ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS))?;
+        let item_id = Item::find_item_id(item_id, conn)
+            .ok_or_else(|| ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ITEM_NOT_OWNED_BY_LIST))?;
+
+        Ok((list_id, item_id))
     }).await
 }
+
+/// Makes sure that the user with `user_id` has access to the list with `list_id` and that the item with
+/// `item_id` belongs to it. Returns the failed response to send if not.
+pub fn check_item_access(list_id: i32, item_id: i32, user_id: i32, conn: &PgConnection) -> Option<ResponseWithStatus> {
+    if !UserList::has_list_access(list_id, user_id, conn) {
+        return Some(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_NO_ACCESS));
+    }
+
+    if !Item::owned_by_list(item_id, list_id, conn) {
+        return Some(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_ITEM_NOT_OWNED_BY_LIST));
+    }
+
+    None
+}
+
+/// Checks that the fields of an `item` that clients can set freely are valid, as every way of writing items does
+/// before saving one. Returns the message to respond with if they aren't.
+pub fn check_item(item: &ItemDTO) -> Result<(), &'static str> {
+    check_item_fields(Some(&item.description), item.priority.as_deref(), item.recurrence.as_deref())
+}
+
+/// Checks the fields that a `patch` changes in the same way as `check_item`.
+pub fn check_item_patch(patch: &ItemPatchDTO) -> Result<(), &'static str> {
+    check_item_fields(patch.description.as_deref(), patch.priority.as_ref().and_then(Option::as_deref),
+                      patch.recurrence.as_ref().and_then(Option::as_deref))
+}
+
+/// Checks the `description`, `priority` and `recurrence` of an item, where those that are given must be valid.
+fn check_item_fields(description: Option<&str>, priority: Option<&str>, recurrence: Option<&str>) -> Result<(), &'static str> {
+    if matches!(description, Some(description) if !is_valid_description(description)) {
+        return Err(constants::MESSAGE_INVALID_ITEM_DESCRIPTION);
+    }
+
+    if !matches!(priority, None | Some("low" | "medium" | "high")) {
+        return Err(constants::MESSAGE_INVALID_PRIORITY);
+    }
+
+    if matches!(recurrence, Some(rule) if !import::is_valid_recurrence(rule)) {
+        return Err(constants::MESSAGE_INVALID_RECURRENCE);
+    }
+
+    Ok(())
+}
+
+/// Determines whether `description` can be used as the description of an item, which must not be blank or longer
+/// than `MAX_DESCRIPTION_LENGTH`.
+pub fn is_valid_description(description: &str) -> bool {
+    !description.trim().is_empty() && description.chars().count() <= MAX_DESCRIPTION_LENGTH
+}
+
+/// Determines whether the user with `assignee_id`, if there is one, can access the list with `list_id`.
+/// Items can only be assigned to members of their list.
+pub fn assignee_has_access(assignee_id: Option<i32>, list_id: i32, conn: &PgConnection) -> bool {
+    match assignee_id {
+        Some(assignee_id) => UserList::has_list_access(list_id, assignee_id, conn),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::trash_service;
+    use crate::test_support;
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn trashing_a_list_removes_every_member() {
+        let rocket = test_support::rocket().await;
+        let pool = PostgresDbConn::pool(&rocket).cloned().unwrap();
+        let (events, mut receiver) = test_support::listening_bus(&pool).await;
+        let (list, owner, member) = pool.get().await.unwrap().run(|conn| {
+            let owner = test_support::create_user(conn);
+            let member = test_support::create_user(conn);
+            let list = test_support::create_list("Chores", owner.id, conn);
+            test_support::add_member(List::find_list_by_id(list.list_id, conn).unwrap(), member.id, false, conn);
+
+            (list, owner.id, member.id)
+        }).await;
+
+        let response = delete_list(list.list_id, owner, Preconditions::default(), events, PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        assert_eq!(response.status_code, Status::Ok.code);
+
+        let names: Vec<_> = test_support::next_events(&mut receiver, list.list_id, 3).await.iter()
+            .map(|event| match event {
+                ListEvent::MemberRemoved { user_id, .. } => format!("member_removed {}", user_id),
+                event => String::from(event.name()),
+            })
+            .collect();
+        assert_eq!(names, vec![
+            String::from("list_trashed"),
+            format!("member_removed {}", owner),
+            format!("member_removed {}", member),
+        ]);
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn restoring_a_list_adds_every_member_back() {
+        let rocket = test_support::rocket().await;
+        let pool = PostgresDbConn::pool(&rocket).cloned().unwrap();
+        let (events, mut receiver) = test_support::listening_bus(&pool).await;
+        let (list, owner, member) = pool.get().await.unwrap().run(|conn| {
+            let owner = test_support::create_user(conn);
+            let member = test_support::create_user(conn);
+            let list = test_support::create_list("Chores", owner.id, conn);
+            test_support::add_member(List::find_list_by_id(list.list_id, conn).unwrap(), member.id, false, conn);
+            assert!(List::trash_list(list.list_id, owner.id, conn));
+
+            (list, owner.id, member.id)
+        }).await;
+
+        let response = trash_service::restore_list(list.uuid, owner, events, PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        assert_eq!(response.status_code, Status::Ok.code);
+
+        let names: Vec<_> = test_support::next_events(&mut receiver, list.list_id, 3).await.iter()
+            .map(|event| match event {
+                ListEvent::MemberAdded { user_id, .. } => format!("member_added {}", user_id),
+                event => String::from(event.name()),
+            })
+            .collect();
+        assert_eq!(names, vec![
+            format!("member_added {}", owner),
+            format!("member_added {}", member),
+            String::from("list_restored"),
+        ]);
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn restoring_an_item_tells_followers_about_it_again() {
+        let rocket = test_support::rocket().await;
+        let pool = PostgresDbConn::pool(&rocket).cloned().unwrap();
+        let (events, mut receiver) = test_support::listening_bus(&pool).await;
+        let (list, item, owner) = pool.get().await.unwrap().run(|conn| {
+            let owner = test_support::create_user(conn);
+            let list = test_support::create_list("Chores", owner.id, conn);
+            let item = test_support::create_item("Water the plants", list.list_id, conn);
+            assert!(Item::trash_item(item.item_id, owner.id, conn));
+
+            (list, item, owner.id)
+        }).await;
+
+        let response = trash_service::restore_item(item.uuid, owner, events, PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        assert_eq!(response.status_code, Status::Ok.code);
+
+        match test_support::next_events(&mut receiver, list.list_id, 1).await.as_slice() {
+            [ListEvent::ItemCreated { item: restored, .. }] => {
+                assert_eq!(restored.uuid, item.uuid);
+                assert!(restored.deleted_at.is_none());
+            }
+            events => panic!("expected item_created, got {:?}", events.iter().map(ListEvent::name).collect::<Vec<_>>()),
+        }
+    }
+}
@@ -1,2 +1,16 @@
 pub mod account_service;
-pub mod list_service;
\ No newline at end of file
+pub mod list_service;
+pub mod comment_service;
+pub mod attachment_service;
+pub mod trash_service;
+pub mod search_service;
+pub mod tag_service;
+pub mod smart_list_service;
+pub mod export_service;
+pub mod import_service;
+pub mod calendar_service;
+pub mod app_password_service;
+pub mod caldav_service;
+pub mod event_service;
+pub mod webhook_service;
+pub mod sync_service;
\ No newline at end of file
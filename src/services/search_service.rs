@@ -0,0 +1,37 @@
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::models::page;
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::search::{SearchCursor, SearchResult};
+
+/// Responds with a page of the lists and items that the user with `user_id` can reach and that match the search
+/// `query`, ranked best first with the matches highlighted. `cursor` is the `next_cursor` of the previous page.
+pub async fn search(user_id: i32, query: String, limit: Option<i64>, cursor: Option<String>, db: PostgresDbConn) -> ResponseWithStatus {
+    if query.trim().is_empty() {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_EMPTY_SEARCH);
+    }
+
+    let limit = page::parse_limit(limit);
+    let after = cursor.map(|cursor| page::decode_cursor::<SearchCursor>(&cursor));
+
+    let (limit, after) = match (limit, after) {
+        (Some(limit), None) => (limit, None),
+        (Some(limit), Some(Some(after))) => (limit, Some(after)),
+        _ => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_PAGE),
+    };
+
+    db.run(move |conn| {
+        match SearchResult::search(user_id, &query, limit, after, conn) {
+            Ok(results) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(results).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_SEARCH_FAILED),
+        }
+    }).await
+}
@@ -0,0 +1,247 @@
+use chrono::Utc;
+use chrono_tz::Tz;
+use diesel::PgConnection;
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::models::filter::{self, Filter, FilterContext};
+use crate::models::item::{Item, RenderedItem};
+use crate::models::page::{PageQuery, PageRequest, SORT_CREATED, SORT_DESCRIPTION, SORT_UPDATED};
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::smart_list::{SmartList, SmartListDTO, SmartListWithItems};
+use crate::models::user::User;
+
+static TODAY_NAME: &str = "Today"; // Name of the built-in smart list of items due today
+
+/// Responds with every smart list saved by the user with `user_id`.
+pub async fn get_smart_lists(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data: serde_json::to_value(SmartList::find_smart_lists_for_user(user_id, conn)).unwrap(),
+            },
+        }
+    }).await
+}
+
+/// Attempts to save the given `smart_list` for the user with `user_id`, as long as its filter is valid.
+/// Responds with the new smart list.
+pub async fn create_smart_list(user_id: i32, smart_list: SmartListDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    let smart_list = match validate_smart_list(smart_list) {
+        Ok(smart_list) => smart_list,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        if SmartList::name_taken(user_id, &smart_list.name, None, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_SMART_LIST_EXISTS);
+        }
+
+        if let Some(result_smart_list) = SmartList::create_smart_list(smart_list, user_id, conn) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_CREATE_SMART_LIST_SUCCESS),
+                    data: serde_json::to_value(result_smart_list).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_SMART_LIST_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to replace the name and filter of the smart list with `smart_list_id`, which must belong to the
+/// user with `user_id`. Responds with the changed smart list.
+pub async fn put_smart_list(smart_list_id: i32, user_id: i32, new_smart_list: SmartListDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    let new_smart_list = match validate_smart_list(new_smart_list) {
+        Ok(new_smart_list) => new_smart_list,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        if let Err(response) = find_own_smart_list(smart_list_id, user_id, conn) {
+            return response;
+        }
+
+        if SmartList::name_taken(user_id, &new_smart_list.name, Some(smart_list_id), conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_SMART_LIST_EXISTS);
+        }
+
+        if let Some(result_smart_list) = SmartList::update_smart_list(smart_list_id, new_smart_list, conn) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                    data: serde_json::to_value(result_smart_list).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to delete the smart list with `smart_list_id`, which must belong to the user with `user_id`.
+pub async fn delete_smart_list(smart_list_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Err(response) = find_own_smart_list(smart_list_id, user_id, conn) {
+            return response;
+        }
+
+        if SmartList::delete_smart_list(smart_list_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_SMART_LIST_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_SMART_LIST_FAILED)
+        }
+    }).await
+}
+
+/// Responds with a page of the items matching the smart list with `smart_list_id`, which must belong to the
+/// user with `user_id`, across every list they can access. If `render_html` is set, the notes of each item
+/// are also rendered to sanitized HTML.
+pub async fn get_smart_list(smart_list_id: i32, user_id: i32, page: PageQuery, render_html: bool, db: PostgresDbConn) -> ResponseWithStatus {
+    let page = match parse_page(page) {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        let smart_list = match find_own_smart_list(smart_list_id, user_id, conn) {
+            Ok(smart_list) => smart_list,
+            Err(response) => return response,
+        };
+
+        // Saved filters were validated when they were saved, but may no longer parse or be valid if the format
+        // or the rules change, or if they were changed in the database
+        let filter = match serde_json::from_value::<Filter>(smart_list.filter.clone()) {
+            Ok(filter) => filter,
+            Err(error) => return invalid_filter(error.to_string()),
+        };
+
+        if let Err(reason) = filter.validate() {
+            return invalid_filter(reason);
+        }
+
+        evaluate(Some(smart_list.smart_list_id), smart_list.name, filter, user_id, page, render_html, conn)
+    }).await
+}
+
+/// Responds with a page of the built-in "Today" smart list of the user with `user_id`, which holds the
+/// unfinished items that are due today in their time zone or overdue.
+pub async fn get_today(user_id: i32, page: PageQuery, render_html: bool, db: PostgresDbConn) -> ResponseWithStatus {
+    let page = match parse_page(page) {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        evaluate(None, String::from(TODAY_NAME), filter::today(), user_id, page, render_html, conn)
+    }).await
+}
+
+/// Evaluates the `filter` of a smart list for the user with `user_id`, and responds with the requested page
+/// of matching items in the shape of a complete list.
+fn evaluate(smart_list_id: Option<i32>, name: String, filter: Filter, user_id: i32, page: PageRequest,
+            render_html: bool, conn: &PgConnection) -> ResponseWithStatus {
+    let time_zone = match User::find_user_by_id(user_id, conn) {
+        Some(user) => user.time_zone(),
+        None => Tz::UTC,
+    };
+
+    let context = FilterContext {
+        user_id,
+        time_zone,
+        now: Utc::now(),
+    };
+
+    let items = match Item::find_page(|page| Item::with_page_filters(filter.matching_items(&context), page), page, conn) {
+        Ok(items) => items,
+        Err(_) => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_SMART_LIST_FAILED),
+    };
+
+    let filter = serde_json::to_value(&filter).unwrap();
+    let data = if render_html {
+        let rendered: Vec<RenderedItem> = items.results.into_iter().map(Item::render).collect();
+        serde_json::to_value(SmartListWithItems {
+            smart_list_id,
+            name,
+            filter,
+            items: rendered,
+            next_cursor: items.next_cursor,
+            total_count: items.total_count,
+        }).unwrap()
+    } else {
+        serde_json::to_value(SmartListWithItems {
+            smart_list_id,
+            name,
+            filter,
+            items: items.results,
+            next_cursor: items.next_cursor,
+            total_count: items.total_count,
+        }).unwrap()
+    };
+
+    ResponseWithStatus {
+        status_code: Status::Ok.code,
+        response: Response {
+            message: String::from(constants::MESSAGE_OK),
+            data,
+        },
+    }
+}
+
+/// Trims the name of the given `smart_list` and checks that its filter is a valid `Filter`, which is stored
+/// in its normalized form. Returns the failed response to send if it isn't valid.
+fn validate_smart_list(smart_list: SmartListDTO) -> Result<SmartListDTO, ResponseWithStatus> {
+    let name = smart_list.name.trim();
+    if name.is_empty() {
+        return Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_SMART_LIST_NAME));
+    }
+
+    let filter = match serde_json::from_value::<Filter>(smart_list.filter) {
+        Ok(filter) => filter,
+        Err(error) => return Err(invalid_filter(error.to_string())),
+    };
+
+    if let Err(reason) = filter.validate() {
+        return Err(invalid_filter(reason));
+    }
+
+    Ok(SmartListDTO {
+        name: String::from(name),
+        filter: serde_json::to_value(filter).unwrap(),
+    })
+}
+
+/// Validates the pagination parameters for the items of a smart list. Returns the failed response to send
+/// if they aren't valid.
+fn parse_page(page: PageQuery) -> Result<PageRequest, ResponseWithStatus> {
+    match page.parse(&[SORT_CREATED, SORT_UPDATED, SORT_DESCRIPTION]) {
+        Some(page) => Ok(page),
+        None => Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_PAGE)),
+    }
+}
+
+/// Finds the smart list with `smart_list_id`, as long as it belongs to the user with `user_id`. Returns the
+/// failed response to send if not.
+fn find_own_smart_list(smart_list_id: i32, user_id: i32, conn: &PgConnection) -> Result<SmartList, ResponseWithStatus> {
+    match SmartList::find_smart_list_by_id(smart_list_id, conn) {
+        Some(smart_list) if smart_list.user_id == user_id => Ok(smart_list),
+        _ => Err(ResponseWithStatus::with(Status::NotFound.code, constants::MESSAGE_SMART_LIST_NOT_FOUND)),
+    }
+}
+
+/// Builds the response for a filter that isn't valid, giving the `reason` in the data.
+fn invalid_filter(reason: String) -> ResponseWithStatus {
+    ResponseWithStatus {
+        status_code: Status::BadRequest.code,
+        response: Response {
+            message: String::from(constants::MESSAGE_INVALID_FILTER),
+            data: serde_json::Value::String(reason),
+        },
+    }
+}
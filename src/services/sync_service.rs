@@ -0,0 +1,565 @@
+use chrono::{Duration, Utc};
+use diesel::{Connection, PgConnection, QueryResult};
+use rocket::http::Status;
+use uuid::Uuid;
+
+use crate::constants;
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::events::EventBus;
+use crate::models::event::ListEvent;
+use crate::models::item::{Item, ItemDTO, ItemPatchDTO};
+use crate::models::list::{List, ListDTO, ListPatchDTO};
+use crate::models::page;
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::sync::{ConflictPolicy, Mutation, MutationResult, ObjectRef, SyncBatchDTO, SyncBatchResult, SyncChange,
+                          SyncChanges, SyncMutation, SyncResult, SyncToken};
+use crate::models::user::User;
+use crate::models::user_lists::UserList;
+use crate::services::list_service;
+
+static MAX_MUTATIONS: usize = 500; // Most changes that a client can send in a single sync
+static MAX_CLIENT_ID_LENGTH: usize = 100; // Longest id that a client can give a change, in characters
+static HISTORY_RETENTION_DAYS: i64 = 90; // Days that deletions and synced changes are remembered, and that tokens last
+static PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60); // Check for expired history hourly
+
+/// The version of a list or item that a change was made to, if the client gave one, and what to do if it's no
+/// longer the current version.
+struct BaseVersion {
+    version: Option<i32>,
+    policy: ConflictPolicy,
+}
+
+/// What came of applying a single change, with the public ids of the list and item that it was made to. Anything but
+/// an applied change is rolled back.
+enum Outcome {
+    Applied { list_id: Uuid, item_id: Option<Uuid> },
+    Conflict { list_id: Uuid, item_id: Option<Uuid>, current: serde_json::Value },
+    Rejected(String),
+}
+
+/// Responds with everything in the lists that the user with `user_id` can access that changed since the sync that
+/// gave them the `since` token, including what was deleted, along with the token for their next sync. Without a
+/// token, responds with everything that they can access.
+pub async fn get_changes(user_id: i32, since: Option<String>, db: PostgresDbConn) -> ResponseWithStatus {
+    let since = match parse_token(since.as_deref()) {
+        Ok(since) => since,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        match find_changes(user_id, since, conn) {
+            Ok(changes) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(changes).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_SYNC_FAILED),
+        }
+    }).await
+}
+
+/// Applies each of the changes that the user with `user_id` made offline, in order, resolving conflicts with the
+/// policy of the `batch`. Each change is applied on its own, so that one being rejected doesn't stop the rest, and
+/// a change that was already synced isn't applied again. Responds with the outcome of each change, followed by
+/// everything that changed since the token in the `batch`, as with `get_changes`.
+pub async fn push_changes(user_id: i32, batch: SyncBatchDTO, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    let since = match parse_token(batch.since.as_deref()) {
+        Ok(since) => since,
+        Err(response) => return response,
+    };
+
+    if batch.mutations.len() > MAX_MUTATIONS {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_TOO_MANY_MUTATIONS);
+    }
+
+    db.run(move |conn| {
+        let policy = batch.conflict_policy;
+        let results = batch.mutations.into_iter()
+            .map(|mutation| apply_mutation(user_id, mutation, policy, &events, conn))
+            .collect();
+
+        match find_changes(user_id, since, conn) {
+            Ok(changes) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(SyncBatchResult { results, changes }).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_SYNC_FAILED),
+        }
+    }).await
+}
+
+/// Periodically and permanently forgets the deletions and synced changes older than any token that can still be used.
+pub async fn purge_expired_history(pool: PostgresPool) {
+    let mut interval = rocket::tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Some(conn) = pool.get().await {
+            let _ = conn.run(|conn| purge_history_older_than(Duration::days(HISTORY_RETENTION_DAYS), conn)).await;
+        }
+    }
+}
+
+/// Permanently forgets the deletions and synced changes from more than `retention` ago. Returns the number of rows
+/// deleted.
+fn purge_history_older_than(retention: Duration, conn: &PgConnection) -> QueryResult<usize> {
+    let cutoff = Utc::now() - retention;
+
+    conn.transaction(|| {
+        let tombstones = SyncChange::purge_tombstones(cutoff, conn)?;
+        let mutations = SyncMutation::purge_mutations(cutoff, conn)?;
+
+        Ok(tombstones + mutations)
+    })
+}
+
+/// Decodes the transaction to sync from out of the given `token`, if there is one. Returns the failed response to
+/// send if the token isn't valid, or is too old for the deletions since then to still be known.
+fn parse_token(token: Option<&str>) -> Result<Option<i64>, ResponseWithStatus> {
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+
+    match page::decode_cursor::<SyncToken>(token) {
+        Some(token) if token.issued_at < Utc::now() - Duration::days(HISTORY_RETENTION_DAYS) => {
+            Err(ResponseWithStatus::with(Status::Gone.code, constants::MESSAGE_SYNC_TOKEN_EXPIRED))
+        },
+        Some(token) => Ok(Some(token.xid)),
+        None => Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_SYNC_TOKEN)),
+    }
+}
+
+/// Finds everything that changed for the user with `user_id` since the transaction `since`, from a single snapshot
+/// of the database, and makes the token for their next sync.
+fn find_changes(user_id: i32, since: Option<i64>, conn: &PgConnection) -> QueryResult<SyncResult> {
+    let changes = conn.build_transaction().repeatable_read().read_only().run(|| {
+        SyncChanges::find_for_user(user_id, since, conn)
+    })?;
+
+    let token = SyncToken {
+        xid: changes.xid,
+        issued_at: Utc::now(),
+    };
+
+    Ok(SyncResult {
+        lists: changes.lists,
+        items: changes.items,
+        memberships: changes.memberships,
+        deleted: changes.deleted,
+        token: page::encode_cursor(&token),
+    })
+}
+
+/// Applies a single change made offline by the user with `user_id` in its own transaction, recording it as synced
+/// if it was applied. A change that was already synced is reported as applied again without changing anything.
+fn apply_mutation(user_id: i32, mutation: Mutation, policy: ConflictPolicy, events: &EventBus, conn: &PgConnection) -> MutationResult {
+    let client_id = String::from(mutation.client_id());
+
+    if client_id.is_empty() || client_id.chars().count() > MAX_CLIENT_ID_LENGTH {
+        return mutation_result(client_id, Outcome::Rejected(String::from(constants::MESSAGE_INVALID_CLIENT_ID)));
+    }
+
+    if let Some(synced) = SyncMutation::find_mutation(user_id, &client_id, conn) {
+        return mutation_result(client_id, Outcome::Applied { list_id: synced.list_uuid, item_id: synced.item_uuid });
+    }
+
+    let mut not_applied = None;
+    let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        match apply(user_id, mutation, policy, events, conn)? {
+            Outcome::Applied { list_id, item_id } => {
+                SyncMutation::record_mutation(user_id, &client_id, list_id, item_id, conn)?;
+                Ok(Outcome::Applied { list_id, item_id })
+            },
+            outcome => {
+                not_applied = Some(outcome);
+                Err(diesel::result::Error::RollbackTransaction)
+            },
+        }
+    });
+
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(_) => not_applied.unwrap_or_else(|| Outcome::Rejected(String::from(constants::MESSAGE_SYNC_FAILED))),
+    };
+
+    mutation_result(client_id, outcome)
+}
+
+/// Applies a single change made offline by the user with `user_id`, with the same checks as making it online.
+fn apply(user_id: i32, mutation: Mutation, policy: ConflictPolicy, events: &EventBus, conn: &PgConnection) -> QueryResult<Outcome> {
+    match mutation {
+        Mutation::CreateList { list, .. } => create_list(user_id, list, events, conn),
+        Mutation::UpdateList { list_id, base_version, changes, .. } => {
+            match resolve_list(user_id, list_id, conn) {
+                Ok(list_id) => update_list(list_id, user_id, BaseVersion { version: base_version, policy }, changes, events, conn),
+                Err(message) => Ok(Outcome::Rejected(String::from(message))),
+            }
+        },
+        Mutation::DeleteList { list_id, base_version, .. } => {
+            match resolve_list(user_id, list_id, conn) {
+                Ok(list_id) => delete_list(list_id, user_id, BaseVersion { version: base_version, policy }, events, conn),
+                Err(message) => Ok(Outcome::Rejected(String::from(message))),
+            }
+        },
+        Mutation::CreateItem { list_id, item, .. } => {
+            match resolve_list(user_id, list_id, conn) {
+                Ok(list_id) => create_item(list_id, user_id, item, events, conn),
+                Err(message) => Ok(Outcome::Rejected(String::from(message))),
+            }
+        },
+        Mutation::UpdateItem { list_id, item_id, base_version, changes, .. } => {
+            match (resolve_list(user_id, list_id, conn), resolve_item(user_id, item_id, conn)) {
+                (Ok(list_id), Ok(item_id)) => {
+                    update_item(list_id, item_id, user_id, BaseVersion { version: base_version, policy }, changes, events, conn)
+                },
+                (Err(message), _) | (_, Err(message)) => Ok(Outcome::Rejected(String::from(message))),
+            }
+        },
+        Mutation::DeleteItem { list_id, item_id, base_version, .. } => {
+            match (resolve_list(user_id, list_id, conn), resolve_item(user_id, item_id, conn)) {
+                (Ok(list_id), Ok(item_id)) => {
+                    delete_item(list_id, item_id, user_id, BaseVersion { version: base_version, policy }, events, conn)
+                },
+                (Err(message), _) | (_, Err(message)) => Ok(Outcome::Rejected(String::from(message))),
+            }
+        },
+    }
+}
+
+/// Creates the list and makes the user with `user_id` its owner.
+fn create_list(user_id: i32, list: ListDTO, events: &EventBus, conn: &PgConnection) -> QueryResult<Outcome> {
+    let user = match User::find_user_by_id(user_id, conn) {
+        Some(user) => user,
+        None => return Ok(Outcome::Rejected(String::from(constants::MESSAGE_USER_NOT_FOUND))),
+    };
+
+    let list = match List::create_list(list, conn) {
+        Some(list) => list,
+        None => return Ok(Outcome::Rejected(String::from(constants::MESSAGE_CREATE_LIST_FAILED))),
+    };
+
+    let list_id = list.uuid;
+    let added = ListEvent::member_added(&list, user_id);
+    if !UserList::associate_list(list, user, true, conn) {
+        return Ok(Outcome::Rejected(String::from(constants::MESSAGE_CREATE_LIST_ASSOCIATE_FAIL)));
+    }
+
+    events.publish(added, conn)?;
+    Ok(Outcome::Applied { list_id, item_id: None })
+}
+
+/// Applies the `changes` to the list with `list_id`, as long as the user with `user_id` has access to it.
+fn update_list(list_id: i32, user_id: i32, base: BaseVersion, changes: ListPatchDTO, events: &EventBus,
+               conn: &PgConnection) -> QueryResult<Outcome> {
+    if !UserList::has_list_access(list_id, user_id, conn) {
+        return Ok(Outcome::Rejected(String::from(constants::MESSAGE_NO_ACCESS)));
+    }
+
+    let list = List::lock_list(list_id, conn)?;
+    if base.conflicts_with(list.version) {
+        return Ok(Outcome::Conflict { list_id: list.uuid, item_id: None, current: serde_json::to_value(list).unwrap() });
+    }
+
+    match List::patch_list(list_id, changes, conn) {
+        Ok(updated) => {
+            if let Some(event) = list_service::renamed_event(&list.name, &updated) {
+                events.publish(event, conn)?;
+            }
+
+            Ok(Outcome::Applied { list_id: list.uuid, item_id: None })
+        },
+        Err(_) => Ok(Outcome::Rejected(String::from(constants::MESSAGE_UPDATE_FAILED))),
+    }
+}
+
+/// Moves the list with `list_id` into the trash of the user with `user_id`, as long as they own it.
+fn delete_list(list_id: i32, user_id: i32, base: BaseVersion, events: &EventBus, conn: &PgConnection) -> QueryResult<Outcome> {
+    if !UserList::is_list_owner(list_id, user_id, conn) {
+        return Ok(Outcome::Rejected(String::from(constants::MESSAGE_DELETE_NOT_OWNER)));
+    }
+
+    let list = List::lock_list(list_id, conn)?;
+    if base.conflicts_with(list.version) {
+        return Ok(Outcome::Conflict { list_id: list.uuid, item_id: None, current: serde_json::to_value(list).unwrap() });
+    }
+
+    let member_ids = UserList::find_member_ids(list_id, conn)?;
+    if List::trash_list(list_id, user_id, conn) {
+        events.publish_all(ListEvent::list_and_members_trashed(&list, member_ids), conn)?;
+        Ok(Outcome::Applied { list_id: list.uuid, item_id: None })
+    } else {
+        Ok(Outcome::Rejected(String::from(constants::MESSAGE_DELETE_LIST_FAILED)))
+    }
+}
+
+/// Adds the `item` to the list with `list_id`, as long as the user with `user_id` has access to it.
+fn create_item(list_id: i32, user_id: i32, item: ItemDTO, events: &EventBus, conn: &PgConnection) -> QueryResult<Outcome> {
+    if !UserList::has_list_access(list_id, user_id, conn) {
+        return Ok(Outcome::Rejected(String::from(constants::MESSAGE_NO_ACCESS)));
+    }
+
+    if let Err(message) = list_service::check_item(&item) {
+        return Ok(Outcome::Rejected(String::from(message)));
+    }
+
+    if !list_service::assignee_has_access(item.assignee_id, list_id, conn) {
+        return Ok(Outcome::Rejected(String::from(constants::MESSAGE_ASSIGNEE_NO_ACCESS)));
+    }
+
+    match Item::insert_item(item, list_id, conn) {
+        Ok(item) => {
+            let (list_id, item_id) = (item.list_uuid, item.uuid);
+            events.publish(ListEvent::item_created(item), conn)?;
+
+            Ok(Outcome::Applied { list_id, item_id: Some(item_id) })
+        },
+        Err(_) => Ok(Outcome::Rejected(String::from(constants::MESSAGE_CREATE_ITEM_FAILED))),
+    }
+}
+
+/// Applies the `changes` to the item with `item_id`, as long as it's in the list with `list_id` that the user with
+/// `user_id` has access to.
+fn update_item(list_id: i32, item_id: i32, user_id: i32, base: BaseVersion, changes: ItemPatchDTO, events: &EventBus,
+               conn: &PgConnection) -> QueryResult<Outcome> {
+    if let Some(response) = list_service::check_item_access(list_id, item_id, user_id, conn) {
+        return Ok(Outcome::Rejected(response.response.message));
+    }
+
+    if let Err(message) = list_service::check_item_patch(&changes) {
+        return Ok(Outcome::Rejected(String::from(message)));
+    }
+
+    if let Some(assignee_id) = changes.assignee_id {
+        if !list_service::assignee_has_access(assignee_id, list_id, conn) {
+            return Ok(Outcome::Rejected(String::from(constants::MESSAGE_ASSIGNEE_NO_ACCESS)));
+        }
+    }
+
+    let item = Item::lock_item(item_id, conn)?;
+    if base.conflicts_with(item.version) {
+        return Ok(Outcome::Conflict { list_id: item.list_uuid, item_id: Some(item.uuid), current: serde_json::to_value(item).unwrap() });
+    }
+
+    match Item::patch_item(item_id, changes, conn) {
+        Ok(item) => {
+            let (list_id, item_id) = (item.list_uuid, item.uuid);
+            events.publish(ListEvent::item_updated(item), conn)?;
+
+            Ok(Outcome::Applied { list_id, item_id: Some(item_id) })
+        },
+        Err(_) => Ok(Outcome::Rejected(String::from(constants::MESSAGE_UPDATE_FAILED))),
+    }
+}
+
+/// Moves the item with `item_id` into the trash of the user with `user_id`, as long as it's in the list with
+/// `list_id` that they have access to.
+fn delete_item(list_id: i32, item_id: i32, user_id: i32, base: BaseVersion, events: &EventBus, conn: &PgConnection) -> QueryResult<Outcome> {
+    if let Some(response) = list_service::check_item_access(list_id, item_id, user_id, conn) {
+        return Ok(Outcome::Rejected(response.response.message));
+    }
+
+    let item = Item::lock_item(item_id, conn)?;
+    if base.conflicts_with(item.version) {
+        return Ok(Outcome::Conflict { list_id: item.list_uuid, item_id: Some(item.uuid), current: serde_json::to_value(item).unwrap() });
+    }
+
+    if Item::trash_item(item_id, user_id, conn) {
+        events.publish(ListEvent::item_deleted(&item), conn)?;
+        Ok(Outcome::Applied { list_id: item.list_uuid, item_id: Some(item.uuid) })
+    } else {
+        Ok(Outcome::Rejected(String::from(constants::MESSAGE_DELETE_ITEM_FAILED)))
+    }
+}
+
+impl BaseVersion {
+    /// Determines whether the change conflicts with the current `version` on the server, and should be turned
+    /// down under the policy.
+    fn conflicts_with(&self, version: i32) -> bool {
+        self.policy == ConflictPolicy::ServerWins && matches!(self.version, Some(base_version) if base_version != version)
+    }
+}
+
+/// Finds the id of the list that `list` refers to, which may be the list created or changed by a change that the
+/// user with `user_id` already synced. Returns the reason to reject the change with if there's no such list.
+fn resolve_list(user_id: i32, list: ObjectRef, conn: &PgConnection) -> Result<i32, &'static str> {
+    let list_id = match list {
+        ObjectRef::Id(list_id) => list_id,
+        ObjectRef::ClientId(client_id) => match SyncMutation::find_mutation(user_id, &client_id, conn) {
+            Some(synced) => synced.list_uuid,
+            None => return Err(constants::MESSAGE_UNKNOWN_CLIENT_ID),
+        },
+    };
+
+    List::find_list_id(list_id, conn).ok_or(constants::MESSAGE_NO_ACCESS)
+}
+
+/// Finds the id of the item that `item` refers to, which may be the item created or changed by a change that the
+/// user with `user_id` already synced. Returns the reason to reject the change with if there's no such item.
+fn resolve_item(user_id: i32, item: ObjectRef, conn: &PgConnection) -> Result<i32, &'static str> {
+    let item_id = match item {
+        ObjectRef::Id(item_id) => item_id,
+        ObjectRef::ClientId(client_id) => match SyncMutation::find_mutation(user_id, &client_id, conn).and_then(|synced| synced.item_uuid) {
+            Some(item_id) => item_id,
+            None => return Err(constants::MESSAGE_UNKNOWN_CLIENT_ID),
+        },
+    };
+
+    Item::find_item_id(item_id, conn).ok_or(constants::MESSAGE_ITEM_NOT_OWNED_BY_LIST)
+}
+
+/// Builds the result to report for the change with `client_id`.
+fn mutation_result(client_id: String, outcome: Outcome) -> MutationResult {
+    match outcome {
+        Outcome::Applied { list_id, item_id } => MutationResult {
+            client_id,
+            status: "applied",
+            list_id: Some(list_id),
+            item_id,
+            message: None,
+            current: None,
+        },
+        Outcome::Conflict { list_id, item_id, current } => MutationResult {
+            client_id,
+            status: "conflict",
+            list_id: Some(list_id),
+            item_id,
+            message: Some(String::from(constants::MESSAGE_SYNC_CONFLICT)),
+            current: Some(current),
+        },
+        Outcome::Rejected(message) => MutationResult {
+            client_id,
+            status: "rejected",
+            list_id: None,
+            item_id: None,
+            message: Some(message),
+            current: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support;
+
+    /// Sends the `mutations` as a batch made by the user with `user_id`, and gets the data of the response.
+    async fn push(user_id: i32, policy: &str, mutations: serde_json::Value, db: PostgresDbConn) -> serde_json::Value {
+        let batch: SyncBatchDTO = serde_json::from_value(json!({
+            "conflict_policy": policy,
+            "mutations": mutations,
+        })).unwrap();
+
+        let response = push_changes(user_id, batch, EventBus::new(16), db).await;
+        assert_eq!(response.status_code, Status::Ok.code);
+        response.response.data
+    }
+
+    #[test]
+    fn tokens_older_than_the_history_are_gone() {
+        let token = page::encode_cursor(&SyncToken {
+            xid: 1,
+            issued_at: Utc::now() - Duration::days(HISTORY_RETENTION_DAYS + 1),
+        });
+
+        match parse_token(Some(&token)) {
+            Err(response) => assert_eq!(response.status_code, Status::Gone.code),
+            Ok(_) => panic!("an expired token was accepted"),
+        }
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn resent_batches_arent_applied_twice() {
+        let rocket = test_support::rocket().await;
+        let user = PostgresDbConn::get_one(&rocket).await.unwrap().run(|conn| test_support::create_user(conn).id).await;
+        let mutations = json!([
+            { "op": "create_list", "client_id": "list-1", "list": { "name": "Groceries", "description": null } },
+            { "op": "create_item", "client_id": "item-1", "list_id": "list-1", "item": {
+                "description": "Milk", "finished": false, "notes": null, "assignee_id": null, "due_at": null,
+                "priority": null, "recurrence": null,
+            } },
+        ]);
+
+        let first = push(user, "server_wins", mutations.clone(), PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        let resent = push(user, "server_wins", mutations, PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+
+        assert_eq!(first["results"][0]["status"], "applied");
+        assert_eq!(first["results"][1]["status"], "applied");
+        assert_eq!(resent["results"], first["results"]);
+        assert_eq!(resent["lists"].as_array().unwrap().len(), 1);
+        assert_eq!(resent["items"].as_array().unwrap().len(), 1);
+
+        // A later batch can still refer to what the first one created by its client id
+        let renamed = push(user, "server_wins", json!([
+            { "op": "update_item", "client_id": "item-2", "list_id": "list-1", "item_id": "item-1",
+              "changes": { "description": "Oat milk" } },
+        ]), PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+
+        assert_eq!(renamed["results"][0]["status"], "applied");
+        assert_eq!(renamed["results"][0]["item_id"], first["results"][1]["item_id"]);
+        assert_eq!(renamed["items"][0]["description"], "Oat milk");
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn stale_changes_conflict_unless_the_client_wins() {
+        let rocket = test_support::rocket().await;
+        let (user, list, item) = PostgresDbConn::get_one(&rocket).await.unwrap().run(|conn| {
+            let user = test_support::create_user(conn);
+            let list = test_support::create_list("Chores", user.id, conn);
+            let item = test_support::create_item("Water the plants", list.list_id, conn);
+
+            (user.id, list, item)
+        }).await;
+        let change = |client_id: &str| json!([
+            { "op": "update_item", "client_id": client_id, "list_id": list.uuid, "item_id": item.uuid,
+              "base_version": item.version - 1, "changes": { "description": "Water the cactus" } },
+        ]);
+
+        let conflict = push(user, "server_wins", change("stale-1"), PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        assert_eq!(conflict["results"][0]["status"], "conflict");
+        assert_eq!(conflict["results"][0]["current"]["description"], "Water the plants");
+        assert_eq!(conflict["results"][0]["current"]["version"], item.version);
+
+        let applied = push(user, "client_wins", change("stale-2"), PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        assert_eq!(applied["results"][0]["status"], "applied");
+        assert_eq!(applied["items"][0]["description"], "Water the cactus");
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn trashed_items_come_back_as_tombstones() {
+        let rocket = test_support::rocket().await;
+        let (user, list, item) = PostgresDbConn::get_one(&rocket).await.unwrap().run(|conn| {
+            let user = test_support::create_user(conn);
+            let list = test_support::create_list("Chores", user.id, conn);
+            let item = test_support::create_item("Water the plants", list.list_id, conn);
+
+            (user.id, list, item)
+        }).await;
+
+        let synced = get_changes(user, None, PostgresDbConn::get_one(&rocket).await.unwrap()).await;
+        assert_eq!(synced.status_code, Status::Ok.code);
+        let token = String::from(synced.response.data["token"].as_str().unwrap());
+
+        let item_id = item.item_id;
+        PostgresDbConn::get_one(&rocket).await.unwrap().run(move |conn| {
+            assert!(Item::trash_item(item_id, user, conn));
+        }).await;
+
+        let changes = get_changes(user, Some(token), PostgresDbConn::get_one(&rocket).await.unwrap()).await.response.data;
+        assert_eq!(changes["items"], json!([]));
+        assert_eq!(changes["deleted"], json!([
+            { "type": "item", "id": item.uuid, "user_id": null, "list_id": list.uuid },
+        ]));
+    }
+}
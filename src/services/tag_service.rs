@@ -0,0 +1,226 @@
+use diesel::PgConnection;
+use rocket::http::Status;
+
+use crate::constants;
+use crate::database::PostgresDbConn;
+use crate::models::item::{Item, RenderedItem};
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::tag::{Tag, TagDTO};
+use crate::services::list_service::check_item_access;
+
+static MAX_TAG_NAME_LENGTH: usize = 50; // Longest tag name, in characters
+
+/// Responds with every tag of the user with `user_id`.
+pub async fn get_tags(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data: serde_json::to_value(Tag::find_tags_for_user(user_id, conn)).unwrap(),
+            },
+        }
+    }).await
+}
+
+/// Attempts to create the given `tag` for the user with `user_id`. Responds with the new tag.
+pub async fn create_tag(user_id: i32, tag: TagDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    let tag = match validate_tag(tag) {
+        Ok(tag) => tag,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        if Tag::find_tag_by_name(user_id, &tag.name, conn).is_some() {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_TAG_EXISTS);
+        }
+
+        if let Some(result_tag) = Tag::create_tag(tag, user_id, conn) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_CREATE_TAG_SUCCESS),
+                    data: serde_json::to_value(result_tag).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_TAG_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to rename and recolor the tag of the user with `user_id` called `name` to the values in `new_tag`.
+/// Responds with the changed tag.
+pub async fn put_tag(user_id: i32, name: String, new_tag: TagDTO, db: PostgresDbConn) -> ResponseWithStatus {
+    let new_tag = match validate_tag(new_tag) {
+        Ok(new_tag) => new_tag,
+        Err(response) => return response,
+    };
+
+    db.run(move |conn| {
+        let tag = match find_tag(user_id, &name, conn) {
+            Ok(tag) => tag,
+            Err(response) => return response,
+        };
+
+        if new_tag.name != tag.name && Tag::find_tag_by_name(user_id, &new_tag.name, conn).is_some() {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_TAG_EXISTS);
+        }
+
+        if let Some(result_tag) = Tag::update_tag(tag.tag_id, new_tag, conn) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                    data: serde_json::to_value(result_tag).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to delete the tag of the user with `user_id` called `name`, taking it off every item.
+pub async fn delete_tag(user_id: i32, name: String, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let tag = match find_tag(user_id, &name, conn) {
+            Ok(tag) => tag,
+            Err(response) => return response,
+        };
+
+        if Tag::delete_tag(tag.tag_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_TAG_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_TAG_FAILED)
+        }
+    }).await
+}
+
+/// Responds with every item with the tag of the user with `user_id` called `name`, across all of the lists
+/// they can access. If `render_html` is set, the notes of each item are also rendered to sanitized HTML.
+pub async fn get_tagged_items(user_id: i32, name: String, render_html: bool, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let tag = match find_tag(user_id, &name, conn) {
+            Ok(tag) => tag,
+            Err(response) => return response,
+        };
+
+        let items = Tag::find_items_with_tag(tag.tag_id, user_id, conn);
+        let data = if render_html {
+            let rendered: Vec<RenderedItem> = items.into_iter().map(Item::render).collect();
+            serde_json::to_value(rendered).unwrap()
+        } else {
+            serde_json::to_value(items).unwrap()
+        };
+
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data,
+            },
+        }
+    }).await
+}
+
+/// Responds with the tags that the user with `user_id` put on the item with `item_id`, as long as that item
+/// is in the list with `list_id` and the user has access to that list.
+pub async fn get_item_tags(list_id: i32, item_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+            return response;
+        }
+
+        ResponseWithStatus {
+            status_code: Status::Ok.code,
+            response: Response {
+                message: String::from(constants::MESSAGE_OK),
+                data: serde_json::to_value(Tag::find_tags_for_item(item_id, user_id, conn)).unwrap(),
+            },
+        }
+    }).await
+}
+
+/// Attempts to put the tag of the user with `user_id` called `name` on the item with `item_id`, as long as
+/// that item is in the list with `list_id` and the user has access to that list.
+pub async fn tag_item(list_id: i32, item_id: i32, user_id: i32, name: String, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let tag = match find_tag_for_item(list_id, item_id, user_id, &name, conn) {
+            Ok(tag) => tag,
+            Err(response) => return response,
+        };
+
+        if Tag::tag_item(tag.tag_id, item_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_TAG_ITEM_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_TAG_ITEM_FAILED)
+        }
+    }).await
+}
+
+/// Attempts to take the tag of the user with `user_id` called `name` off the item with `item_id`, as long as
+/// that item is in the list with `list_id` and the user has access to that list.
+pub async fn untag_item(list_id: i32, item_id: i32, user_id: i32, name: String, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let tag = match find_tag_for_item(list_id, item_id, user_id, &name, conn) {
+            Ok(tag) => tag,
+            Err(response) => return response,
+        };
+
+        if Tag::untag_item(tag.tag_id, item_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_UNTAG_ITEM_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_TAG_ITEM_FAILED)
+        }
+    }).await
+}
+
+/// Trims the name of the given `tag` and checks that it's a valid tag. Names can't be empty, longer than
+/// `MAX_TAG_NAME_LENGTH` or contain a `/`, since they are used in paths, and colors are given as `#rrggbb`.
+/// Returns the failed response to send if it isn't valid.
+fn validate_tag(tag: TagDTO) -> Result<TagDTO, ResponseWithStatus> {
+    let name = tag.name.trim();
+    if !is_valid_tag_name(name) {
+        return Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_TAG_NAME));
+    }
+
+    if let Some(color) = &tag.color {
+        let valid_color = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+        if !valid_color {
+            return Err(ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_TAG_COLOR));
+        }
+    }
+
+    Ok(TagDTO {
+        name: String::from(name),
+        color: tag.color,
+    })
+}
+
+/// Determines whether `name` can be used as the name of a tag.
+pub fn is_valid_tag_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().count() <= MAX_TAG_NAME_LENGTH && !name.contains('/')
+}
+
+/// Finds the tag of the user with `user_id` called `name`. Returns the failed response to send if they don't
+/// have one.
+fn find_tag(user_id: i32, name: &str, conn: &PgConnection) -> Result<Tag, ResponseWithStatus> {
+    match Tag::find_tag_by_name(user_id, name, conn) {
+        Some(tag) => Ok(tag),
+        None => Err(ResponseWithStatus::with(Status::NotFound.code, constants::MESSAGE_TAG_NOT_FOUND)),
+    }
+}
+
+/// Finds the tag of the user with `user_id` called `name` after checking that they can access the item with
+/// `item_id` in the list with `list_id`. Returns the failed response to send if not.
+fn find_tag_for_item(list_id: i32, item_id: i32, user_id: i32, name: &str, conn: &PgConnection) -> Result<Tag, ResponseWithStatus> {
+    if let Some(response) = check_item_access(list_id, item_id, user_id, conn) {
+        return Err(response);
+    }
+
+    find_tag(user_id, name, conn)
+}
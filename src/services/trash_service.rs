@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::{Connection, PgConnection, QueryResult};
+use rocket::http::Status;
+use uuid::Uuid;
+
+use crate::constants;
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::events::EventBus;
+use crate::models::event::ListEvent;
+use crate::models::item::Item;
+use crate::models::list::List;
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::trash::Trash;
+use crate::models::user_lists::UserList;
+
+static PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60); // Check for expired trash hourly
+
+/// Responds with every list and item in the trash of the user with `user_id`.
+pub async fn get_trash(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let lists = List::find_trashed_lists_for_user(user_id, conn);
+        let items = Item::find_trashed_items_for_user(user_id, conn);
+
+        if let (Ok(lists), Ok(items)) = (lists, items) {
+            ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(Trash::new(lists, items)).unwrap(),
+                },
+            }
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_USER_NOT_FOUND)
+        }
+    }).await
+}
+
+/// Attempts to take the list with the public `list_id` out of the trash of the user with `user_id`, making it and its
+/// items visible to every member again. Every member is told that they have access to the list again. Responds with
+/// the restored list.
+pub async fn restore_list(list_id: Uuid, user_id: i32, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let restored = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let list_id = List::find_list_id(list_id, conn).ok_or(diesel::result::Error::NotFound)?;
+            let list = List::restore_list(list_id, user_id, conn)?;
+            let member_ids = UserList::find_member_ids(list_id, conn)?;
+
+            events.publish_all(ListEvent::list_and_members_restored(&list, member_ids), conn)?;
+            Ok(list)
+        });
+
+        match restored {
+            Ok(list) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_RESTORE_SUCCESS),
+                    data: serde_json::to_value(list).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_RESTORE_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to take the item with the public `item_id` out of the trash of the user with `user_id`. The user must
+/// still have access to the list containing the item, so a trashed list has to be restored before its items.
+/// Followers of the list are told about the item as if it were new. Responds with the restored item.
+pub async fn restore_item(item_id: Uuid, user_id: i32, events: EventBus, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let item = match Item::find_item_id(item_id, conn).and_then(|item_id| Item::find_item_by_id(item_id, conn)) {
+            Some(item) if item.deleted_at.is_some() && item.deleted_by == Some(user_id) => item,
+            _ => return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_RESTORE_FAILED),
+        };
+
+        if !UserList::has_list_access(item.list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_RESTORE_LIST_FIRST);
+        }
+
+        let restored = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let item = Item::restore_item(item.item_id, user_id, conn)?;
+
+            events.publish(ListEvent::item_created(item.clone()), conn)?;
+            Ok(item)
+        });
+
+        match restored {
+            Ok(item) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_RESTORE_SUCCESS),
+                    data: serde_json::to_value(item).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_RESTORE_FAILED),
+        }
+    }).await
+}
+
+/// Permanently deletes every list and item in the trash of the user with `user_id`, in a single transaction.
+pub async fn empty_trash(user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            Item::delete_trashed_items_for_user(user_id, conn)?;
+            List::delete_trashed_lists_for_user(user_id, conn)
+        });
+
+        match result {
+            Ok(_) => ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_EMPTY_TRASH_SUCCESS),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_EMPTY_TRASH_FAILED),
+        }
+    }).await
+}
+
+/// Periodically and permanently deletes the lists and items that have been in the trash for longer than `retention`.
+pub async fn purge_expired_trash(retention: chrono::Duration, pool: PostgresPool) {
+    let mut interval = rocket::tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Some(conn) = pool.get().await {
+            let _ = conn.run(move |conn| purge_trash_older_than(retention, conn)).await;
+        }
+    }
+}
+
+/// Permanently deletes everything moved to the trash more than `retention` ago. Returns the number of lists and
+/// items deleted.
+fn purge_trash_older_than(retention: chrono::Duration, conn: &PgConnection) -> QueryResult<usize> {
+    let cutoff = Utc::now() - retention;
+
+    conn.transaction(|| {
+        let items = Item::purge_trashed_items(cutoff, conn)?;
+        let lists = List::purge_trashed_lists(cutoff, conn)?;
+
+        Ok(items + lists)
+    })
+}
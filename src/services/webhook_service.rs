@@ -0,0 +1,507 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::{Connection, PgConnection, QueryResult};
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use rocket::futures::{stream, StreamExt};
+use rocket::http::Status;
+use rocket::tokio::time::{self, Instant};
+use sha2::Sha256;
+
+use crate::addresses::AddressPolicy;
+use crate::constants;
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::models::response::{Response, ResponseWithStatus};
+use crate::models::user_lists::UserList;
+use crate::models::webhook::{EVENT_ITEM_FINISHED, PendingDelivery, Webhook, WebhookDTO, WebhookDelivery, WebhookPatchDTO};
+
+static DELIVERY_INTERVAL: Duration = Duration::from_secs(5); // Check for due deliveries every few seconds
+static DELIVERY_TIMEOUT: Duration = Duration::from_secs(10); // Longest a webhook can take to respond
+static DELIVERY_BATCH_SIZE: i64 = 50; // Most deliveries claimed at once by a single instance
+static DELIVERY_DEADLINE: Duration = Duration::from_secs(30); // Longest a claimed batch can take to send, within its lease
+static DELIVERY_LEASE_SECONDS: i64 = 60; // Time that a claimed delivery is kept from other instances
+static MAX_ATTEMPTS: i32 = 8; // Attempts at a delivery before giving up on it
+static RETRY_BASE_SECONDS: i64 = 30; // Wait before the first retry, which doubles with each attempt
+static MAX_CONSECUTIVE_FAILURES: i32 = 20; // Failed attempts in a row before a webhook is disabled
+static DELIVERY_LOG_SIZE: i64 = 100; // Latest deliveries shown in the log of a webhook
+static MAX_ERROR_LENGTH: usize = 500; // Longest error kept for a failed attempt, in characters
+
+/// The types of events that a webhook can ask for.
+static EVENT_TYPES: [&str; 9] = [
+    "item_created", "item_updated", EVENT_ITEM_FINISHED, "item_deleted", "list_renamed", "member_added", "member_removed",
+    "list_trashed", "list_restored",
+];
+
+/// Responds with every webhook of the list with `list_id`, without their secrets, as long as the user with
+/// `user_id` owns the list.
+pub async fn get_webhooks(list_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::is_list_owner(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_WEBHOOK_NOT_OWNER);
+        }
+
+        match Webhook::find_webhooks_for_list(list_id, conn) {
+            Ok(webhooks) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(webhooks).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_WEBHOOKS_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to register a new webhook for the list with `list_id`, as long as the user with `user_id` owns the
+/// list. Its URL has to be allowed by the `addresses` policy. Responds with the secret that signs its deliveries,
+/// which is the only time it's shown.
+pub async fn create_webhook(list_id: i32, user_id: i32, webhook: WebhookDTO, addresses: AddressPolicy, db: PostgresDbConn) -> ResponseWithStatus {
+    if let Err(message) = check_url(&webhook.url, addresses).await {
+        return ResponseWithStatus::with(Status::BadRequest.code, message);
+    }
+    if !are_valid_events(&webhook.events) {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_WEBHOOK_EVENTS);
+    }
+
+    db.run(move |conn| {
+        if !UserList::is_list_owner(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_WEBHOOK_NOT_OWNER);
+        }
+
+        match Webhook::create_webhook(list_id, webhook, conn) {
+            Ok(created) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_CREATE_WEBHOOK_SUCCESS),
+                    data: serde_json::to_value(created).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_CREATE_WEBHOOK_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to update only the fields present in `patch` for the webhook with `webhook_id` of the list with
+/// `list_id`, as long as the user with `user_id` owns the list. A new URL has to be allowed by the `addresses` policy.
+/// Responds with the updated webhook.
+pub async fn patch_webhook(list_id: i32, webhook_id: i32, user_id: i32, patch: WebhookPatchDTO, addresses: AddressPolicy,
+                           db: PostgresDbConn) -> ResponseWithStatus {
+    if let Some(url) = &patch.url {
+        if let Err(message) = check_url(url, addresses).await {
+            return ResponseWithStatus::with(Status::BadRequest.code, message);
+        }
+    }
+    if matches!(&patch.events, Some(events) if !are_valid_events(events)) {
+        return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_INVALID_WEBHOOK_EVENTS);
+    }
+
+    db.run(move |conn| {
+        if !UserList::is_list_owner(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_WEBHOOK_NOT_OWNER);
+        }
+
+        match Webhook::patch_webhook(webhook_id, list_id, patch, conn) {
+            Ok(webhook) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_UPDATE_SUCCESS),
+                    data: serde_json::to_value(webhook).unwrap(),
+                },
+            },
+            Err(diesel::result::Error::NotFound) => ResponseWithStatus::with(Status::NotFound.code, constants::MESSAGE_WEBHOOK_NOT_FOUND),
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_UPDATE_FAILED),
+        }
+    }).await
+}
+
+/// Attempts to delete the webhook with `webhook_id` of the list with `list_id`, along with its delivery log, as
+/// long as the user with `user_id` owns the list.
+pub async fn delete_webhook(list_id: i32, webhook_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::is_list_owner(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_WEBHOOK_NOT_OWNER);
+        }
+
+        if Webhook::delete_webhook(webhook_id, list_id, conn) {
+            ResponseWithStatus::with(Status::Ok.code, constants::MESSAGE_DELETE_WEBHOOK_SUCCESS)
+        } else {
+            ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_DELETE_WEBHOOK_FAILED)
+        }
+    }).await
+}
+
+/// Responds with the latest deliveries to the webhook with `webhook_id` of the list with `list_id`, newest first,
+/// as long as the user with `user_id` owns the list.
+pub async fn get_deliveries(list_id: i32, webhook_id: i32, user_id: i32, db: PostgresDbConn) -> ResponseWithStatus {
+    db.run(move |conn| {
+        if !UserList::is_list_owner(list_id, user_id, conn) {
+            return ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_WEBHOOK_NOT_OWNER);
+        }
+
+        if Webhook::find_webhook(webhook_id, list_id, conn).is_none() {
+            return ResponseWithStatus::with(Status::NotFound.code, constants::MESSAGE_WEBHOOK_NOT_FOUND);
+        }
+
+        match WebhookDelivery::find_deliveries_for_webhook(webhook_id, DELIVERY_LOG_SIZE, conn) {
+            Ok(deliveries) => ResponseWithStatus {
+                status_code: Status::Ok.code,
+                response: Response {
+                    message: String::from(constants::MESSAGE_OK),
+                    data: serde_json::to_value(deliveries).unwrap(),
+                },
+            },
+            Err(_) => ResponseWithStatus::with(Status::BadRequest.code, constants::MESSAGE_GET_WEBHOOKS_FAILED),
+        }
+    }).await
+}
+
+/// Sends the deliveries in the outbox as they come due, until the server shuts down. Deliveries are claimed
+/// before they are sent, so that every instance of the server can run this at once. Deliveries are only sent to
+/// addresses allowed by the `addresses` policy, which is checked as they're sent, and redirects aren't followed.
+pub async fn deliver_webhooks(addresses: AddressPolicy, pool: PostgresPool) {
+    let client = match delivery_client(addresses) {
+        Ok(client) => client,
+        Err(_) => return,
+    };
+    let mut interval = time::interval(DELIVERY_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        deliver_due_webhooks(&client, addresses, &pool).await;
+    }
+}
+
+/// Builds the client that deliveries are sent with, which only connects to addresses allowed by `addresses`.
+fn delivery_client(addresses: AddressPolicy) -> reqwest::Result<Client> {
+    Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .redirect(Policy::none())
+        .dns_resolver(Arc::new(addresses))
+        .build()
+}
+
+/// Claims the deliveries that are due and sends them with the `client`, recording how each one went. The batch is
+/// sent all at once, and any delivery still waiting on its webhook at `DELIVERY_DEADLINE` is counted as failed, so
+/// the whole batch is done before its lease runs out and another instance claims it again.
+async fn deliver_due_webhooks(client: &Client, addresses: AddressPolicy, pool: &PostgresPool) {
+    let conn = match pool.get().await {
+        Some(conn) => conn,
+        None => return,
+    };
+
+    let deliveries = conn.run(|conn| {
+        WebhookDelivery::claim_due_deliveries(DELIVERY_BATCH_SIZE, DELIVERY_LEASE_SECONDS, conn)
+    }).await;
+
+    let deadline = Instant::now() + DELIVERY_DEADLINE;
+    stream::iter(deliveries.unwrap_or_default())
+        .for_each_concurrent(None, |delivery| {
+            let conn = &conn;
+
+            async move {
+                let outcome = time::timeout_at(deadline, send(client, addresses, &delivery)).await
+                    .unwrap_or_else(|_| Err((None, String::from("the webhook took too long to respond"))));
+                let _ = conn.run(move |conn| record_outcome(&delivery, outcome, conn)).await;
+            }
+        })
+        .await;
+}
+
+/// Posts a delivery to its webhook, signed with the webhook's secret. Returns the status the webhook responded
+/// with if it succeeded, or else the status if there was one and what went wrong. Names are checked against the
+/// `addresses` policy by the client as they're resolved, so only hosts that are addresses are checked here.
+async fn send(client: &Client, addresses: AddressPolicy, delivery: &PendingDelivery) -> Result<i32, (Option<i32>, String)> {
+    let url = Url::parse(&delivery.url).map_err(|error| (None, error.to_string()))?;
+    if !addresses.allows_host_address(&url) {
+        return Err((None, format!("{} isn't a public address", url.host_str().unwrap_or_default())));
+    }
+
+    let response = client.post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Event", &delivery.event_type)
+        .header("X-Webhook-Delivery", delivery.delivery_id.to_string())
+        .header("X-Webhook-Signature", format!("sha256={}", sign(&delivery.secret, &delivery.payload)))
+        .body(delivery.payload.clone())
+        .send()
+        .await
+        .map_err(|error| (None, error.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16() as i32)
+    } else {
+        Err((Some(status.as_u16() as i32), format!("the webhook responded with {}", status)))
+    }
+}
+
+/// Saves the outcome of an attempt at a delivery, scheduling it to be tried again after a wait that doubles with
+/// each attempt, or giving up after `MAX_ATTEMPTS`. A webhook that fails `MAX_CONSECUTIVE_FAILURES` times in a row
+/// is disabled, and the rest of its deliveries are given up on.
+fn record_outcome(delivery: &PendingDelivery, outcome: Result<i32, (Option<i32>, String)>, conn: &PgConnection) -> QueryResult<()> {
+    conn.transaction(|| {
+        match outcome {
+            Ok(response_status) => {
+                WebhookDelivery::mark_succeeded(delivery.delivery_id, response_status, conn)?;
+                Webhook::record_success(delivery.webhook_id, conn)?;
+            }
+            Err((response_status, error)) => {
+                let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+                let attempts = delivery.attempts + 1;
+                let retry_at = if attempts < MAX_ATTEMPTS {
+                    Some(Utc::now() + chrono::Duration::seconds(RETRY_BASE_SECONDS << (attempts - 1)))
+                } else {
+                    None
+                };
+
+                WebhookDelivery::mark_failed(delivery.delivery_id, response_status, &error, retry_at, conn)?;
+
+                if Webhook::record_failure(delivery.webhook_id, MAX_CONSECUTIVE_FAILURES, conn)? {
+                    WebhookDelivery::abandon_pending_deliveries(delivery.webhook_id, "the webhook was disabled after failing repeatedly", conn)?;
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Signs a delivery's payload with the `secret` of its webhook, as the hex HMAC-SHA256 that receivers check.
+fn sign(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+
+    mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Checks that the given URL can be used for a webhook, which needs an absolute http or https URL whose host is
+/// allowed by the `addresses` policy. Returns the reason it can't be used otherwise.
+async fn check_url(url: &str, addresses: AddressPolicy) -> Result<(), &'static str> {
+    let url = match Url::parse(url) {
+        Ok(url) if (url.scheme() == "http" || url.scheme() == "https") && url.has_host() => url,
+        _ => return Err(constants::MESSAGE_INVALID_WEBHOOK_URL),
+    };
+
+    if addresses.allows_url(&url).await {
+        Ok(())
+    } else {
+        Err(constants::MESSAGE_WEBHOOK_URL_NOT_PUBLIC)
+    }
+}
+
+/// Determines whether every one of the given names is a type of event that a webhook can ask for.
+fn are_valid_events(events: &[String]) -> bool {
+    events.iter().all(|name| EVENT_TYPES.contains(&name.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::Mutex;
+
+    use chrono::DateTime;
+    use diesel::prelude::*;
+    use hyper::header::HeaderMap;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Server, StatusCode};
+
+    use super::*;
+    use crate::events::EventBus;
+    use crate::models::event::ListEvent;
+    use crate::models::webhook::{DELIVERY_FAILED, DELIVERY_PENDING, DELIVERY_SUCCEEDED};
+    use crate::schema::webhook_deliveries;
+    use crate::test_support;
+
+    /// A webhook served on this machine, which responds to every request with `status` and keeps what it was sent.
+    struct Receiver {
+        url: String,
+        status: Arc<AtomicU16>,
+        requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+    }
+
+    impl Receiver {
+        async fn start(status: u16) -> Receiver {
+            let status = Arc::new(AtomicU16::new(status));
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            let (responds_with, received) = (status.clone(), requests.clone());
+            let make_service = make_service_fn(move |_| {
+                let (responds_with, received) = (responds_with.clone(), received.clone());
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                        let (responds_with, received) = (responds_with.clone(), received.clone());
+
+                        async move {
+                            let (parts, body) = request.into_parts();
+                            let body = hyper::body::to_bytes(body).await?;
+                            received.lock().unwrap().push((parts.headers, String::from_utf8(body.to_vec()).unwrap()));
+
+                            let mut response = hyper::Response::new(Body::empty());
+                            *response.status_mut() = StatusCode::from_u16(responds_with.load(Ordering::SeqCst)).unwrap();
+                            Ok::<_, hyper::Error>(response)
+                        }
+                    }))
+                }
+            });
+
+            let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+            let url = format!("http://{}/hook", server.local_addr());
+            rocket::tokio::spawn(server);
+
+            Receiver { url, status, requests }
+        }
+    }
+
+    /// Makes a list with a webhook that's sent to `url`, and renames it so that a delivery is queued for the
+    /// webhook. Returns the delivery and the webhook's secret.
+    async fn queue_delivery(url: String, pool: &PostgresPool) -> (WebhookDelivery, String) {
+        pool.get().await.unwrap().run(move |conn| {
+            let owner = test_support::create_user(conn);
+            let list = test_support::create_list("Groceries", owner.id, conn);
+            let created = Webhook::create_webhook(list.list_id, WebhookDTO { url, events: Vec::new() }, conn).unwrap();
+
+            EventBus::new(1).publish(ListEvent::list_renamed(&list), conn).unwrap();
+
+            let mut deliveries = WebhookDelivery::find_deliveries_for_webhook(created.webhook.webhook_id, 2, conn).unwrap();
+            assert_eq!(deliveries.len(), 1);
+
+            (deliveries.remove(0), created.secret)
+        }).await
+    }
+
+    /// Sends the due deliveries until the one with `delivery_id` has been attempted `attempts` times, and returns
+    /// it. Other tests can be holding a claim on it for a moment, so it's sent again until it's been attempted.
+    async fn deliver_until_attempted(delivery_id: i32, attempts: i32, pool: &PostgresPool) -> WebhookDelivery {
+        let addresses = AddressPolicy { allow_private: true };
+        let client = delivery_client(addresses).unwrap();
+        let started_at = Instant::now();
+
+        loop {
+            assert!(started_at.elapsed() < Duration::from_secs(10), "the delivery wasn't attempted");
+            deliver_due_webhooks(&client, addresses, pool).await;
+
+            let delivery = pool.get().await.unwrap().run(move |conn| {
+                webhook_deliveries::table.find(delivery_id).first::<WebhookDelivery>(conn)
+            }).await.unwrap();
+
+            if delivery.attempts >= attempts {
+                return delivery;
+            }
+
+            time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Makes the delivery with `delivery_id` due at `next_attempt_at`.
+    fn reschedule(delivery_id: i32, next_attempt_at: DateTime<Utc>, conn: &PgConnection) {
+        diesel::update(webhook_deliveries::table.find(delivery_id))
+            .set(webhook_deliveries::next_attempt_at.eq(next_attempt_at))
+            .execute(conn)
+            .unwrap();
+    }
+
+    /// Checks that `retry_at` is the wait after a failed attempt later than `attempted_at`, give or take a second.
+    fn assert_retried_after(retry_at: DateTime<Utc>, attempted_at: DateTime<Utc>, wait_seconds: i64) {
+        let wait = (retry_at - attempted_at).num_seconds();
+        assert!((wait_seconds - 1..=wait_seconds + 1).contains(&wait), "retried after {}s instead of {}s", wait, wait_seconds);
+    }
+
+    #[test]
+    fn claimed_batches_are_sent_within_their_lease() {
+        assert!(DELIVERY_TIMEOUT < DELIVERY_DEADLINE);
+        assert!(DELIVERY_DEADLINE < Duration::from_secs(DELIVERY_LEASE_SECONDS as u64));
+    }
+
+    #[test]
+    fn payloads_are_signed_with_hmac_sha256() {
+        assert_eq!(
+            sign("key", "The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+        );
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn deliveries_are_sent_signed_with_the_webhooks_secret() {
+        let pool = test_support::pool().await;
+        let receiver = Receiver::start(204).await;
+        let (delivery, secret) = queue_delivery(receiver.url.clone(), &pool).await;
+
+        let delivery = deliver_until_attempted(delivery.delivery_id, 1, &pool).await;
+        assert_eq!(delivery.status, DELIVERY_SUCCEEDED);
+        assert_eq!(delivery.response_status, Some(204));
+
+        let requests = receiver.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+
+        let (headers, body) = &requests[0];
+        assert_eq!(body, &delivery.payload);
+        assert_eq!(headers["X-Webhook-Event"], "list_renamed");
+        assert_eq!(headers["X-Webhook-Delivery"], delivery.delivery_id.to_string().as_str());
+        assert_eq!(headers["X-Webhook-Signature"], format!("sha256={}", sign(&secret, body)).as_str());
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn failed_deliveries_are_retried_with_backoff_until_they_give_up() {
+        let pool = test_support::pool().await;
+        let receiver = Receiver::start(500).await;
+        let (delivery, _) = queue_delivery(receiver.url.clone(), &pool).await;
+        let delivery_id = delivery.delivery_id;
+
+        let delivery = deliver_until_attempted(delivery_id, 1, &pool).await;
+        assert_eq!(delivery.status, DELIVERY_PENDING);
+        assert_eq!(delivery.response_status, Some(500));
+        assert_eq!(delivery.error.as_deref(), Some("the webhook responded with 500 Internal Server Error"));
+        assert_retried_after(delivery.next_attempt_at, delivery.last_attempt_at.unwrap(), RETRY_BASE_SECONDS);
+
+        pool.get().await.unwrap().run(move |conn| reschedule(delivery_id, Utc::now(), conn)).await;
+        let delivery = deliver_until_attempted(delivery_id, 2, &pool).await;
+        assert_eq!(delivery.status, DELIVERY_PENDING);
+        assert_retried_after(delivery.next_attempt_at, delivery.last_attempt_at.unwrap(), RETRY_BASE_SECONDS * 2);
+
+        pool.get().await.unwrap().run(move |conn| {
+            diesel::update(webhook_deliveries::table.find(delivery_id))
+                .set(webhook_deliveries::attempts.eq(MAX_ATTEMPTS - 1))
+                .execute(conn)
+                .unwrap();
+            reschedule(delivery_id, Utc::now(), conn);
+        }).await;
+        let delivery = deliver_until_attempted(delivery_id, MAX_ATTEMPTS, &pool).await;
+        assert_eq!(delivery.status, DELIVERY_FAILED);
+        assert_eq!(receiver.requests.lock().unwrap().len(), 3);
+
+        // A delivery that was given up on isn't sent again, even once the webhook works
+        receiver.status.store(200, Ordering::SeqCst);
+        pool.get().await.unwrap().run(move |conn| reschedule(delivery_id, Utc::now(), conn)).await;
+        deliver_until_attempted(delivery_id, MAX_ATTEMPTS, &pool).await;
+        assert_eq!(receiver.requests.lock().unwrap().len(), 3);
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a database in TEST_DATABASE_URL"]
+    async fn claimed_deliveries_arent_claimed_again_until_their_lease_runs_out() {
+        let pool = test_support::pool().await;
+        let (delivery, _) = queue_delivery(String::from("http://127.0.0.1:9/hook"), &pool).await;
+        let delivery_id = delivery.delivery_id;
+
+        pool.get().await.unwrap().run(move |conn| conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let claims = |conn| WebhookDelivery::claim_due_deliveries(i64::from(i32::MAX), DELIVERY_LEASE_SECONDS, conn)
+                .map(|claimed| claimed.iter().any(|delivery| delivery.delivery_id == delivery_id));
+
+            assert!(claims(conn)?);
+            assert!(!claims(conn)?);
+
+            // The time the transaction started is used as the time now, so the lease is ended well before it
+            reschedule(delivery_id, Utc::now() - chrono::Duration::seconds(DELIVERY_LEASE_SECONDS * 2), conn);
+            assert!(claims(conn)?);
+
+            Ok(())
+        })).await;
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::storage::{Storage, StoredObject};
+
+/// Stores attachment files as plain files in a single directory on the local disk.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Creates a `LocalStorage` that keeps its files in the `root` directory, creating it if necessary.
+    pub fn new<P: Into<PathBuf>>(root: P) -> io::Result<LocalStorage> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+
+        Ok(LocalStorage { root })
+    }
+
+    /// Finds the path for the given `key`, refusing anything that could escape the root directory.
+    fn path_for(&self, key: &str) -> io::Result<PathBuf> {
+        let is_plain_name = !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if is_plain_name {
+            Ok(self.root.join(key))
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid storage key"))
+        }
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, key: &str, contents: &[u8]) -> io::Result<()> {
+        fs::write(self.path_for(key)?, contents)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path_for(key)?)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path_for(key)?) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn list(&self) -> io::Result<Vec<StoredObject>> {
+        let mut objects = vec![];
+
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+
+            if let (true, Some(key)) = (metadata.is_file(), entry.file_name().to_str()) {
+                objects.push(StoredObject {
+                    key: String::from(key),
+                    last_modified: metadata.modified()?,
+                });
+            }
+        }
+
+        Ok(objects)
+    }
+}
@@ -0,0 +1,27 @@
+use std::io;
+use std::time::SystemTime;
+
+pub mod local;
+
+/// A file that has been written to a storage backend.
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: SystemTime,
+}
+
+/// A place that attachment files can be kept, addressed by an opaque key. Rows in the attachments
+/// table only hold the key, so the backend can be swapped out (eg for an S3-compatible bucket)
+/// without touching the database.
+pub trait Storage: Send + Sync {
+    /// Writes `contents` under the given `key`, replacing anything already there.
+    fn put(&self, key: &str, contents: &[u8]) -> io::Result<()>;
+
+    /// Reads back the contents stored under the given `key`.
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+
+    /// Removes the contents stored under the given `key`. Removing a missing key is not an error.
+    fn delete(&self, key: &str) -> io::Result<()>;
+
+    /// Lists every object currently in the backend.
+    fn list(&self) -> io::Result<Vec<StoredObject>>;
+}
@@ -0,0 +1,139 @@
+//! Helpers for the tests that run against a real database. They need a migrated Postgres database named by
+//! `TEST_DATABASE_URL`, so they're ignored unless run with `cargo test -- --ignored`.
+
+use std::time::Duration;
+
+use diesel::PgConnection;
+use rocket::{Ignite, Rocket};
+use rocket::tokio::sync::broadcast::Receiver;
+use rocket::tokio::time;
+use uuid::Uuid;
+
+use crate::database::{PostgresDbConn, PostgresPool};
+use crate::events::EventBus;
+use crate::models::event::ListEvent;
+use crate::models::item::{Item, ItemDTO};
+use crate::models::list::{List, ListDTO};
+use crate::models::user::{User, UserDTO};
+use crate::models::user_lists::UserList;
+
+pub static PASSWORD: &str = "correct horse battery staple"; // Password of every user made by `create_user`
+
+/// Gets the URL of the test database.
+pub fn database_url() -> String {
+    std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must name a migrated Postgres database")
+}
+
+/// Ignites a server with only the test database attached, which routes' connections can be taken from.
+pub async fn rocket() -> Rocket<Ignite> {
+    let figment = rocket::Config::figment().merge(("databases.postgres_db.url", database_url()));
+    rocket::custom(figment)
+        .attach(PostgresDbConn::fairing())
+        .ignite().await
+        .unwrap()
+}
+
+/// Opens a pool of connections to the test database in the same way the server does.
+pub async fn pool() -> PostgresPool {
+    PostgresDbConn::pool(&rocket().await).cloned().unwrap()
+}
+
+/// Signs up a new user with a unique username and `PASSWORD`.
+pub fn create_user(conn: &PgConnection) -> User {
+    // Usernames and emails can only be 30 characters long
+    let username = format!("test-{}", &Uuid::new_v4().to_simple().to_string()[..12]);
+    let user = UserDTO {
+        username: username.clone(),
+        email: format!("{}@example.com", username),
+        preferred_name: String::from("Test"),
+        password: String::from(PASSWORD),
+    };
+
+    assert!(User::signup(user, conn));
+    User::find_user_by_username(&username, conn).unwrap()
+}
+
+/// Creates a list called `name` that's owned by the user with `user_id`.
+pub fn create_list(name: &str, user_id: i32, conn: &PgConnection) -> List {
+    let list = List::create_list(ListDTO { name: String::from(name), description: None, uuid: None }, conn).unwrap();
+    let list_id = list.list_id;
+
+    add_member(List::find_list_by_id(list_id, conn).unwrap(), user_id, true, conn);
+    List::find_list_by_id(list_id, conn).unwrap()
+}
+
+/// Creates an unfinished item described as `description` in the list with `list_id`.
+pub fn create_item(description: &str, list_id: i32, conn: &PgConnection) -> Item {
+    let item = ItemDTO {
+        description: String::from(description),
+        finished: false,
+        notes: None,
+        assignee_id: None,
+        due_at: None,
+        priority: None,
+        recurrence: None,
+        uuid: None,
+    };
+
+    Item::insert_item(item, list_id, conn).unwrap()
+}
+
+/// Gives the user with `user_id` access to the `list`.
+pub fn add_member(list: List, user_id: i32, is_owner: bool, conn: &PgConnection) {
+    assert!(UserList::associate_list(list, User::find_user_by_id(user_id, conn).unwrap(), is_owner, conn));
+}
+
+/// Makes an event bus that relays the events published on the test database, and subscribes to it once it's
+/// listening, so that nothing published afterwards is missed.
+pub async fn listening_bus(pool: &PostgresPool) -> (EventBus, Receiver<ListEvent>) {
+    listening_bus_on(database_url(), pool).await
+}
+
+/// Makes an event bus that relays the events published on the database at `url`, and subscribes to it once it's
+/// listening.
+pub async fn listening_bus_on(url: String, pool: &PostgresPool) -> (EventBus, Receiver<ListEvent>) {
+    let events = EventBus::new(1024);
+    let mut receiver = events.subscribe();
+    rocket::tokio::spawn(events.clone().listen(url, pool.clone()));
+
+    wait_until_listening(&events, &mut receiver, pool).await;
+    (events, receiver)
+}
+
+/// Waits until the `events` are being relayed to the `receiver`. Events published before the bus is listening
+/// are lost, so one is published over and over until it comes back.
+pub async fn wait_until_listening(events: &EventBus, receiver: &mut Receiver<ListEvent>, pool: &PostgresPool) {
+    let probe_uuid = Uuid::new_v4();
+    let started_at = time::Instant::now();
+
+    loop {
+        assert!(started_at.elapsed() < Duration::from_secs(10), "the event bus didn't start listening");
+
+        let publisher = events.clone();
+        pool.get().await.unwrap().run(move |conn| {
+            publisher.publish(ListEvent::ListRenamed { list_id: 0, list_uuid: probe_uuid, name: String::from("probe") }, conn)
+        }).await;
+
+        while let Ok(Ok(event)) = time::timeout(Duration::from_millis(200), receiver.recv()).await {
+            if matches!(event, ListEvent::ListRenamed { list_uuid, .. } if list_uuid == probe_uuid) {
+                return;
+            }
+        }
+    }
+}
+
+/// Waits for the next `count` events about the list with `list_id`, skipping those about other lists.
+pub async fn next_events(receiver: &mut Receiver<ListEvent>, list_id: i32, count: usize) -> Vec<ListEvent> {
+    let mut events = Vec::new();
+    while events.len() < count {
+        let event = time::timeout(Duration::from_secs(5), receiver.recv()).await
+            .expect("timed out waiting for an event")
+            .unwrap();
+
+        if event.list_id() == list_id {
+            events.push(event);
+        }
+    }
+
+    events
+}